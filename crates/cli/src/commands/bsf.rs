@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
+use wptreport::bsf::{browser_specific_failures, BsfReference};
+use wptreport::Error;
+
+use crate::compression::read_any_report;
+
+#[derive(Clone, Debug, Default, Parser)]
+#[clap(name = "bsf")]
+pub struct Bsf {
+    /// Read the report to find browser-specific failures in from REPORT. Either a wptreport,
+    /// a Servo scores file or a wpt.fyi summary file.
+    report: PathBuf,
+
+    /// Read a report from another browser from REFERENCE. Either a wptreport, a Servo scores
+    /// file or a wpt.fyi summary file. May be given more than once.
+    #[arg(long = "reference", required = true)]
+    references: Vec<PathBuf>,
+
+    /// Output the scores for every area and the contributing tests as JSON to OUT
+    #[arg(long)]
+    out: Option<PathBuf>,
+
+    /// The number of contributing tests to print
+    #[arg(long, default_value_t = 20)]
+    top: usize,
+}
+
+impl Bsf {
+    pub fn run(self) -> Result<(), Error> {
+        let start = Instant::now();
+
+        let report = read_any_report(&self.report)?;
+        let references = self
+            .references
+            .par_iter()
+            .map(|path| read_any_report(path).map(|report| BsfReference::new(&report)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let read_time = start.elapsed().as_millis();
+
+        let summary = browser_specific_failures(&report, &references);
+        if let Some(out) = &self.out {
+            let summary_str = serde_json::to_string(&summary).unwrap();
+            fs::write(out, summary_str).map_err(|err| Error::from(err).with_file(out))?;
+        }
+
+        for (area, scores) in &summary.areas {
+            let slash_count = area.chars().filter(|c| *c == '/').count();
+            if slash_count < 2 {
+                println!("{area}: {:.2} ({} tests)", scores.score, scores.tests);
+            }
+        }
+        let mut top_tests: Vec<_> = summary.tests.iter().collect();
+        top_tests.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.test.cmp(&b.test)));
+        if !top_tests.is_empty() {
+            println!("====================");
+        }
+        for test in top_tests.iter().take(self.top) {
+            println!("{:.2} {}", test.score, test.test);
+        }
+
+        let grand_total_time = start.elapsed().as_millis();
+        println!("====================");
+        println!("Done in {grand_total_time}ms (read in {read_time}ms)");
+
+        Ok(())
+    }
+}
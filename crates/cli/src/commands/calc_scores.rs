@@ -1,26 +1,31 @@
 use std::collections::BTreeMap;
 use std::fs::{self, read_dir};
+use std::io::BufRead as _;
 use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 
-use clap::Parser;
-use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
-use serde::de::DeserializeOwned;
+use clap::{Parser, ValueEnum};
+use rayon::iter::{IntoParallelIterator as _, IntoParallelRefIterator as _, ParallelIterator as _};
 use serde::{Deserialize, Serialize};
-use wptreport::reports::servo_test_scores::WptScores;
-use wptreport::score_summary::FocusArea;
+use wptreport::any_report::AnyReport;
+use wptreport::manifest::Manifest;
+use wptreport::score_summary::{FocusArea, ScoringMethod};
+use wptreport::scores_history::{ScoresHistory, MAGIC};
 use wptreport::summarize::{summarize_results, RunInfoWithScores};
-use wptreport::wpt_report::{WptReport, WptRunInfo};
-use wptreport::{score_wpt_report, AreaScores, HasRunInfo, ScorableReport};
+use wptreport::wpt_report::WptRunInfo;
+use wptreport::wpt_report_stream::StreamingReport;
+use wptreport::{score_wpt_report, score_wpt_report_against, AreaScores, Error, HasRunInfo};
 
-use crate::compression::read_maybe_compressed_file;
+use crate::compression::{open_maybe_compressed_file, read_any_report, read_report, stream_report};
 
 #[derive(Clone, Debug, Default, Parser)]
 #[clap(name = "calc-scores")]
 pub struct CalcScores {
-    /// Read report files from IN
+    /// Read report files from IN: a wptreport file, a directory of wptreport, Servo scores or
+    /// wpt.fyi summary files, or a packed history of scores (see the pack command). Each
+    /// report in a directory is scored against the most recent one.
     #[arg(long)]
     r#in: PathBuf,
 
@@ -31,6 +36,33 @@ pub struct CalcScores {
     /// Read focus areas from FOCUS_AREAS
     #[arg(long)]
     focus_areas: Option<PathBuf>,
+
+    /// Score tests in the WPT MANIFEST.json at MANIFEST that are missing from the report
+    /// as failures (only when IN is a file)
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Extra scores to include in the score summary (only when IN is a directory or packed
+    /// history). The servo and subtest ratio scores are always included.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    scoring: Vec<Scoring>,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum Scoring {
+    /// The mean of each test's passing subtests per 1000, as used by Interop
+    Interop,
+    /// The number of tests where every subtest passes
+    TestsPassed,
+}
+
+impl From<Scoring> for ScoringMethod {
+    fn from(scoring: Scoring) -> Self {
+        match scoring {
+            Scoring::Interop => ScoringMethod::Interop,
+            Scoring::TestsPassed => ScoringMethod::TestsPassed,
+        }
+    }
 }
 
 fn as_percent(amount: u32, out_of: u32) -> f32 {
@@ -38,21 +70,42 @@ fn as_percent(amount: u32, out_of: u32) -> f32 {
 }
 
 impl CalcScores {
-    pub fn run(self) {
+    pub fn run(self) -> Result<(), Error> {
         let in_path_buf = self.r#in;
         let in_path = &in_path_buf;
 
+        let methods: Vec<ScoringMethod> = self.scoring.iter().map(|&s| s.into()).collect();
         let start = Instant::now();
 
-        if in_path_buf.is_file() {
+        if in_path_buf.is_file() && is_scores_history(in_path)? {
+            let focus_areas: Option<Vec<FocusArea>> =
+                self.focus_areas.as_deref().map(read_report).transpose()?;
+
+            let scores = score_history(in_path)?;
+            write_score_summary(&self.out, &scores, focus_areas.as_deref(), &methods)?;
+
+            let grand_total_time = start.elapsed().as_millis();
+            println!("====================");
+            println!("Processed all runs in {grand_total_time}ms");
+        } else if in_path_buf.is_file() {
             fn is_focus_area(area: &str) -> bool {
                 let slash_count = area.chars().filter(|c| *c == '/').count();
                 slash_count < 2 || (slash_count == 2 && area.starts_with("css/CSS2"))
             }
 
-            let result = score_report::<WptReport>(in_path).unwrap();
+            let manifest: Option<Manifest> = self
+                .manifest
+                .as_deref()
+                .map(|path| {
+                    let reader = open_maybe_compressed_file(path)?;
+                    Manifest::from_reader(reader).map_err(|err| err.with_file(path))
+                })
+                .transpose()?;
+
+            let result = score_report_stream(in_path, manifest.as_ref())?;
             let result_json = serde_json::to_string(&result).unwrap();
-            fs::write(self.out, result_json).unwrap();
+            fs::write(&self.out, result_json)
+                .map_err(|err| Error::from(err).with_file(&self.out))?;
 
             for (area, scores) in result.scores_by_area {
                 if !is_focus_area(&area) {
@@ -76,69 +129,131 @@ impl CalcScores {
                 result.score_time
             );
         } else if in_path_buf.is_dir() {
-            let focus_areas = self.focus_areas.as_ref().map(|path| {
-                let focus_areas_json = fs::read_to_string(path).unwrap();
-                let focus_areas: Vec<FocusArea> = serde_json::from_str(&focus_areas_json).unwrap();
-                focus_areas
-            });
-
-            let dir_entries = read_dir(&in_path_buf).unwrap();
-
-            let mut file_paths: Vec<_> = dir_entries
-                .flatten()
-                .filter(|entry| entry.metadata().unwrap().is_file())
-                .map(|entry| entry.path())
-                .filter(|path| path.file_name().is_some_and(|p| p.as_bytes()[0] != b'.'))
-                .collect();
+            let focus_areas: Option<Vec<FocusArea>> =
+                self.focus_areas.as_deref().map(read_report).transpose()?;
+
+            let dir_entries =
+                read_dir(&in_path_buf).map_err(|err| Error::from(err).with_file(in_path))?;
+
+            let mut file_paths = Vec::new();
+            for entry in dir_entries.flatten() {
+                let path = entry.path();
+                let metadata = entry
+                    .metadata()
+                    .map_err(|err| Error::from(err).with_file(&path))?;
+                if metadata.is_file() && path.file_name().is_some_and(|p| p.as_bytes()[0] != b'.') {
+                    file_paths.push(path);
+                }
+            }
             file_paths.sort();
 
             // Load most recent report
             let Some(latest_report_path) = file_paths.last() else {
                 println!("No files found");
-                return;
+                return Ok(());
             };
-            let latest_report_str = read_maybe_compressed_file(latest_report_path);
-            let latest_report: WptScores = serde_json::from_str(&latest_report_str).ok().unwrap();
+            let latest_report = read_any_report(latest_report_path)?;
 
             let count = file_paths.len();
             let i = AtomicU64::new(0);
-            let scores: Vec<_> = file_paths
+            let scores = file_paths
                 .par_iter()
-                .filter_map(|file_path| {
-                    let result =
-                        score_report_against_reference::<WptScores>(file_path, &latest_report)
-                            .unwrap();
-                    let file_name = file_path.file_name().unwrap().to_str().unwrap();
+                .map(|file_path| {
+                    let result = score_report_against_reference(file_path, &latest_report)?;
+                    let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
                     let i = i.fetch_add(1, Ordering::SeqCst) + 1;
                     println!(
                         "[{i}/{count}] Processed {file_name} in {}ms (read in {}ms; Scored in {}ms)",
                         result.total_time, result.read_time, result.score_time
                     );
 
-                    let date = file_name[0..10].to_string();
-                    Some(RunInfoWithScores {
+                    let date = file_name.get(0..10).unwrap_or(&file_name).to_string();
+                    Ok(RunInfoWithScores {
                         date,
                         info: result.run_info,
                         scores: result.scores_by_area,
                     })
                 })
-                .collect();
+                .collect::<Result<Vec<_>, Error>>()?;
 
-            // Write scores.json file
-            let score_summary =
-                summarize_results(&scores, focus_areas.as_ref().map(|areas| &**areas));
-            let score_summary_str = serde_json::to_string(&score_summary).unwrap();
-            fs::write(self.out, score_summary_str).unwrap();
+            write_score_summary(&self.out, &scores, focus_areas.as_deref(), &methods)?;
 
             let grand_total_time = start.elapsed().as_secs();
             println!("====================");
             println!("Processed all files in {grand_total_time}s");
         } else {
-            panic!("{} is not a file or directory", in_path.display());
+            let err = std::io::Error::other("not a file or directory");
+            return Err(Error::from(err).with_file(in_path));
         }
+
+        Ok(())
     }
 }
 
+/// Write a scores.json file summarising `runs`
+fn write_score_summary(
+    out: &Path,
+    runs: &[RunInfoWithScores],
+    focus_areas: Option<&[FocusArea]>,
+    methods: &[ScoringMethod],
+) -> Result<(), Error> {
+    let score_summary = summarize_results(runs, focus_areas, methods);
+    let score_summary_str = serde_json::to_string(&score_summary).unwrap();
+    fs::write(out, score_summary_str).map_err(|err| Error::from(err).with_file(out))
+}
+
+/// Whether a (possibly compressed) file is a packed scores history (see the `pack` command)
+fn is_scores_history(file_path: &Path) -> Result<bool, Error> {
+    let mut reader = open_maybe_compressed_file(file_path)?;
+    let start = reader
+        .fill_buf()
+        .map_err(|err| Error::from(err).with_file(file_path))?;
+    Ok(start.starts_with(MAGIC))
+}
+
+/// Score every run in a packed scores history against its most recent run
+fn score_history(file_path: &Path) -> Result<Vec<RunInfoWithScores>, Error> {
+    let read_start = Instant::now();
+    let reader = open_maybe_compressed_file(file_path)?;
+    let history = ScoresHistory::from_reader(reader).map_err(|err| err.with_file(file_path))?;
+    let Some(latest_run) = history.len().checked_sub(1) else {
+        println!("No runs found");
+        return Ok(Vec::new());
+    };
+    let reference = history
+        .reference(latest_run)
+        .map_err(|err| err.with_file(file_path))?;
+    println!(
+        "Read {} runs in {}ms",
+        history.len(),
+        read_start.elapsed().as_millis()
+    );
+
+    let count = history.len();
+    let i = AtomicU64::new(0);
+    (0..count)
+        .into_par_iter()
+        .map(|run| {
+            let score_start = Instant::now();
+            let scores = history
+                .score_against(run, &reference)
+                .map_err(|err| err.with_file(file_path))?;
+            let label = history.label(run);
+            let i = i.fetch_add(1, Ordering::SeqCst) + 1;
+            println!(
+                "[{i}/{count}] Scored {label} in {}ms",
+                score_start.elapsed().as_millis()
+            );
+
+            Ok(RunInfoWithScores {
+                date: label.get(0..10).unwrap_or(label).to_string(),
+                info: history.run_info(run).clone(),
+                scores,
+            })
+        })
+        .collect()
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ScoreResult {
     scores_by_area: BTreeMap<String, AreaScores>,
@@ -148,54 +263,61 @@ pub struct ScoreResult {
     total_time: u128,
 }
 
-pub fn score_report_against_reference<T>(
+/// Score a wptreport, Servo scores or wpt.fyi summary file against a reference
+pub fn score_report_against_reference(
     file_path: &Path,
-    reference: &WptScores,
-) -> Option<ScoreResult>
-where
-    T: DeserializeOwned,
-    WptScores: From<T>,
-{
+    reference: &AnyReport,
+) -> Result<ScoreResult, Error> {
     let read_start = Instant::now();
 
-    let report_str = read_maybe_compressed_file(file_path);
-    let report: T = serde_json::from_str(&report_str).ok()?;
-    let scores = WptScores::from(report);
+    let report = read_any_report(file_path)?;
 
     let read_elapsed = read_start.elapsed().as_millis();
 
     let score_start = Instant::now();
-    let scores_by_area = scores.score_against(reference);
+    let scores_by_area = match (&report, reference) {
+        // Scores files can look tests up by name without indexing them first
+        (AnyReport::WptScores(scores), AnyReport::WptScores(reference)) => {
+            scores.score_against(reference)
+        }
+        (report, reference) => score_wpt_report_against(report, reference),
+    };
     let score_elapsed = score_start.elapsed().as_millis();
     let total_elapsed = read_start.elapsed().as_millis();
 
-    Some(ScoreResult {
+    Ok(ScoreResult {
         scores_by_area,
-        run_info: scores.run_info,
+        run_info: report.run_info().clone(),
         read_time: read_elapsed,
         score_time: score_elapsed,
         total_time: total_elapsed,
     })
 }
 
-pub fn score_report<T: DeserializeOwned + ScorableReport + HasRunInfo>(
+/// Score a wptreport file, streaming results from disk so that the full report is never
+/// held in memory. As reading and scoring are interleaved, `read_time` only covers reading
+/// the fields that precede the results. If a manifest is given then tests which are missing
+/// from the report are scored as failures.
+pub fn score_report_stream(
     file_path: &Path,
-) -> Option<ScoreResult> {
+    manifest: Option<&Manifest>,
+) -> Result<ScoreResult, Error> {
     let read_start = Instant::now();
-
-    let report_str = read_maybe_compressed_file(file_path);
-    let report: T = serde_json::from_str(&report_str).ok()?;
-
+    let report = StreamingReport::new(stream_report(file_path)?);
     let read_elapsed = read_start.elapsed().as_millis();
 
     let score_start = Instant::now();
-    let scores_by_area = score_wpt_report(&report);
+    let scores_by_area = match manifest {
+        Some(manifest) => score_wpt_report(&manifest.fill_missing(&report)),
+        None => score_wpt_report(&report),
+    };
+    let header = report.finish().map_err(|err| err.with_file(file_path))?;
     let score_elapsed = score_start.elapsed().as_millis();
     let total_elapsed = read_start.elapsed().as_millis();
 
-    Some(ScoreResult {
+    Ok(ScoreResult {
         scores_by_area,
-        run_info: report.run_info().clone(),
+        run_info: header.run_info,
         read_time: read_elapsed,
         score_time: score_elapsed,
         total_time: total_elapsed,
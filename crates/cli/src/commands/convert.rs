@@ -2,11 +2,33 @@ use std::fs;
 use std::path::PathBuf;
 use std::time::Instant;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use wptreport::mozlog::read_mozlog;
 use wptreport::servo_test_scores::WptScores;
-use wptreport::wpt_report::WptReport;
+use wptreport::wpt_fyi_summary::WptFyiSummary;
+use wptreport::Error;
 
-use crate::compression::read_maybe_compressed_file;
+use crate::compression::{open_maybe_compressed_file, stream_report};
+
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+pub enum InputFormat {
+    /// The standard wptreport JSON format
+    #[default]
+    Wptreport,
+    /// A mozlog structured log (as produced by wptrunner's --log-raw)
+    Mozlog,
+}
+
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// The standard wptreport JSON format
+    Wptreport,
+    /// Servo's cut-down scores format
+    #[default]
+    ServoScores,
+    /// wpt.fyi's compact summary format (summary_v2)
+    WptFyiSummary,
+}
 
 #[derive(Clone, Debug, Default, Parser)]
 #[clap(name = "merge")]
@@ -19,35 +41,71 @@ pub struct Convert {
     #[arg(long)]
     out: PathBuf,
 
+    /// The format of the input file
+    #[arg(long, value_enum, default_value_t)]
+    from: InputFormat,
+
+    /// The format of the output file
+    #[arg(long, value_enum, default_value_t)]
+    to: OutputFormat,
+
     /// Sort tests in JS object iteration order
     #[arg(long)]
     js_sort: bool,
 }
 
 impl Convert {
-    pub fn run(self) {
+    pub fn run(self) -> Result<(), Error> {
         let in_path = self.r#in;
         let start = Instant::now();
 
-        // Read file
-        let read_start = Instant::now();
-        let report_str = read_maybe_compressed_file(&in_path);
-        let wpt_report: WptReport = serde_json::from_str(&report_str).unwrap();
-        let read_elapsed = read_start.elapsed().as_millis();
-        println!("Read and decompress report in {read_elapsed}ms");
-
+        // Read file (wptreport results are converted as they are streamed in)
         let convert_start = Instant::now();
-        let mut servo_scores_report = WptScores::from(wpt_report);
+        let out_str = match (self.from, self.to) {
+            (InputFormat::Wptreport, OutputFormat::ServoScores) => {
+                let wpt_report = stream_report(&in_path)?;
+                let mut servo_scores_report =
+                    WptScores::try_from(wpt_report).map_err(|err| err.with_file(&in_path))?;
+                if self.js_sort {
+                    servo_scores_report.apply_javascript_key_sort();
+                }
+                serde_json::to_string(&servo_scores_report).unwrap()
+            }
+            (InputFormat::Wptreport, OutputFormat::Wptreport) => {
+                let wpt_report = stream_report(&in_path)?
+                    .into_report()
+                    .map_err(|err| err.with_file(&in_path))?;
+                serde_json::to_string(&wpt_report).unwrap()
+            }
+            (InputFormat::Wptreport, OutputFormat::WptFyiSummary) => {
+                let wpt_report = stream_report(&in_path)?
+                    .into_report()
+                    .map_err(|err| err.with_file(&in_path))?;
+                serde_json::to_string(&WptFyiSummary::from(wpt_report)).unwrap()
+            }
+            (InputFormat::Mozlog, to) => {
+                let reader = open_maybe_compressed_file(&in_path)?;
+                let wpt_report = read_mozlog(reader).map_err(|err| err.with_file(&in_path))?;
+                match to {
+                    OutputFormat::Wptreport => serde_json::to_string(&wpt_report).unwrap(),
+                    OutputFormat::WptFyiSummary => {
+                        serde_json::to_string(&WptFyiSummary::from(wpt_report)).unwrap()
+                    }
+                    OutputFormat::ServoScores => {
+                        let mut servo_scores_report = WptScores::from(wpt_report);
+                        if self.js_sort {
+                            servo_scores_report.apply_javascript_key_sort();
+                        }
+                        serde_json::to_string(&servo_scores_report).unwrap()
+                    }
+                }
+            }
+        };
         let convert_elapsed = convert_start.elapsed().as_millis();
-        println!("Converted report to servo wpt scores format in {convert_elapsed}ms");
-
-        if self.js_sort {
-            servo_scores_report.apply_javascript_key_sort();
-        }
+        println!("Read and converted report in {convert_elapsed}ms");
 
         let write_start = Instant::now();
-        let servo_scores_report_str = serde_json::to_string(&servo_scores_report).unwrap();
-        fs::write(&self.out, servo_scores_report_str).unwrap();
+        fs::write(&self.out, out_str).map_err(|err| Error::from(err).with_file(&self.out))?;
         let write_elapsed = write_start.elapsed().as_millis();
         println!("Wrote report in {write_elapsed}ms");
 
@@ -55,5 +113,7 @@ impl Convert {
         let out_file_name = self.out.display();
         println!("====================");
         println!("Wrote merged report to {out_file_name} in {grand_total_time}ms");
+
+        Ok(())
     }
 }
@@ -2,50 +2,72 @@ use std::path::PathBuf;
 use std::time::Instant;
 
 use clap::Parser;
-use wptreport::aggregate::aggregate;
-use wptreport::wpt_report::WptReport;
+use wptreport::aggregate::{diff, DiffStatus};
+use wptreport::wpt_report::TestStatus;
+use wptreport::Error;
 
-use crate::compression::read_maybe_compressed_file;
+use crate::compression::read_any_report;
 
 #[derive(Clone, Debug, Default, Parser)]
 #[clap(name = "diff")]
 pub struct Diff {
-    /// Read report file from FILE_A
+    /// Read report file from FILE_A. Either a wptreport, a Servo scores file or a wpt.fyi
+    /// summary file. If either file isn't a wptreport then tests are only compared as PASS
+    /// or FAIL.
     file_a: PathBuf,
 
-    /// Read report file from FILE_B
+    /// Read report file from FILE_B. Either a wptreport, a Servo scores file or a wpt.fyi
+    /// summary file.
     file_b: PathBuf,
+
+    /// Print the tests that differ as JSON
+    #[arg(long)]
+    json: bool,
 }
 
 impl Diff {
-    pub fn run(self) {
+    pub fn run(self) -> Result<(), Error> {
         let start = Instant::now();
 
         // Read files
-        let report_str = read_maybe_compressed_file(&self.file_a);
-        let report_a: WptReport = serde_json::from_str(&report_str).unwrap();
-        let report_str = read_maybe_compressed_file(&self.file_b);
-        let report_b: WptReport = serde_json::from_str(&report_str).unwrap();
+        let report_a = read_any_report(&self.file_a)?;
+        let report_b = read_any_report(&self.file_b)?;
 
         // Diff and print results
-        aggregate(&mut [report_a, report_b], |results| {
-            let a = results[0];
-            let b = results[1];
-
-            match (a, b) {
+        let diffs: Vec<_> = diff(&report_a, &report_b)
+            .into_iter()
+            .filter(|test| test.status != DiffStatus::Same)
+            .collect();
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&diffs).unwrap());
+            return Ok(());
+        }
+        for test in diffs {
+            match (&test.before, &test.after) {
                 (None, None) => unreachable!(),
-                (Some(test), None) => println!("REM  {}", test.test),
-                (None, Some(test)) => println!("ADD  {}", test.test),
+                (Some(_), None) => println!("REM  {}", test.test),
+                (None, Some(_)) => println!("ADD  {}", test.test),
                 (Some(a), Some(b)) => {
-                    if a.status != b.status {
-                        println!("{:?} => {:?} {}", a.status, b.status, a.test)
+                    if a != b {
+                        let (a_status, b_status) = (fmt_status(a), fmt_status(b));
+                        println!("{a_status} => {b_status} {}", test.test)
                     }
                 }
             };
-        });
+        }
 
         let grand_total_time = start.elapsed().as_millis();
         println!("====================");
         println!("Done in {grand_total_time}ms");
+
+        Ok(())
+    }
+}
+
+/// Format a status for display, flagging statuses that wptreport doesn't recognise
+fn fmt_status(status: &TestStatus) -> String {
+    match status {
+        TestStatus::Unknown(status) => format!("{status} (unrecognised)"),
+        status => status.to_string(),
     }
 }
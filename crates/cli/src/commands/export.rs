@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use clap::{Parser, ValueEnum};
+use wptreport::csv::{write_area_rows, write_test_rows, Delimiter};
+use wptreport::junit::{write_junit, SubtestMode};
+use wptreport::wpt_report::WptReport;
+use wptreport::{score_wpt_report, Error};
+
+use crate::compression::stream_report;
+
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+pub enum ExportFormat {
+    /// JUnit XML
+    #[default]
+    Junit,
+    /// Comma separated values
+    Csv,
+    /// Tab separated values
+    Tsv,
+    /// Apache Parquet tables of runs, tests and subtests (requires the `arrow` feature)
+    Parquet,
+}
+
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+pub enum SubtestsAs {
+    /// Write each subtest as a testcase
+    #[default]
+    Testcases,
+    /// Write subtest statuses as properties of their test's testcase
+    Properties,
+}
+
+#[derive(Clone, Debug, Default, Parser)]
+#[clap(name = "export")]
+pub struct Export {
+    /// Read the WPT report from IN. For parquet, IN may also be a directory of Servo scores
+    /// files (as read by calc-scores), which are exported as one run each.
+    #[arg(long)]
+    r#in: PathBuf,
+
+    /// Write the exported report to OUT (a directory for parquet)
+    #[arg(long)]
+    out: PathBuf,
+
+    /// The format to export to
+    #[arg(long, value_enum, default_value_t)]
+    format: ExportFormat,
+
+    /// How to represent subtests (junit only) [default: testcases]
+    #[arg(long, value_enum)]
+    subtests: Option<SubtestsAs>,
+
+    /// Write one row per area rather than one row per test (csv and tsv only)
+    #[arg(long)]
+    areas: bool,
+}
+
+impl Export {
+    pub fn run(self) -> Result<(), Error> {
+        let start = Instant::now();
+        self.check_options()?;
+        match self.format {
+            ExportFormat::Junit => {
+                let subtests = match self.subtests.unwrap_or_default() {
+                    SubtestsAs::Testcases => SubtestMode::Testcases,
+                    SubtestsAs::Properties => SubtestMode::Properties,
+                };
+                self.write_report(|report, out| write_junit(report, subtests, out))?;
+            }
+            ExportFormat::Csv => self.write_rows(Delimiter::Comma)?,
+            ExportFormat::Tsv => self.write_rows(Delimiter::Tab)?,
+            ExportFormat::Parquet => export_parquet(&self.r#in, &self.out)?,
+        }
+
+        let total_time = start.elapsed().as_millis();
+        println!("Wrote {} in {total_time}ms", self.out.display());
+
+        Ok(())
+    }
+
+    fn write_rows(&self, delimiter: Delimiter) -> Result<(), Error> {
+        self.write_report(|report, out| match self.areas {
+            true => write_area_rows(&score_wpt_report(report), delimiter, out),
+            false => write_test_rows(report, delimiter, out),
+        })
+    }
+
+    /// Read the input report and write it to the output file with `write`
+    fn write_report(
+        &self,
+        write: impl FnOnce(&WptReport, File) -> io::Result<()>,
+    ) -> Result<(), Error> {
+        let report = stream_report(&self.r#in)?
+            .into_report()
+            .map_err(|err| err.with_file(&self.r#in))?;
+
+        let out = File::create(&self.out).map_err(|err| Error::from(err).with_file(&self.out))?;
+        write(&report, out).map_err(|err| Error::from(err).with_file(&self.out))
+    }
+
+    /// Reject options that the chosen format doesn't use
+    fn check_options(&self) -> Result<(), Error> {
+        let areas = self.areas.then_some("--areas");
+        let subtests = self.subtests.map(|_| "--subtests");
+        let unused = match self.format {
+            ExportFormat::Junit => areas,
+            ExportFormat::Csv | ExportFormat::Tsv => subtests,
+            ExportFormat::Parquet => areas.or(subtests),
+        };
+        match unused {
+            Some(option) => {
+                let format = format!("{:?}", self.format).to_lowercase();
+                let err = io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{option} can't be used with --format {format}"),
+                );
+                Err(Error::from(err))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "arrow")]
+fn export_parquet(in_path: &Path, out_dir: &Path) -> Result<(), Error> {
+    use std::fs::read_dir;
+    use wptreport::parquet::ParquetWriter;
+    use wptreport::servo_test_scores::WptScores;
+
+    use crate::compression::read_report;
+
+    fn label(path: &Path) -> String {
+        path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+    let mut writer = ParquetWriter::create(out_dir)?;
+    if in_path.is_dir() {
+        let mut file_paths: Vec<_> = read_dir(in_path)
+            .map_err(|err| Error::from(err).with_file(in_path))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && !label(path).starts_with('.'))
+            .collect();
+        file_paths.sort();
+
+        let count = file_paths.len();
+        for (i, file_path) in file_paths.iter().enumerate() {
+            let scores: WptScores = read_report(file_path)?;
+            writer.add_scores(&label(file_path), &scores)?;
+            println!("[{}/{count}] Exported {}", i + 1, label(file_path));
+        }
+    } else {
+        let report = stream_report(in_path)?
+            .into_report()
+            .map_err(|err| err.with_file(in_path))?;
+        writer.add_report(&label(in_path), &report)?;
+    }
+    writer.finish()
+}
+
+#[cfg(not(feature = "arrow"))]
+fn export_parquet(_in_path: &Path, _out_dir: &Path) -> Result<(), Error> {
+    let err =
+        std::io::Error::other("parquet export requires wpt to be built with the `arrow` feature");
+    Err(Error::from(err))
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::Parser;
+use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
+use wptreport::score_summary::FocusArea;
+use wptreport::summarize::summarize_interop;
+use wptreport::Error;
+
+use crate::compression::{read_any_report, read_report};
+
+#[derive(Clone, Debug, Default, Parser)]
+#[clap(name = "interop")]
+pub struct Interop {
+    /// Read a report for each browser from REPORTS. Each may be a wptreport, a Servo scores
+    /// file or a wpt.fyi summary file. Summaries don't record the browser, so their product
+    /// is empty.
+    #[arg(required = true)]
+    reports: Vec<PathBuf>,
+
+    /// Output the interop summary at OUT
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Read focus areas from FOCUS_AREAS
+    #[arg(long)]
+    focus_areas: Option<PathBuf>,
+}
+
+impl Interop {
+    pub fn run(self) -> Result<(), Error> {
+        let start = Instant::now();
+
+        let focus_areas: Option<Vec<FocusArea>> =
+            self.focus_areas.as_deref().map(read_report).transpose()?;
+        let reports = self
+            .reports
+            .par_iter()
+            .map(|path| read_any_report(path))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let read_time = start.elapsed().as_millis();
+
+        let summary = summarize_interop(&reports, focus_areas.as_deref());
+        let summary_str = serde_json::to_string(&summary).unwrap();
+        fs::write(&self.out, summary_str).map_err(|err| Error::from(err).with_file(&self.out))?;
+
+        println!("area\t{}\tinterop", summary.browsers.join("\t"));
+        for (area, scores) in summary.focus_areas.iter().zip(&summary.scores) {
+            let browser_scores: Vec<_> = scores
+                .browser_scores
+                .iter()
+                .map(|&score| as_percent(score))
+                .collect();
+            println!(
+                "{area}\t{}\t{}",
+                browser_scores.join("\t"),
+                as_percent(scores.interop_score)
+            );
+        }
+
+        let grand_total_time = start.elapsed().as_millis();
+        println!("====================");
+        println!(
+            "Scored {} reports in {grand_total_time}ms (read in {read_time}ms)",
+            reports.len()
+        );
+
+        Ok(())
+    }
+}
+
+/// Format a score out of 1000 as a percentage
+fn as_percent(score: u16) -> String {
+    format!("{:.1}%", score as f32 / 10.0)
+}
@@ -5,9 +5,9 @@ use std::time::Instant;
 
 use clap::Parser;
 use wptreport::merge::WptReportMerger;
-use wptreport::wpt_report::WptReport;
+use wptreport::Error;
 
-use crate::compression::read_maybe_compressed_file;
+use crate::compression::stream_report;
 
 #[derive(Clone, Debug, Default, Parser)]
 #[clap(name = "merge")]
@@ -22,17 +22,21 @@ pub struct Merge {
 }
 
 impl Merge {
-    pub fn run(self) {
+    pub fn run(self) -> Result<(), Error> {
         let in_path = self.r#in;
-        let dir_entries = read_dir(&in_path).unwrap();
+        let dir_entries = read_dir(&in_path).map_err(|err| Error::from(err).with_file(&in_path))?;
         let start = Instant::now();
 
-        let mut file_paths: Vec<_> = dir_entries
-            .flatten()
-            .filter(|entry| entry.metadata().unwrap().is_file())
-            .map(|entry| entry.path())
-            .filter(|path| path.file_name().is_some_and(|p| p.as_bytes()[0] != b'.'))
-            .collect();
+        let mut file_paths = Vec::new();
+        for entry in dir_entries.flatten() {
+            let path = entry.path();
+            let metadata = entry
+                .metadata()
+                .map_err(|err| Error::from(err).with_file(&path))?;
+            if metadata.is_file() && path.file_name().is_some_and(|p| p.as_bytes()[0] != b'.') {
+                file_paths.push(path);
+            }
+        }
         file_paths.sort();
 
         let mut merger = WptReportMerger::new();
@@ -40,28 +44,24 @@ impl Merge {
         let count = file_paths.len();
         let mut i = 0;
         for path in file_paths {
-            // Read file
-            let read_start = Instant::now();
-            let report_str = read_maybe_compressed_file(&path);
-            let report: WptReport = serde_json::from_str(&report_str).unwrap();
-            let read_elapsed = read_start.elapsed().as_millis();
-
+            // Read file (results are merged as they are streamed in)
             let merge_start = Instant::now();
-            merger.add_chunk(report);
-            let merge_elapsed = merge_start.elapsed().as_millis();
+            let report = stream_report(&path)?;
+            merger
+                .add_chunk_stream(report)
+                .map_err(|err| err.with_file(&path))?;
+            let total_time = merge_start.elapsed().as_millis();
 
-            let total_time = read_elapsed + merge_elapsed;
-            let file_name = path.file_name().unwrap().display();
+            let file_name = path.file_name().unwrap_or_default().display();
             i += 1;
-            println!(
-              "[{i}/{count}] Processed {file_name} in {total_time}ms (read in {read_elapsed}ms; Scored in {merge_elapsed}ms)",
-          );
+            println!("[{i}/{count}] Processed {file_name} in {total_time}ms");
         }
 
         let write_start = Instant::now();
         let merged_report = merger.into_merged_report();
         let merged_report_str = serde_json::to_string(&merged_report).unwrap();
-        fs::write(&self.out, merged_report_str).unwrap();
+        fs::write(&self.out, merged_report_str)
+            .map_err(|err| Error::from(err).with_file(&self.out))?;
         let write_elapsed = write_start.elapsed().as_millis();
 
         println!("Generated merged report in {write_elapsed}ms");
@@ -70,5 +70,7 @@ impl Merge {
         let out_file_name = self.out.display();
         println!("====================");
         println!("Wrote merged report to {out_file_name} in {grand_total_time}ms");
+
+        Ok(())
     }
 }
@@ -6,3 +6,13 @@ mod convert;
 pub use convert::Convert;
 mod diff;
 pub use diff::Diff;
+mod update_metadata;
+pub use update_metadata::UpdateMetadata;
+mod export;
+pub use export::Export;
+mod pack;
+pub use pack::{Pack, Unpack};
+mod interop;
+pub use interop::Interop;
+mod bsf;
+pub use bsf::Bsf;
@@ -0,0 +1,123 @@
+use std::ffi::OsStr;
+use std::fs::{self, read_dir, File};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use clap::Parser;
+use wptreport::scores_history::ScoresHistory;
+use wptreport::servo_test_scores::WptScores;
+use wptreport::Error;
+
+use crate::compression::{open_maybe_compressed_file, read_report};
+
+#[derive(Clone, Debug, Default, Parser)]
+#[clap(name = "pack")]
+pub struct Pack {
+    /// Read Servo scores files from the directory IN
+    #[arg(long)]
+    r#in: PathBuf,
+
+    /// Write the packed history to OUT
+    #[arg(long)]
+    out: PathBuf,
+}
+
+impl Pack {
+    pub fn run(self) -> Result<(), Error> {
+        let in_path = self.r#in;
+        let dir_entries = read_dir(&in_path).map_err(|err| Error::from(err).with_file(&in_path))?;
+        let start = Instant::now();
+
+        let mut file_paths = Vec::new();
+        for entry in dir_entries.flatten() {
+            let path = entry.path();
+            let metadata = entry
+                .metadata()
+                .map_err(|err| Error::from(err).with_file(&path))?;
+            if metadata.is_file() && path.file_name().is_some_and(|p| p.as_bytes()[0] != b'.') {
+                file_paths.push(path);
+            }
+        }
+        file_paths.sort();
+
+        let mut history = ScoresHistory::new();
+        let count = file_paths.len();
+        for (i, path) in file_paths.iter().enumerate() {
+            let read_start = Instant::now();
+            let scores: WptScores = read_report(path)?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            history.push(file_name.as_ref(), &scores);
+            let total_time = read_start.elapsed().as_millis();
+            println!("[{}/{count}] Packed {file_name} in {total_time}ms", i + 1);
+        }
+
+        let out = File::create(&self.out).map_err(|err| Error::from(err).with_file(&self.out))?;
+        history
+            .write(out)
+            .map_err(|err| Error::from(err).with_file(&self.out))?;
+
+        let grand_total_time = start.elapsed().as_millis();
+        println!("====================");
+        println!(
+            "Wrote {count} runs to {} in {grand_total_time}ms",
+            self.out.display()
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Parser)]
+#[clap(name = "unpack")]
+pub struct Unpack {
+    /// Read the packed history from IN
+    #[arg(long)]
+    r#in: PathBuf,
+
+    /// Write a Servo scores file for each run to the directory OUT. Files are named after
+    /// the file each run was packed from (without any .xz or .zst extension).
+    #[arg(long)]
+    out: PathBuf,
+}
+
+impl Unpack {
+    pub fn run(self) -> Result<(), Error> {
+        let start = Instant::now();
+        let reader = open_maybe_compressed_file(&self.r#in)?;
+        let history =
+            ScoresHistory::from_reader(reader).map_err(|err| err.with_file(&self.r#in))?;
+
+        fs::create_dir_all(&self.out).map_err(|err| Error::from(err).with_file(&self.out))?;
+        for run in 0..history.len() {
+            let scores = history
+                .to_scores(run)
+                .map_err(|err| err.with_file(&self.r#in))?;
+            // Labels come from the file, so only their final component is used to keep every
+            // file in OUT
+            let label = history.label(run);
+            let Some(file_name) = Path::new(label).file_name().and_then(OsStr::to_str) else {
+                let message = format!("run {run} has an invalid label {label:?}");
+                let err = io::Error::new(io::ErrorKind::InvalidData, message);
+                return Err(Error::from(err).with_file(&self.r#in));
+            };
+            let file_name = file_name
+                .strip_suffix(".xz")
+                .or_else(|| file_name.strip_suffix(".zst"))
+                .unwrap_or(file_name);
+            let path = self.out.join(file_name);
+            let scores_str = serde_json::to_string(&scores).unwrap();
+            fs::write(&path, scores_str).map_err(|err| Error::from(err).with_file(&path))?;
+        }
+
+        let grand_total_time = start.elapsed().as_millis();
+        println!(
+            "Wrote {} runs to {} in {grand_total_time}ms",
+            history.len(),
+            self.out.display()
+        );
+
+        Ok(())
+    }
+}
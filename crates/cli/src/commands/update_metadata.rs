@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use wptreport::expectations::Metadata;
+use wptreport::manifest::Manifest;
+use wptreport::wpt_report::WptReport;
+use wptreport::Error;
+
+use crate::compression::{open_maybe_compressed_file, read_report};
+
+#[derive(Clone, Debug, Default, Parser)]
+#[clap(name = "update-metadata")]
+pub struct UpdateMetadata {
+    /// Read the WPT report from IN
+    #[arg(long)]
+    r#in: PathBuf,
+
+    /// Update the wptrunner metadata (.ini files) in METADATA
+    #[arg(long)]
+    metadata: PathBuf,
+
+    /// Read the WPT MANIFEST.json from MANIFEST (used to find the files of generated tests
+    /// such as .any.js tests)
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Only list unexpected results, without updating any files
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl UpdateMetadata {
+    pub fn run(self) -> Result<(), Error> {
+        let report: WptReport = read_report(&self.r#in)?;
+        let manifest = self
+            .manifest
+            .as_deref()
+            .map(|path| {
+                let reader = open_maybe_compressed_file(path)?;
+                Manifest::from_reader(reader).map_err(|err| err.with_file(path))
+            })
+            .transpose()?;
+
+        let mut metadata = Metadata::new(&self.metadata, manifest);
+
+        let unexpected = metadata.unexpected_results(&report)?;
+        for result in &unexpected {
+            let expected = result.expected.join(", ");
+            match &result.subtest {
+                Some(subtest) => println!(
+                    "{} (expected {expected}): {} [{subtest}]",
+                    result.status, result.test
+                ),
+                None => println!("{} (expected {expected}): {}", result.status, result.test),
+            }
+        }
+        println!("{} unexpected results", unexpected.len());
+
+        if self.dry_run {
+            return Ok(());
+        }
+
+        metadata.update(&report)?;
+        let changed = metadata.write()?;
+        for path in &changed {
+            println!("Updated {}", path.display());
+        }
+        println!("Updated {} metadata files", changed.len());
+
+        Ok(())
+    }
+}
@@ -1,26 +1,52 @@
-use std::fs::{self, File};
-use std::io::Read;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
 use std::os::unix::ffi::OsStrExt as _;
 use std::path::Path;
+use wptreport::any_report::AnyReport;
+use wptreport::wpt_report_stream::WptReportStream;
+use wptreport::Error;
 use xz2::read::XzDecoder;
 
-pub fn read_maybe_compressed_file(file_path: &Path) -> String {
-    let file = File::open(file_path).unwrap();
-    let extension = file_path.extension().unwrap().as_bytes();
+/// Open a file, transparently decompressing it if it has a `.xz` or `.zst` extension
+pub fn open_maybe_compressed_file(file_path: &Path) -> Result<Box<dyn BufRead>, Error> {
+    open_maybe_compressed_file_inner(file_path).map_err(|err| Error::from(err).with_file(file_path))
+}
+
+fn open_maybe_compressed_file_inner(file_path: &Path) -> std::io::Result<Box<dyn BufRead>> {
+    let file = File::open(file_path)?;
+    let extension = file_path.extension().map(|ext| ext.as_bytes());
+
+    Ok(match extension {
+        Some(b"xz") => Box::new(BufReader::new(XzDecoder::new(file))),
+        Some(b"zst") => Box::new(BufReader::new(zstd::Decoder::new(file)?)),
+        _ => Box::new(BufReader::new(file)),
+    })
+}
+
+pub fn read_maybe_compressed_file(file_path: &Path) -> Result<String, Error> {
+    let mut s = String::new();
+    open_maybe_compressed_file(file_path)?
+        .read_to_string(&mut s)
+        .map_err(|err| Error::from(err).with_file(file_path))?;
+    Ok(s)
+}
+
+/// Read and parse a (possibly compressed) JSON report file
+pub fn read_report<T: serde::de::DeserializeOwned>(file_path: &Path) -> Result<T, Error> {
+    let report_str = read_maybe_compressed_file(file_path)?;
+    wptreport::from_str(&report_str).map_err(|err| err.with_file(file_path))
+}
+
+/// Read and parse a (possibly compressed) wptreport, Servo scores or wpt.fyi summary file
+pub fn read_any_report(file_path: &Path) -> Result<AnyReport, Error> {
+    let report_str = read_maybe_compressed_file(file_path)?;
+    report_str
+        .parse()
+        .map_err(|err: Error| err.with_file(file_path))
+}
 
-    match extension {
-        b"xz" => {
-            let mut decompressed = XzDecoder::new(file);
-            let mut s = String::new();
-            decompressed.read_to_string(&mut s).unwrap();
-            s
-        }
-        b"zst" => {
-            let mut decompressed = zstd::Decoder::new(file).unwrap();
-            let mut s = String::new();
-            decompressed.read_to_string(&mut s).unwrap();
-            s
-        }
-        _ => fs::read_to_string(file_path).unwrap(),
-    }
+/// Open a (possibly compressed) wptreport file for streaming
+pub fn stream_report(file_path: &Path) -> Result<WptReportStream<Box<dyn BufRead>>, Error> {
+    let reader = open_maybe_compressed_file(file_path)?;
+    WptReportStream::new(reader).map_err(|err| err.with_file(file_path))
 }
@@ -37,14 +37,49 @@ pub(crate) enum Commands {
     /// Diff two WPT reports
     #[clap(name = "diff")]
     Diff(commands::Diff),
+
+    /// Export a WPT report to another format (e.g. JUnit XML, CSV or Parquet)
+    #[clap(name = "export")]
+    Export(commands::Export),
+
+    /// Score reports from several browsers in the way that Interop does
+    #[clap(name = "interop")]
+    Interop(commands::Interop),
+
+    /// Find the tests that fail in one browser but pass in others
+    #[clap(name = "bsf")]
+    Bsf(commands::Bsf),
+
+    /// Pack a directory of Servo scores files into a compact binary history
+    #[clap(name = "pack")]
+    Pack(commands::Pack),
+
+    /// Unpack a binary history into a directory of Servo scores files
+    #[clap(name = "unpack")]
+    Unpack(commands::Unpack),
+
+    /// Update wptrunner expectation metadata from a WPT report
+    #[clap(name = "update-metadata")]
+    UpdateMetadata(commands::UpdateMetadata),
 }
 
 fn main() {
     let args = Cli::parse();
-    match args.action {
+    let result = match args.action {
         Commands::CalcScores(cmd) => cmd.run(),
         Commands::Merge(cmd) => cmd.run(),
         Commands::Convert(cmd) => cmd.run(),
         Commands::Diff(cmd) => cmd.run(),
+        Commands::Export(cmd) => cmd.run(),
+        Commands::Interop(cmd) => cmd.run(),
+        Commands::Bsf(cmd) => cmd.run(),
+        Commands::Pack(cmd) => cmd.run(),
+        Commands::Unpack(cmd) => cmd.run(),
+        Commands::UpdateMetadata(cmd) => cmd.run(),
     };
+
+    if let Err(err) = result {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    }
 }
@@ -1,51 +1,155 @@
 use crate::{
-    wpt_report::{TestResult, WptReport},
-    TestResultIter,
+    wpt_report::{SubtestStatus, TestResult, TestStatus, WptReport},
+    ScorableReport, SubtestCounts, TestResultIter,
 };
-use std::collections::BTreeMap;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 
-pub fn aggregate<T>(
-    reports: &mut [WptReport],
-    mut map_fn: impl FnMut(&[Option<&TestResult>]) -> T,
-) -> Vec<T> {
-    let report_count = reports.len();
-    assert!(report_count <= 64);
-
-    let mut test_names: BTreeMap<String, u64> = BTreeMap::new();
-    for (i, report) in reports.iter_mut().enumerate() {
-        report.results.sort_by(|a, b| a.test.cmp(&b.test));
-        let mask = 1 << i;
-        for result in &report.results {
-            test_names
-                .entry(result.test.clone())
-                .and_modify(|bitset| *bitset |= mask)
-                .or_insert(mask);
-        }
-    }
+/// Join the results of any number of reports by test name, yielding one row per test (in
+/// test name order) with the test's result in each report (or `None` if the report doesn't
+/// contain it).
+///
+/// The reports are not modified: each report's results are sorted by reference, and rows
+/// are produced lazily by a k-way merge.
+pub fn aggregate_rows<R: ScorableReport>(reports: &[R]) -> AggregateRows<R::TestResultIter<'_>> {
+    let mut iters: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            let mut results: Vec<_> = report.results().collect();
+            results.sort_by(|a, b| a.name().cmp(b.name()));
+            results.into_iter()
+        })
+        .collect();
+    let heads = iters.iter_mut().map(|iter| iter.next()).collect();
+    AggregateRows { heads, iters }
+}
 
-    let mut results = Vec::with_capacity(test_names.len());
-    let mut iterators = reports
-        .iter_mut()
-        .map(|report| report.results.iter().peekable())
-        .collect::<Vec<_>>();
-    let mut current_row: Vec<Option<&TestResult>> = vec![None; report_count];
-
-    for (_, bitvec) in test_names {
-        for (i, item) in current_row.iter_mut().enumerate() {
-            if (bitvec & (1 << i as u64)) != 0 {
-                *item = iterators[i].next()
-            } else {
-                *item = None
+/// An iterator over the rows of a join of reports (see [`aggregate_rows`])
+pub struct AggregateRows<T> {
+    /// The next result from each report
+    heads: Vec<Option<T>>,
+    iters: Vec<std::vec::IntoIter<T>>,
+}
+
+impl<T: TestResultIter> Iterator for AggregateRows<T> {
+    type Item = Vec<Option<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Find the reports whose next result has the smallest name
+        let mut min: Option<&str> = None;
+        let mut in_row = vec![false; self.heads.len()];
+        for (i, head) in self.heads.iter().enumerate() {
+            let Some(name) = head.as_ref().map(|head| head.name()) else {
+                continue;
+            };
+            match min.map(|min| name.cmp(min)) {
+                Some(Ordering::Greater) => {}
+                Some(Ordering::Equal) => in_row[i] = true,
+                Some(Ordering::Less) | None => {
+                    min = Some(name);
+                    in_row.fill(false);
+                    in_row[i] = true;
+                }
             }
         }
+        min?;
+
+        let row = in_row
+            .iter()
+            .zip(self.heads.iter_mut().zip(&mut self.iters))
+            .map(|(&in_row, (head, iter))| match in_row {
+                true => std::mem::replace(head, iter.next()),
+                false => None,
+            })
+            .collect();
+        Some(row)
+    }
+}
+
+/// A row of a subtest-level join of reports (see [`aggregate_subtests`])
+pub struct SubtestRow<'a> {
+    pub test: &'a str,
+    /// The subtest's name, or `None` for a test without subtests (which is treated as a
+    /// single subtest with the test's status)
+    pub subtest: Option<&'a str>,
+    /// The subtest's status in each report (or `None` if the report doesn't contain it).
+    /// For formats that don't record statuses this is `PASS` or `FAIL`, as in [`diff`].
+    pub results: Vec<Option<SubtestStatus>>,
+}
+
+/// Join the results of any number of reports by test and subtest name, calling `map_fn`
+/// with one row per subtest. Rows are in test name order, and then subtest name order.
+pub fn aggregate_subtests<R: ScorableReport, T>(
+    reports: &[R],
+    mut map_fn: impl FnMut(&SubtestRow<'_>) -> T,
+) -> Vec<T> {
+    let mut rows = Vec::new();
+    for test_row in aggregate_rows(reports) {
+        let test = test_row.iter().flatten().next().unwrap().name();
+
+        // Each report's subtests for this test, sorted by name
+        let subtests: Vec<Vec<(Option<&str>, SubtestStatus)>> = test_row
+            .iter()
+            .map(|result| {
+                let Some(result) = result else {
+                    return Vec::new();
+                };
+                let view = DiffView::new(result, true);
+                if view.subtests.is_empty() {
+                    return vec![(None, SubtestStatus::from(view.status.as_str()))];
+                }
+                let mut subtests: Vec<_> = view
+                    .subtests
+                    .into_iter()
+                    .map(|(name, status)| (Some(name), status))
+                    .collect();
+                subtests.sort_by(|a, b| a.0.cmp(&b.0));
+                subtests
+            })
+            .collect();
 
-        results.push(map_fn(&current_row));
+        let mut positions = vec![0; subtests.len()];
+        loop {
+            let min = subtests
+                .iter()
+                .zip(&positions)
+                .filter_map(|(subtests, &pos)| subtests.get(pos).map(|subtest| subtest.0))
+                .min();
+            let Some(subtest) = min else {
+                break;
+            };
+            let results = subtests
+                .iter()
+                .zip(&mut positions)
+                .map(|(subtests, pos)| match subtests.get(*pos) {
+                    Some((name, status)) if *name == subtest => {
+                        *pos += 1;
+                        Some(status.clone())
+                    }
+                    _ => None,
+                })
+                .collect();
+            rows.push(map_fn(&SubtestRow {
+                test,
+                subtest,
+                results,
+            }));
+        }
     }
+    rows
+}
 
-    results
+/// Join wptreports by test name, calling `map_fn` with each row (see [`aggregate_rows`])
+pub fn aggregate<T>(
+    reports: &mut [WptReport],
+    mut map_fn: impl FnMut(&[Option<&TestResult>]) -> T,
+) -> Vec<T> {
+    aggregate_rows(reports).map(|row| map_fn(&row)).collect()
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DiffStatus {
     Same,
     Added,
@@ -53,28 +157,199 @@ pub enum DiffStatus {
     Changed,
 }
 
+/// Whether a change made a test better or worse, judged by its number of passing subtests
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    /// Fewer subtests pass
+    Regression,
+    /// More subtests pass
+    Improvement,
+    /// The same number of subtests pass (or the test was added or removed)
+    Neutral,
+}
+
+/// The difference in a test's results between two reports
+#[derive(Debug, Clone, Serialize)]
 pub struct TestDiff {
+    pub test: String,
     pub status: DiffStatus,
+    pub kind: DiffKind,
+    /// The test's status in the first report (`None` if the test was added)
+    pub before: Option<TestStatus>,
+    /// The test's status in the second report (`None` if the test was removed)
+    pub after: Option<TestStatus>,
+    pub before_counts: Option<SubtestCounts>,
+    pub after_counts: Option<SubtestCounts>,
+    /// The subtests that were added, removed or changed
+    pub subtests: Vec<SubtestDiff>,
 }
 
-pub fn diff(reports: &mut [WptReport; 2]) -> Vec<TestDiff> {
-    aggregate(&mut *reports, |results| {
-        let a = results[0];
-        let b = results[1];
-
-        let status = match (a, b) {
-            (None, None) => unreachable!(),
-            (Some(_), None) => DiffStatus::Removed,
-            (None, Some(_)) => DiffStatus::Added,
-            (Some(a), Some(b)) => {
-                if a.subtest_counts() == b.subtest_counts() {
-                    DiffStatus::Same
-                } else {
-                    DiffStatus::Changed
+/// The difference in a subtest's result between two reports
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtestDiff {
+    pub name: String,
+    pub status: DiffStatus,
+    pub before: Option<SubtestStatus>,
+    pub after: Option<SubtestStatus>,
+}
+
+/// Compare the results of two reports test by test. Every test in either report is
+/// included, in test name order.
+///
+/// The reports may be in different formats. Statuses are compared if both reports record
+/// them (e.g. two wptreports), otherwise only whether each test and subtest passed is
+/// compared, and statuses are reported as `PASS` or `FAIL`.
+pub fn diff<A: ScorableReport, B: ScorableReport>(before: &A, after: &B) -> Vec<TestDiff> {
+    let mut before: Vec<_> = before.results().collect();
+    before.sort_by(|a, b| a.name().cmp(b.name()));
+    let mut after: Vec<_> = after.results().collect();
+    after.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut diffs = Vec::with_capacity(before.len().max(after.len()));
+    let mut before = before.iter().peekable();
+    let mut after = after.iter().peekable();
+    loop {
+        let order = match (before.peek(), after.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(a), Some(b)) => a.name().cmp(b.name()),
+        };
+        let diff = match order {
+            Ordering::Less => {
+                let a = DiffView::new(before.next().unwrap(), true);
+                TestDiff {
+                    test: a.name.to_string(),
+                    status: DiffStatus::Removed,
+                    kind: DiffKind::Neutral,
+                    before: Some(a.status),
+                    after: None,
+                    before_counts: Some(a.counts),
+                    after_counts: None,
+                    subtests: Vec::new(),
                 }
             }
+            Ordering::Greater => {
+                let b = DiffView::new(after.next().unwrap(), true);
+                TestDiff {
+                    test: b.name.to_string(),
+                    status: DiffStatus::Added,
+                    kind: DiffKind::Neutral,
+                    before: None,
+                    after: Some(b.status),
+                    before_counts: None,
+                    after_counts: Some(b.counts),
+                    subtests: Vec::new(),
+                }
+            }
+            Ordering::Equal => {
+                let (a, b) = (before.next().unwrap(), after.next().unwrap());
+                let detailed = a.as_test_result().is_some() && b.as_test_result().is_some();
+                diff_test(DiffView::new(a, detailed), DiffView::new(b, detailed))
+            }
+        };
+        diffs.push(diff);
+    }
+    diffs
+}
+
+/// A test's result as compared by [`diff`]
+struct DiffView<'a> {
+    name: &'a str,
+    status: TestStatus,
+    subtests: Vec<(&'a str, SubtestStatus)>,
+    counts: SubtestCounts,
+}
+
+impl<'a> DiffView<'a> {
+    /// View a result with statuses if `detailed` is true and the format records them, or
+    /// with statuses of just `PASS` or `FAIL` otherwise
+    fn new<T: TestResultIter>(test: &'a T, detailed: bool) -> Self {
+        let counts = test.subtest_counts();
+        match test.as_test_result().filter(|_| detailed) {
+            Some(result) => DiffView {
+                name: &result.test,
+                status: result.status.clone(),
+                subtests: result
+                    .subtests
+                    .iter()
+                    .map(|subtest| (subtest.name.as_str(), subtest.status.clone()))
+                    .collect(),
+                counts,
+            },
+            None => DiffView {
+                name: test.name(),
+                status: match counts.all_passing() {
+                    true => TestStatus::Pass,
+                    false => TestStatus::Fail,
+                },
+                subtests: test
+                    .iter_subtests_results()
+                    .map(|subtest| match subtest.passes {
+                        true => (subtest.name, SubtestStatus::Pass),
+                        false => (subtest.name, SubtestStatus::Fail),
+                    })
+                    .collect(),
+                counts,
+            },
+        }
+    }
+}
+
+fn diff_test(a: DiffView, b: DiffView) -> TestDiff {
+    let after_subtests: HashMap<&str, &SubtestStatus> = b
+        .subtests
+        .iter()
+        .map(|(name, status)| (*name, status))
+        .collect();
+    let before_names: HashSet<&str> = a.subtests.iter().map(|(name, _)| *name).collect();
+
+    let mut subtests = Vec::new();
+    for (name, before) in &a.subtests {
+        let after = after_subtests.get(name).copied();
+        let status = match after {
+            None => DiffStatus::Removed,
+            Some(after) if after != before => DiffStatus::Changed,
+            Some(_) => continue,
         };
+        subtests.push(SubtestDiff {
+            name: name.to_string(),
+            status,
+            before: Some(before.clone()),
+            after: after.cloned(),
+        });
+    }
+    for (name, after) in &b.subtests {
+        if !before_names.contains(name) {
+            subtests.push(SubtestDiff {
+                name: name.to_string(),
+                status: DiffStatus::Added,
+                before: None,
+                after: Some(after.clone()),
+            });
+        }
+    }
+
+    let status = if a.status == b.status && subtests.is_empty() {
+        DiffStatus::Same
+    } else {
+        DiffStatus::Changed
+    };
+    let kind = match b.counts.pass.cmp(&a.counts.pass) {
+        Ordering::Less => DiffKind::Regression,
+        Ordering::Greater => DiffKind::Improvement,
+        Ordering::Equal => DiffKind::Neutral,
+    };
 
-        TestDiff { status }
-    })
+    TestDiff {
+        test: a.name.to_string(),
+        status,
+        kind,
+        before: Some(a.status),
+        after: Some(b.status),
+        before_counts: Some(a.counts),
+        after_counts: Some(b.counts),
+        subtests,
+    }
 }
@@ -0,0 +1,135 @@
+//! Browser-specific failures (BSF): the tests that fail in one browser but pass in every
+//! other browser, as graphed on wpt.fyi's insights page.
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::score::area_iter;
+use crate::{ScorableReport, SubtestCounts, TestResultIter};
+
+/// Another browser's results, indexed by test name for [`browser_specific_failures`]
+#[derive(Debug, Default)]
+pub struct BsfReference {
+    tests: HashMap<String, ReferenceTest>,
+}
+
+#[derive(Debug)]
+struct ReferenceTest {
+    counts: SubtestCounts,
+    /// Whether each subtest passed, or `None` if the test has no subtests or the format only
+    /// records subtest counts (such as wpt.fyi summaries)
+    subtests: Option<HashMap<String, bool>>,
+}
+
+impl BsfReference {
+    pub fn new<R: ScorableReport>(report: &R) -> Self {
+        let tests = report
+            .results()
+            .map(|test| {
+                let subtests: HashMap<String, bool> = test
+                    .iter_subtests_results()
+                    .map(|subtest| (subtest.name.to_string(), subtest.passes))
+                    .collect();
+                let reference_test = ReferenceTest {
+                    counts: test.subtest_counts(),
+                    subtests: (!subtests.is_empty()).then_some(subtests),
+                };
+                (test.name().to_string(), reference_test)
+            })
+            .collect();
+        Self { tests }
+    }
+}
+
+/// The browser-specific failures of a report
+#[derive(Debug, Default, Serialize)]
+pub struct BsfSummary {
+    /// The scores for each area
+    pub areas: BTreeMap<String, AreaBsf>,
+    /// The tests that contributed to the scores, in test name order
+    pub tests: Vec<BsfTest>,
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize)]
+pub struct AreaBsf {
+    /// The number of tests that were run in every browser
+    pub tests: u32,
+    /// The number of tests that fail only in this browser. A test where only some subtests
+    /// fail only in this browser counts as the fraction of its subtests that do.
+    pub score: f64,
+}
+
+/// A test that fails (at least partly) only in this browser
+#[derive(Debug, Clone, Serialize)]
+pub struct BsfTest {
+    pub test: String,
+    /// The test's contribution to the score (between 0 and 1)
+    pub score: f64,
+    /// The subtests that fail only in this browser. This is empty for tests without subtests,
+    /// and for tests where a reference only records subtest counts.
+    pub subtests: Vec<String>,
+}
+
+/// Find the tests in `report` that fail in its browser but pass in all of the `references`
+/// (one per other browser). Only tests which are in every report are scored.
+///
+/// Where every report records subtest names, each subtest that fails only in this browser
+/// contributes `1 / number of subtests` so that each test counts at most once. Otherwise a
+/// test that fully passes in every reference contributes the fraction of its subtests that
+/// fail in this browser.
+pub fn browser_specific_failures<R: ScorableReport>(
+    report: &R,
+    references: &[BsfReference],
+) -> BsfSummary {
+    let mut summary = BsfSummary::default();
+
+    for test in report.results() {
+        let Some(reference_tests) = references
+            .iter()
+            .map(|reference| reference.tests.get(test.name()))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+
+        let counts = test.subtest_counts();
+        let mut subtests = Vec::new();
+        let subtest_names_known = test.iter_subtests_results().next().is_some()
+            && reference_tests.iter().all(|test| test.subtests.is_some());
+        let score = if subtest_names_known {
+            for subtest in test.iter_subtests_results() {
+                let passes_elsewhere = reference_tests.iter().all(|reference| {
+                    let reference_subtests = reference.subtests.as_ref().unwrap();
+                    reference_subtests.get(subtest.name) == Some(&true)
+                });
+                if !subtest.passes && passes_elsewhere {
+                    subtests.push(subtest.name.to_string());
+                }
+            }
+            subtests.len() as f64 / counts.total as f64
+        } else if reference_tests.iter().all(|test| test.counts.all_passing()) {
+            1.0 - counts.pass_fraction()
+        } else {
+            0.0
+        };
+
+        for area in area_iter(test.name()) {
+            let area_bsf = match summary.areas.get_mut(area) {
+                Some(area_bsf) => area_bsf,
+                None => summary.areas.entry(area.to_string()).or_default(),
+            };
+            area_bsf.tests += 1;
+            area_bsf.score += score;
+        }
+        if score > 0.0 {
+            summary.tests.push(BsfTest {
+                test: test.name().to_string(),
+                score,
+                subtests,
+            });
+        }
+    }
+
+    summary.tests.sort_by(|a, b| a.test.cmp(&b.test));
+    summary
+}
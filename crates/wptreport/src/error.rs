@@ -0,0 +1,242 @@
+//! The error type returned when reading reports fails
+use std::fmt;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use serde::de::{DeserializeOwned, DeserializeSeed};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error {
+    /// The file that was being read (if known)
+    file: Option<PathBuf>,
+    /// The URL that was being fetched (if any)
+    url: Option<String>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// Reading the underlying file or stream failed
+    Io(io::Error),
+    /// The input was not valid JSON or did not match the expected format
+    Json {
+        /// The path to the field that failed to parse (e.g. `results[4123].subtests[2].status`)
+        path: String,
+        source: serde_json::Error,
+    },
+    /// A wptrunner expectation metadata (`.ini`) file could not be parsed
+    Ini { line: usize, message: String },
+    /// A binary file (such as a packed scores history) is malformed
+    Binary {
+        /// The byte offset at which the problem was found
+        offset: usize,
+        message: String,
+    },
+    /// An HTTP request failed
+    #[cfg(feature = "wpt-fyi")]
+    Http(reqwest::Error),
+    /// Writing a Parquet file failed
+    #[cfg(feature = "arrow")]
+    Parquet(::parquet::errors::ParquetError),
+    /// Chunks of a report that were merged have different `run_info`
+    RunInfoMismatch {
+        /// The `run_info` keys whose values differ
+        keys: Vec<String>,
+    },
+}
+
+impl Error {
+    /// Create an error from a serde_json error. I/O errors encountered while deserializing
+    /// from a reader are reported as [`ErrorKind::Io`].
+    pub(crate) fn json(path: String, source: serde_json::Error) -> Self {
+        if source.is_io() {
+            return Self::from(io::Error::from(source));
+        }
+        Self::from(ErrorKind::Json { path, source })
+    }
+
+    /// Attach the path of the file that was being read to the error
+    pub fn with_file(mut self, file: impl AsRef<Path>) -> Self {
+        self.file = Some(file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Attach the URL that was being fetched to the error
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// The path to the JSON field that failed to parse (if the error is a parse error)
+    pub fn json_path(&self) -> Option<&str> {
+        match &self.kind {
+            ErrorKind::Json { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self {
+            file: None,
+            url: None,
+            kind,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::from(ErrorKind::Io(err))
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<::parquet::errors::ParquetError> for Error {
+    fn from(err: ::parquet::errors::ParquetError) -> Self {
+        Self::from(ErrorKind::Parquet(err))
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<arrow_schema::ArrowError> for Error {
+    fn from(err: arrow_schema::ArrowError) -> Self {
+        Self::from(::parquet::errors::ParquetError::from(err))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        if let Some(url) = &self.url {
+            write!(f, "{url}: ")?;
+        }
+        match &self.kind {
+            ErrorKind::Io(err) => write!(f, "{err}"),
+            #[cfg(feature = "wpt-fyi")]
+            ErrorKind::Http(err) => write!(f, "{err}"),
+            #[cfg(feature = "arrow")]
+            ErrorKind::Parquet(err) => write!(f, "{err}"),
+            // serde_path_to_error uses "." to represent the root of the document
+            ErrorKind::Json { path, source } if path == "." => write!(f, "{source}"),
+            ErrorKind::Json { path, source } => write!(f, "{path}: {source}"),
+            ErrorKind::Ini { line, message } => write!(f, "line {line}: {message}"),
+            ErrorKind::Binary { offset, message } => write!(f, "byte {offset}: {message}"),
+            ErrorKind::RunInfoMismatch { keys } => {
+                write!(
+                    f,
+                    "run_info doesn't match (differs in: {})",
+                    keys.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(err) => Some(err),
+            #[cfg(feature = "wpt-fyi")]
+            ErrorKind::Http(err) => Some(err),
+            #[cfg(feature = "arrow")]
+            ErrorKind::Parquet(err) => Some(err),
+            ErrorKind::Json { source, .. } => Some(source),
+            ErrorKind::Ini { .. }
+            | ErrorKind::Binary { .. }
+            | ErrorKind::RunInfoMismatch { .. } => None,
+        }
+    }
+}
+
+/// Convert a path-tracking serde error into an [`Error`]
+pub(crate) fn from_path_error(err: serde_path_to_error::Error<serde_json::Error>) -> Error {
+    let path = err.path().to_string();
+    Error::json(path, err.into_inner())
+}
+
+/// Parse a JSON document from a string
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(from_path_error)?;
+    deserializer
+        .end()
+        .map_err(|err| Error::json(String::from("."), err))?;
+    Ok(value)
+}
+
+/// Parse a JSON document from a byte slice
+pub fn from_slice<T: DeserializeOwned>(v: &[u8]) -> Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_slice(v);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(from_path_error)?;
+    deserializer
+        .end()
+        .map_err(|err| Error::json(String::from("."), err))?;
+    Ok(value)
+}
+
+/// Parse a JSON document from a reader. The reader is buffered internally.
+pub fn from_reader<T: DeserializeOwned>(reader: impl Read) -> Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(from_path_error)?;
+    deserializer
+        .end()
+        .map_err(|err| Error::json(String::from("."), err))?;
+    Ok(value)
+}
+
+/// Parse a JSON document from a string using a [`DeserializeSeed`]
+pub(crate) fn from_str_seed<'de, S: DeserializeSeed<'de>>(
+    s: &'de str,
+    seed: S,
+) -> Result<S::Value> {
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    let mut track = serde_path_to_error::Track::new();
+    let value = seed
+        .deserialize(serde_path_to_error::Deserializer::new(
+            &mut deserializer,
+            &mut track,
+        ))
+        .map_err(|err| Error::json(track.path().to_string(), err))?;
+    deserializer
+        .end()
+        .map_err(|err| Error::json(String::from("."), err))?;
+    Ok(value)
+}
+
+/// Parse a JSON document from a reader using a [`DeserializeSeed`]. The reader is buffered
+/// internally.
+pub(crate) fn from_reader_seed<T, S>(reader: impl Read, seed: S) -> Result<T>
+where
+    S: for<'de> DeserializeSeed<'de, Value = T>,
+{
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let mut track = serde_path_to_error::Track::new();
+    let value = seed
+        .deserialize(serde_path_to_error::Deserializer::new(
+            &mut deserializer,
+            &mut track,
+        ))
+        .map_err(|err| Error::json(track.path().to_string(), err))?;
+    deserializer
+        .end()
+        .map_err(|err| Error::json(String::from("."), err))?;
+    Ok(value)
+}
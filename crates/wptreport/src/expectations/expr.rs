@@ -0,0 +1,283 @@
+//! The condition language used in `if <condition>: <value>` lines
+use serde_json::{Map, Value};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A `run_info` property (e.g. `os`)
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Bool(bool),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    NotEq(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluate the expression with identifiers looked up in `properties`. Unknown
+    /// identifiers evaluate to null.
+    pub fn evaluate(&self, properties: &Map<String, Value>) -> Value {
+        match self {
+            Expr::Ident(name) => properties.get(name).cloned().unwrap_or(Value::Null),
+            Expr::Str(s) => Value::from(s.as_str()),
+            Expr::Number(n) => Value::from(*n),
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Not(expr) => Value::Bool(!is_truthy(&expr.evaluate(properties))),
+            Expr::And(lhs, rhs) => Value::Bool(lhs.matches(properties) && rhs.matches(properties)),
+            Expr::Or(lhs, rhs) => Value::Bool(lhs.matches(properties) || rhs.matches(properties)),
+            Expr::Eq(lhs, rhs) => Value::Bool(values_equal(
+                &lhs.evaluate(properties),
+                &rhs.evaluate(properties),
+            )),
+            Expr::NotEq(lhs, rhs) => Value::Bool(!values_equal(
+                &lhs.evaluate(properties),
+                &rhs.evaluate(properties),
+            )),
+        }
+    }
+
+    /// Whether the expression evaluates to a truthy value
+    pub fn matches(&self, properties: &Map<String, Value>) -> bool {
+        is_truthy(&self.evaluate(properties))
+    }
+
+    /// Parse an expression such as `(os == "linux") and not debug`
+    pub fn parse(s: &str) -> Result<Expr, String> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {token} in condition")),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 1,
+            Expr::And(..) => 2,
+            Expr::Not(..) => 3,
+            Expr::Eq(..) | Expr::NotEq(..) => 4,
+            Expr::Ident(_) | Expr::Str(_) | Expr::Number(_) | Expr::Bool(_) => 5,
+        }
+    }
+
+    /// Write `self` as an operand of an operator with precedence `min`, adding parentheses
+    /// if needed. Comparisons are always parenthesized when combined (as wptrunner does).
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, min: u8) -> fmt::Result {
+        if self.precedence() < min || matches!(self, Expr::Eq(..) | Expr::NotEq(..)) {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Ident(name) => f.write_str(name),
+            Expr::Str(s) => write!(f, "\"{}\"", super::parse::escape(s, '"')),
+            Expr::Number(n) => write!(f, "{n}"),
+            Expr::Bool(b) => write!(f, "{b}"),
+            Expr::Not(expr) => {
+                f.write_str("not ")?;
+                expr.fmt_operand(f, 3)
+            }
+            Expr::And(lhs, rhs) => {
+                lhs.fmt_operand(f, 2)?;
+                f.write_str(" and ")?;
+                rhs.fmt_operand(f, 3)
+            }
+            Expr::Or(lhs, rhs) => {
+                lhs.fmt_operand(f, 1)?;
+                f.write_str(" or ")?;
+                rhs.fmt_operand(f, 2)
+            }
+            Expr::Eq(lhs, rhs) => write!(f, "{lhs} == {rhs}"),
+            Expr::NotEq(lhs, rhs) => write!(f, "{lhs} != {rhs}"),
+        }
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(items) => !items.is_empty(),
+    }
+}
+
+/// Compare values, treating integers and floats with the same value as equal
+fn values_equal(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => lhs.as_f64() == rhs.as_f64(),
+        _ => lhs == rhs,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    LParen,
+    RParen,
+    Eq,
+    NotEq,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{name}'"),
+            Token::Str(s) => write!(f, "string \"{s}\""),
+            Token::Number(n) => write!(f, "number {n}"),
+            Token::LParen => f.write_str("'('"),
+            Token::RParen => f.write_str("')'"),
+            Token::Eq => f.write_str("'=='"),
+            Token::NotEq => f.write_str("'!='"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '=' | '!' => {
+                chars.next();
+                if chars.next_if(|&(_, c)| c == '=').is_none() {
+                    return Err(format!("expected '=' after '{c}' in condition"));
+                }
+                tokens.push(if c == '=' { Token::Eq } else { Token::NotEq });
+            }
+            '"' | '\'' => {
+                let (value, len) = super::parse::unquote(&s[start..])?;
+                tokens.push(Token::Str(value));
+                while chars.next_if(|&(idx, _)| idx < start + len).is_some() {}
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut end = start;
+                while let Some((idx, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.' || c == '-' || c == 'e')
+                {
+                    end = idx + c.len_utf8();
+                }
+                let number = s[start..end]
+                    .parse()
+                    .map_err(|_| format!("invalid number '{}' in condition", &s[start..end]))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some((idx, c)) =
+                    chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_' || c == '.')
+                {
+                    end = idx + c.len_utf8();
+                }
+                tokens.push(Token::Ident(s[start..end].to_string()));
+            }
+            c => return Err(format!("unexpected character '{c}' in condition")),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next_if_keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(Token::Ident(name)) if name == keyword => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.next_if_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_not()?;
+        while self.next_if_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+        if self.next_if_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.parse_atom()?;
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Eq) => Expr::Eq,
+            Some(Token::NotEq) => Expr::NotEq,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.parse_atom()?;
+        Ok(op(Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(String::from("unexpected end of condition"));
+        };
+        self.pos += 1;
+        match token {
+            Token::Ident(name) => Ok(match name.as_str() {
+                "true" | "True" => Expr::Bool(true),
+                "false" | "False" => Expr::Bool(false),
+                "and" | "or" | "not" => return Err(format!("unexpected '{name}' in condition")),
+                _ => Expr::Ident(name),
+            }),
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::LParen => {
+                let expr = self.parse_or()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::RParen) => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    _ => Err(String::from("expected ')' in condition")),
+                }
+            }
+            token => Err(format!("unexpected {token} in condition")),
+        }
+    }
+}
@@ -0,0 +1,504 @@
+//! wptrunner expectation metadata: the `metadata/**/*.ini` files which record the expected
+//! status of tests and subtests, optionally conditional on `run_info` properties:
+//!
+//! ```text
+//! [test.html]
+//!   expected: TIMEOUT
+//!   [subtest name]
+//!     expected:
+//!       if (os == "linux") and debug: [FAIL, PASS]
+//!       FAIL
+//! ```
+//!
+//! Comments are not preserved when a file is rewritten.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Map;
+
+use crate::manifest::{Manifest, TestType};
+use crate::wpt_report::{TestResult, TestStatus, WptReport, WptRunInfo};
+use crate::Error;
+
+mod expr;
+mod parse;
+
+pub use expr::Expr;
+
+/// The contents of a single `.ini` file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpectationFile {
+    /// Keys that apply to every test covered by the file. The `disabled` key of a `__dir__.ini`
+    /// file applies to every test in its directory and subdirectories (`expected` is only
+    /// read from test and subtest sections).
+    pub keys: Vec<Key>,
+    pub sections: Vec<Section>,
+}
+
+/// A `[name]` section for a test (at the top level) or a subtest (nested in a test)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub keys: Vec<Key>,
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub name: String,
+    /// The values for the key, in order. The first value whose condition matches applies.
+    pub values: Vec<ConditionalValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalValue {
+    /// `None` for the unconditional (default) value
+    pub condition: Option<Expr>,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Atom(String),
+    List(Vec<String>),
+}
+
+impl Value {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            Value::Atom(atom) => std::slice::from_ref(atom),
+            Value::List(items) => items,
+        }
+    }
+}
+
+impl FromStr for ExpectationFile {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse::parse(s)
+    }
+}
+
+impl ExpectationFile {
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.sections.is_empty()
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    fn section_mut(&mut self, name: &str) -> &mut Section {
+        section_mut(&mut self.sections, name)
+    }
+}
+
+impl Section {
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    pub fn key(&self, name: &str) -> Option<&Key> {
+        self.keys.iter().find(|key| key.name == name)
+    }
+
+    /// The value of `key` for a run with the given properties (see [`run_info_properties`])
+    pub fn get(&self, key: &str, properties: &Map<String, serde_json::Value>) -> Option<&Value> {
+        self.key(key)?.resolve(properties)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.sections.is_empty()
+    }
+}
+
+/// Get the section called `name`, adding it if it doesn't exist
+fn section_mut<'a>(sections: &'a mut Vec<Section>, name: &str) -> &'a mut Section {
+    let idx = match sections.iter().position(|section| section.name == name) {
+        Some(idx) => idx,
+        None => {
+            sections.push(Section {
+                name: name.to_string(),
+                ..Section::default()
+            });
+            sections.len() - 1
+        }
+    };
+    &mut sections[idx]
+}
+
+impl Key {
+    /// The first value whose condition matches the given properties
+    pub fn resolve(&self, properties: &Map<String, serde_json::Value>) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|value| match &value.condition {
+                Some(condition) => condition.matches(properties),
+                None => true,
+            })
+            .map(|value| &value.value)
+    }
+}
+
+/// The properties that conditions are evaluated against: every field of `run_info`
+/// (including unrecognised ones)
+pub fn run_info_properties(run_info: &WptRunInfo) -> Map<String, serde_json::Value> {
+    match serde_json::to_value(run_info) {
+        Ok(serde_json::Value::Object(properties)) => properties,
+        _ => Map::new(),
+    }
+}
+
+/// A test or subtest result whose status wasn't one of the expected statuses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnexpectedResult {
+    pub test: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtest: Option<String>,
+    pub status: String,
+    /// The expected status followed by any known intermittent statuses
+    pub expected: Vec<String>,
+}
+
+/// The `.ini` files in a metadata directory. Files are read when they are first needed,
+/// and only files whose contents have changed are written back.
+pub struct Metadata {
+    root: PathBuf,
+    manifest: Option<Manifest>,
+    files: BTreeMap<PathBuf, MetadataFile>,
+}
+
+struct MetadataFile {
+    original: Option<ExpectationFile>,
+    current: ExpectationFile,
+}
+
+impl Metadata {
+    /// Open the metadata directory at `root`. A manifest is used to find the file that each
+    /// test is generated from (e.g. `foo.any.js` for `foo.any.worker.html`) and the default
+    /// expected status for each test type. Without one, test ids are assumed to be file paths.
+    pub fn new(root: impl Into<PathBuf>, manifest: Option<Manifest>) -> Self {
+        Self {
+            root: root.into(),
+            manifest,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// The expectations file for `path` (relative to the root), if there is one
+    pub fn file(&mut self, path: &Path) -> Result<Option<&ExpectationFile>, Error> {
+        let file = self.load(path)?;
+        Ok((!file.current.is_empty()).then_some(&file.current))
+    }
+
+    fn load(&mut self, path: &Path) -> Result<&mut MetadataFile, Error> {
+        if !self.files.contains_key(path) {
+            let full_path = self.root.join(path);
+            let original = match fs::read_to_string(&full_path) {
+                Ok(contents) => Some(
+                    contents
+                        .parse::<ExpectationFile>()
+                        .map_err(|err| err.with_file(&full_path))?,
+                ),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => return Err(Error::from(err).with_file(&full_path)),
+            };
+            let current = original.clone().unwrap_or_default();
+            self.files
+                .insert(path.to_path_buf(), MetadataFile { original, current });
+        }
+        Ok(self.files.get_mut(path).unwrap())
+    }
+
+    /// The metadata file (relative to the root) and section name for a test id
+    fn locate<'a>(&self, test_id: &'a str) -> (PathBuf, &'a str) {
+        let path_end = test_id.find(['?', '#']).unwrap_or(test_id.len());
+        let name_start = test_id[..path_end].rfind('/').map_or(0, |idx| idx + 1);
+        let source_path = match self.manifest.as_ref().and_then(|m| m.get(test_id)) {
+            Some(test) => test.path.as_str(),
+            None => test_id[..path_end].trim_start_matches('/'),
+        };
+        (
+            PathBuf::from(format!("{source_path}.ini")),
+            &test_id[name_start..],
+        )
+    }
+
+    /// The status a test is expected to have when its metadata doesn't say otherwise
+    fn default_test_status(&self, result: &TestResult) -> &'static str {
+        let test_type = self.manifest.as_ref().and_then(|m| m.get(&result.test));
+        let is_testharness = match test_type.map(|test| &test.test_type) {
+            Some(test_type) => matches!(test_type, TestType::Testharness | TestType::Wdspec),
+            // Only testharness tests have subtests or can be OK
+            None => {
+                matches!(result.status, TestStatus::Ok | TestStatus::Error)
+                    || !result.subtests.is_empty()
+            }
+        };
+        match is_testharness {
+            true => "OK",
+            false => "PASS",
+        }
+    }
+
+    /// The results in `report` whose status isn't expected according to this metadata.
+    /// Tests that are `disabled` for the run, by their own section, their file or a `__dir__.ini`
+    /// file in one of their directories, are skipped.
+    pub fn unexpected_results(
+        &mut self,
+        report: &WptReport,
+    ) -> Result<Vec<UnexpectedResult>, Error> {
+        let properties = run_info_properties(&report.run_info);
+        let mut unexpected = Vec::new();
+
+        for result in &report.results {
+            let (path, name) = self.locate(&result.test);
+            let default_status = self.default_test_status(result);
+            let dirs = dir_files(&path);
+            for dir in &dirs {
+                self.load(dir)?;
+            }
+            self.load(&path)?;
+
+            let file = &self.files[&path].current;
+            let section = file.section(name);
+            let levels = section
+                .map(|section| section.keys.as_slice())
+                .into_iter()
+                .chain([file.keys.as_slice()])
+                .chain(
+                    dirs.iter()
+                        .map(|dir| self.files[dir].current.keys.as_slice()),
+                );
+            if is_disabled(levels, &properties) {
+                continue;
+            }
+
+            let expected = expected_statuses(section, &properties, default_status);
+            if !expected.iter().any(|s| s == result.status.as_str()) {
+                unexpected.push(UnexpectedResult {
+                    test: result.test.clone(),
+                    subtest: None,
+                    status: result.status.to_string(),
+                    expected,
+                });
+            }
+
+            for subtest in &result.subtests {
+                let subsection = section.and_then(|section| section.section(&subtest.name));
+                let expected = expected_statuses(subsection, &properties, "PASS");
+                if !expected.iter().any(|s| s == subtest.status.as_str()) {
+                    unexpected.push(UnexpectedResult {
+                        test: result.test.clone(),
+                        subtest: Some(subtest.name.clone()),
+                        status: subtest.status.to_string(),
+                        expected,
+                    });
+                }
+            }
+        }
+
+        Ok(unexpected)
+    }
+
+    /// Update the expectations so that every result in `report` is expected for runs with
+    /// the report's `run_info`.
+    ///
+    /// For each unexpected result, a condition on the run's `os`, `version`, `processor`,
+    /// `bits` and `debug` properties is added before the value that applies to the run (the
+    /// first matching condition, or else the default), or at the end if no value applies.
+    /// The applicable value is only replaced if its condition is exactly the run's, as any
+    /// other condition (or the default) may also match other configurations. Values that are
+    /// the same as the default status are then removed, along with sections that become
+    /// empty.
+    pub fn update(&mut self, report: &WptReport) -> Result<(), Error> {
+        let properties = run_info_properties(&report.run_info);
+
+        for result in &report.results {
+            let (path, name) = self.locate(&result.test);
+            let default_status = self.default_test_status(result);
+            let file = &mut self.load(&path)?.current;
+
+            let section = file.section_mut(name);
+            set_expected(section, result.status.as_str(), default_status, &properties);
+            for subtest in &result.subtests {
+                let subsection = section_mut(&mut section.sections, &subtest.name);
+                set_expected(subsection, subtest.status.as_str(), "PASS", &properties);
+                if subsection.is_empty() {
+                    section.sections.retain(|s| s.name != subtest.name);
+                }
+            }
+            if section.is_empty() {
+                file.sections.retain(|s| s.name != name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write files whose contents have changed, and remove files that no longer contain any
+    /// expectations. Returns the paths of the files that were written or removed.
+    pub fn write(&mut self) -> Result<Vec<PathBuf>, Error> {
+        let mut changed = Vec::new();
+
+        for (path, file) in &mut self.files {
+            let original = file.original.as_ref().filter(|file| !file.is_empty());
+            if original == Some(&file.current) || (original.is_none() && file.current.is_empty()) {
+                continue;
+            }
+
+            let full_path = self.root.join(path);
+            let result = if file.current.is_empty() {
+                fs::remove_file(&full_path)
+            } else {
+                full_path
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|()| fs::write(&full_path, file.current.to_string()))
+            };
+            result.map_err(|err| Error::from(err).with_file(&full_path))?;
+
+            file.original = (!file.current.is_empty()).then(|| file.current.clone());
+            changed.push(full_path);
+        }
+
+        Ok(changed)
+    }
+}
+
+/// The statuses that are expected for a (sub)test: the applicable `expected` value, or the
+/// default status if there isn't one
+fn expected_statuses(
+    section: Option<&Section>,
+    properties: &Map<String, serde_json::Value>,
+    default_status: &str,
+) -> Vec<String> {
+    match section.and_then(|section| section.get("expected", properties)) {
+        Some(value) => value.as_slice().to_vec(),
+        None => vec![default_status.to_string()],
+    }
+}
+
+/// The `__dir__.ini` files (relative to the root) that apply to the tests in the metadata
+/// file at `path`, innermost first
+fn dir_files(path: &Path) -> Vec<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .map(|dir| dir.join("__dir__.ini"))
+        .collect()
+}
+
+/// Whether a test is disabled for a run, given the keys of each level of metadata that
+/// applies to it (its section, its file's keys, then each `__dir__.ini` file), innermost first
+fn is_disabled<'a>(
+    levels: impl IntoIterator<Item = &'a [Key]>,
+    properties: &Map<String, serde_json::Value>,
+) -> bool {
+    // The innermost value that applies overrides those at higher levels
+    let value = levels.into_iter().find_map(|keys| {
+        keys.iter()
+            .find(|key| key.name == "disabled")?
+            .resolve(properties)
+    });
+    // `@False` explicitly re-enables a test which is disabled at a higher level
+    value.is_some_and(|value| value.as_slice() != ["@False"])
+}
+
+/// Make `status` the expected status of a section for a run with the given properties
+fn set_expected(
+    section: &mut Section,
+    status: &str,
+    default_status: &str,
+    properties: &Map<String, serde_json::Value>,
+) {
+    let key_idx = section.keys.iter().position(|key| key.name == "expected");
+    let key = match key_idx {
+        Some(idx) => &mut section.keys[idx],
+        None if status == default_status => return,
+        None => {
+            section.keys.push(Key {
+                name: String::from("expected"),
+                values: Vec::new(),
+            });
+            section.keys.last_mut().unwrap()
+        }
+    };
+
+    let matching = key.values.iter().position(|value| match &value.condition {
+        Some(condition) => condition.matches(properties),
+        None => true,
+    });
+    let new_value = Value::Atom(status.to_string());
+    match matching {
+        Some(idx) if key.values[idx].value.as_slice().iter().any(|s| s == status) => {}
+        // Nothing matched, so the implicit default applies
+        None if status == default_status => {}
+        _ => {
+            // A condition (or the default) can be shared by several configurations, so only
+            // replace its value if it is exactly this run's condition. Otherwise add a more
+            // specific condition ahead of it.
+            let idx = matching.unwrap_or(key.values.len());
+            let existing = key
+                .values
+                .get(idx)
+                .and_then(|value| value.condition.as_ref());
+            match run_condition(properties) {
+                Some(condition) if existing != Some(&condition) => {
+                    key.values.insert(
+                        idx,
+                        ConditionalValue {
+                            condition: Some(condition),
+                            value: new_value,
+                        },
+                    );
+                }
+                _ => match key.values.get_mut(idx) {
+                    Some(value) => value.value = new_value,
+                    None => key.values.push(ConditionalValue {
+                        condition: None,
+                        value: new_value,
+                    }),
+                },
+            }
+        }
+    }
+
+    // An explicit default which is the same as the implicit default is redundant, as are
+    // conditions that come after the default
+    let default_value = Value::Atom(default_status.to_string());
+    if let Some(idx) = key.values.iter().position(|v| v.condition.is_none()) {
+        key.values.truncate(idx + 1);
+        if key.values[idx].value == default_value {
+            key.values.pop();
+        }
+    }
+    if key.values.is_empty() {
+        section.keys.retain(|key| key.name != "expected");
+    }
+}
+
+/// The `run_info` properties that conditions added by [`Metadata::update`] test
+const UPDATE_PROPERTIES: &[&str] = &["os", "version", "processor", "bits", "debug"];
+
+/// A condition that matches runs with the same [`UPDATE_PROPERTIES`] as this one, such as
+/// `(os == "linux") and not debug`. `None` if the run has none of the properties.
+fn run_condition(properties: &Map<String, serde_json::Value>) -> Option<Expr> {
+    UPDATE_PROPERTIES
+        .iter()
+        .filter_map(|&name| {
+            let ident = Box::new(Expr::Ident(name.to_string()));
+            let literal = match properties.get(name)? {
+                serde_json::Value::Bool(true) => return Some(*ident),
+                serde_json::Value::Bool(false) => return Some(Expr::Not(ident)),
+                serde_json::Value::String(s) => Expr::Str(s.clone()),
+                serde_json::Value::Number(n) => Expr::Number(n.as_f64()?),
+                _ => return None,
+            };
+            Some(Expr::Eq(ident, Box::new(literal)))
+        })
+        .reduce(|lhs, rhs| Expr::And(Box::new(lhs), Box::new(rhs)))
+}
@@ -0,0 +1,371 @@
+//! Reading and writing the indentation-based `.ini`-like format used by wptrunner metadata
+use std::fmt::{self, Write as _};
+
+use super::expr::Expr;
+use super::{ConditionalValue, ExpectationFile, Key, Section, Value};
+use crate::{Error, ErrorKind};
+
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+}
+
+fn error(line: usize, message: impl Into<String>) -> Error {
+    Error::from(ErrorKind::Ini {
+        line,
+        message: message.into(),
+    })
+}
+
+pub(super) fn parse(s: &str) -> Result<ExpectationFile, Error> {
+    let lines = s
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let text = line.trim_start();
+            let blank = text.is_empty() || text.starts_with('#');
+            (!blank).then(|| Line {
+                number: idx + 1,
+                indent: line.len() - text.len(),
+                text: text.trim_end(),
+            })
+        })
+        .collect();
+
+    let mut parser = Parser { lines, pos: 0 };
+    let (keys, sections) = parser.parse_body(None)?;
+    Ok(ExpectationFile { keys, sections })
+}
+
+impl Parser<'_> {
+    /// Parse the keys and sections that are indented more than `parent_indent`
+    fn parse_body(
+        &mut self,
+        parent_indent: Option<usize>,
+    ) -> Result<(Vec<Key>, Vec<Section>), Error> {
+        let mut keys = Vec::new();
+        let mut sections = Vec::new();
+        let mut block_indent = None;
+
+        while let Some(line) = self.lines.get(self.pos) {
+            if parent_indent.is_some_and(|parent| line.indent <= parent) {
+                break;
+            }
+            let indent = *block_indent.get_or_insert(line.indent);
+            if line.indent != indent {
+                return Err(error(line.number, "unexpected indentation"));
+            }
+            self.pos += 1;
+
+            if let Some(heading) = line.text.strip_prefix('[') {
+                let name = parse_heading(heading).map_err(|msg| error(line.number, msg))?;
+                let (keys, children) = self.parse_body(Some(indent))?;
+                sections.push(Section {
+                    name,
+                    keys,
+                    sections: children,
+                });
+            } else {
+                let (number, indent, text) = (line.number, line.indent, line.text);
+                keys.push(self.parse_key(number, indent, text)?);
+            }
+        }
+
+        Ok((keys, sections))
+    }
+
+    /// Parse `key: value`, or `key:` followed by a block of conditional values
+    fn parse_key(&mut self, number: usize, indent: usize, text: &str) -> Result<Key, Error> {
+        let Some((name, value)) = text.split_once(':') else {
+            return Err(error(number, "expected a section heading or a key"));
+        };
+        let name = name.trim().to_string();
+        let value = strip_comment(value).trim();
+
+        if !value.is_empty() {
+            let value = parse_value(value).map_err(|msg| error(number, msg))?;
+            return Ok(Key {
+                name,
+                values: vec![ConditionalValue {
+                    condition: None,
+                    value,
+                }],
+            });
+        }
+
+        let mut values = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent <= indent {
+                break;
+            }
+            self.pos += 1;
+            let text = strip_comment(line.text).trim();
+            let value = match text.strip_prefix("if ") {
+                Some(conditional) => {
+                    let split = find_unquoted(conditional, ':')
+                        .ok_or_else(|| error(line.number, "expected ':' after condition"))?;
+                    let condition = Expr::parse(&conditional[..split])
+                        .map_err(|msg| error(line.number, msg))?;
+                    let value = parse_value(conditional[split + 1..].trim())
+                        .map_err(|msg| error(line.number, msg))?;
+                    ConditionalValue {
+                        condition: Some(condition),
+                        value,
+                    }
+                }
+                None => ConditionalValue {
+                    condition: None,
+                    value: parse_value(text).map_err(|msg| error(line.number, msg))?,
+                },
+            };
+            values.push(value);
+        }
+
+        if values.is_empty() {
+            return Err(error(number, format!("no value for key '{name}'")));
+        }
+        Ok(Key { name, values })
+    }
+}
+
+/// Parse the rest of a `[heading]` line (after the opening bracket)
+fn parse_heading(s: &str) -> Result<String, String> {
+    let mut name = String::new();
+    let mut chars = s.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => name.push(unescape_char(chars.next().map(|(_, c)| c))?),
+            ']' => {
+                let rest = strip_comment(&s[idx + 1..]);
+                if !rest.trim().is_empty() {
+                    return Err(format!("unexpected '{}' after heading", rest.trim()));
+                }
+                return Ok(name);
+            }
+            c => name.push(c),
+        }
+    }
+    Err(String::from("expected ']' at end of heading"))
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    let Some(list) = s.strip_prefix('[') else {
+        return parse_atom(s).map(Value::Atom);
+    };
+    let end = find_unquoted(list, ']').ok_or("expected ']' at end of list")?;
+    if !list[end + 1..].trim().is_empty() {
+        return Err(format!(
+            "unexpected '{}' after list",
+            list[end + 1..].trim()
+        ));
+    }
+    let list = list[..end].trim();
+    if list.is_empty() {
+        return Ok(Value::List(Vec::new()));
+    }
+
+    let mut items = Vec::new();
+    let mut rest = list;
+    loop {
+        let end = find_unquoted(rest, ',').unwrap_or(rest.len());
+        items.push(parse_atom(rest[..end].trim())?);
+        if end == rest.len() {
+            break;
+        }
+        rest = &rest[end + 1..];
+    }
+    Ok(Value::List(items))
+}
+
+fn parse_atom(s: &str) -> Result<String, String> {
+    if s.starts_with(['"', '\'']) {
+        let (value, len) = unquote(s)?;
+        if !s[len..].trim().is_empty() {
+            return Err(format!("unexpected '{}' after string", s[len..].trim()));
+        }
+        Ok(value)
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+/// Parse a quoted string at the start of `s`, returning the string and the number of bytes
+/// that it spanned (including the quotes)
+pub(super) fn unquote(s: &str) -> Result<(String, usize), String> {
+    let mut chars = s.char_indices();
+    let (_, quote) = chars.next().ok_or("expected a string")?;
+    let mut value = String::new();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => value.push(unescape_char(chars.next().map(|(_, c)| c))?),
+            c if c == quote => return Ok((value, idx + 1)),
+            c => value.push(c),
+        }
+    }
+    Err(String::from("unterminated string"))
+}
+
+fn unescape_char(c: Option<char>) -> Result<char, String> {
+    match c {
+        Some('n') => Ok('\n'),
+        Some('r') => Ok('\r'),
+        Some('t') => Ok('\t'),
+        Some('0') => Ok('\0'),
+        Some(c) => Ok(c),
+        None => Err(String::from("unexpected end of line after '\\'")),
+    }
+}
+
+/// Escape backslashes, control characters and `special` (a quote or closing bracket)
+pub(super) fn escape(s: &str, special: char) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' => escaped.push_str("\\0"),
+            c if c == special => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Find the first occurrence of `needle` which isn't inside a quoted string. Quotes only
+/// start a string at the beginning of a token, so bare values such as `don't` are allowed.
+fn find_unquoted(s: &str, needle: char) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut prev = None;
+    for (idx, c) in s.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == needle => return Some(idx),
+            None if (c == '"' || c == '\'')
+                && prev.map_or(true, |p: char| p.is_whitespace() || "[,(:=".contains(p)) =>
+            {
+                quote = Some(c)
+            }
+            None => {}
+        }
+        prev = Some(c);
+    }
+    None
+}
+
+/// Remove a trailing comment. A `#` only starts a comment at the start of the line or after
+/// whitespace, so that urls with fragments can be used as bare values.
+fn strip_comment(s: &str) -> &str {
+    let mut start = 0;
+    while let Some(idx) = find_unquoted(&s[start..], '#') {
+        let idx = start + idx;
+        if s[..idx]
+            .chars()
+            .next_back()
+            .map_or(true, char::is_whitespace)
+        {
+            return &s[..idx];
+        }
+        start = idx + 1;
+    }
+    s
+}
+
+/// Write an atom, quoting it if it would otherwise be parsed differently
+fn write_atom(out: &mut String, atom: &str, in_list: bool) {
+    let needs_quotes = atom.is_empty()
+        || atom.trim() != atom
+        || atom.starts_with(['"', '\'', '['])
+        || atom.starts_with("if ")
+        || atom.starts_with('#')
+        || atom.contains(" #")
+        || atom.contains(['"', '\'', '\n', '\r', '\t', '\\', '\0'])
+        || (in_list && atom.contains([',', ']']));
+    if needs_quotes {
+        let _ = write!(out, "\"{}\"", escape(atom, '"'));
+    } else {
+        out.push_str(atom);
+    }
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Atom(atom) => write_atom(out, atom, false),
+        Value::List(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                write_atom(out, item, true);
+            }
+            out.push(']');
+        }
+    }
+}
+
+fn write_keys(out: &mut String, keys: &[Key], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for key in keys {
+        let _ = write!(out, "{indent}{}:", key.name);
+        match key.values.as_slice() {
+            [ConditionalValue {
+                condition: None,
+                value,
+            }] => {
+                out.push(' ');
+                write_value(out, value);
+                out.push('\n');
+            }
+            values => {
+                out.push('\n');
+                for value in values {
+                    let _ = write!(out, "{indent}  ");
+                    if let Some(condition) = &value.condition {
+                        let _ = write!(out, "if {condition}: ");
+                    }
+                    write_value(out, &value.value);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+}
+
+fn write_section(out: &mut String, section: &Section, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let _ = writeln!(out, "{indent}[{}]", escape(&section.name, ']'));
+    write_keys(out, &section.keys, depth + 1);
+    for child in &section.sections {
+        write_section(out, child, depth + 1);
+        out.push('\n');
+    }
+}
+
+impl fmt::Display for ExpectationFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        write_keys(&mut out, &self.keys, 0);
+        for (idx, section) in self.sections.iter().enumerate() {
+            if idx > 0 || !self.keys.is_empty() {
+                out.push('\n');
+            }
+            write_section(&mut out, section, 0);
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,347 @@
+//! Interning of test and subtest names, so that many runs can be held in memory at once.
+//!
+//! An [`Interner`] hands out a single shared [`SmolStr`] for each distinct name. Names of up
+//! to 23 bytes are stored inline and never allocate, and longer names are reference counted,
+//! so each one is only allocated once no matter how many runs it appears in. Names are still
+//! compared and looked up by their contents.
+//!
+//! [`InternedScores`] is the interned equivalent of [`WptScores`].
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::hash::{BuildHasher, RandomState};
+use std::io::Read;
+use std::sync::Mutex;
+
+use indexmap::IndexMap;
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::Deserializer;
+use smol_str::SmolStr;
+
+use crate::score::{score_wpt_report_against_with, ScoringPolicy};
+use crate::servo_test_scores::{test_score_counts, SubtestScore, WptScores};
+use crate::wpt_report::WptRunInfo;
+use crate::{
+    AreaScores, Error, HasRunInfo, ScorableReport, SubtestCounts, SubtestNameAndResult,
+    TestResultIter,
+};
+
+/// The longest string that `SmolStr` stores inline
+const INLINE_LEN: usize = 23;
+const SHARD_COUNT: usize = 16;
+
+/// A thread-safe set of names (see the [module docs](self))
+pub struct Interner {
+    hasher: RandomState,
+    shards: [Mutex<HashSet<SmolStr>>; SHARD_COUNT],
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self {
+            hasher: RandomState::new(),
+            shards: Default::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the shared copy of `name`, adding it to the interner if needed
+    pub fn intern(&self, name: &str) -> SmolStr {
+        if name.len() <= INLINE_LEN {
+            return SmolStr::new(name);
+        }
+        let mut shard = self.shard(name).lock().unwrap();
+        if let Some(interned) = shard.get(name) {
+            return interned.clone();
+        }
+        let interned = SmolStr::new(name);
+        shard.insert(interned.clone());
+        interned
+    }
+
+    /// Get the shared copy of `name` if it has been interned
+    pub fn get(&self, name: &str) -> Option<SmolStr> {
+        if name.len() <= INLINE_LEN {
+            return Some(SmolStr::new(name));
+        }
+        self.shard(name).lock().unwrap().get(name).cloned()
+    }
+
+    /// The number of (heap allocated) names that have been interned
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard(&self, name: &str) -> &Mutex<HashSet<SmolStr>> {
+        &self.shards[self.hasher.hash_one(name) as usize % SHARD_COUNT]
+    }
+}
+
+/// The same as [`WptScores`], but with interned test and subtest names
+#[derive(Debug)]
+pub struct InternedScores {
+    pub run_info: WptRunInfo,
+    pub test_scores: IndexMap<SmolStr, InternedTestScore>,
+}
+
+#[derive(Debug)]
+pub struct InternedTestScore {
+    pub score: u32,
+    pub subtests: IndexMap<SmolStr, SubtestScore>,
+}
+
+impl InternedScores {
+    /// Parse a Servo scores file, interning names with `interner`. Names are interned as they
+    /// are read, so duplicates are never allocated.
+    pub fn from_str_with(s: &str, interner: &Interner) -> Result<Self, Error> {
+        crate::error::from_str_seed(s, ScoresSeed(interner))
+    }
+
+    /// Parse a Servo scores file from a reader, interning names with `interner`
+    pub fn from_reader_with(reader: impl Read, interner: &Interner) -> Result<Self, Error> {
+        crate::error::from_reader_seed(reader, ScoresSeed(interner))
+    }
+
+    /// Intern the names of an already parsed scores file
+    pub fn from_scores(scores: WptScores, interner: &Interner) -> Self {
+        let test_scores = scores
+            .test_scores
+            .into_iter()
+            .map(|(name, test)| {
+                let subtests = test
+                    .subtests
+                    .into_iter()
+                    .map(|(name, subtest)| (interner.intern(&name), subtest))
+                    .collect();
+                let test = InternedTestScore {
+                    score: test.score,
+                    subtests,
+                };
+                (interner.intern(&name), test)
+            })
+            .collect();
+        Self {
+            run_info: scores.run_info,
+            test_scores,
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl ScorableReport for InternedScores {
+    type TestResultIter<'a> = (&'a SmolStr, &'a InternedTestScore) where Self: 'a;
+    fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
+        self.test_scores.iter()
+    }
+    fn get(&self, name: &str) -> Option<Self::TestResultIter<'_>> {
+        self.test_scores.get_key_value(name)
+    }
+    fn is_keyed(&self) -> bool {
+        true
+    }
+}
+
+impl HasRunInfo for InternedScores {
+    fn run_info(&self) -> &WptRunInfo {
+        &self.run_info
+    }
+}
+
+impl TestResultIter for (&SmolStr, &InternedTestScore) {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn subtest_counts(&self) -> SubtestCounts {
+        test_score_counts(self.1.score, self.1.subtests.values())
+    }
+
+    fn subtest_exist_and_passes(&self, name: &str) -> bool {
+        self.1.subtests.get(name).is_some_and(|s| s.score > 0)
+    }
+
+    fn iter_subtests_results(&self) -> impl Iterator<Item = SubtestNameAndResult<'_>> {
+        self.1
+            .subtests
+            .iter()
+            .map(|(name, s)| SubtestNameAndResult {
+                name,
+                passes: s.score > 0,
+            })
+    }
+
+    fn test_passes(&self) -> bool {
+        self.1.score > 0
+    }
+}
+
+impl InternedScores {
+    /// Scores a test run against a reference test run (see [`WptScores::score_against`])
+    pub fn score_against(&self, reference: &InternedScores) -> BTreeMap<String, AreaScores> {
+        self.score_against_with(reference, &ScoringPolicy::default())
+    }
+
+    /// Scores a test run against a reference test run, weighting tests with `policy`
+    /// (see [`score_wpt_report_against_with`])
+    pub fn score_against_with(
+        &self,
+        reference: &InternedScores,
+        policy: &ScoringPolicy,
+    ) -> BTreeMap<String, AreaScores> {
+        score_wpt_report_against_with(self, reference, policy)
+    }
+}
+
+/// Deserializes a name, interning it
+struct NameSeed<'a>(&'a Interner);
+
+impl<'de> DeserializeSeed<'de> for NameSeed<'_> {
+    type Value = SmolStr;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<SmolStr, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for NameSeed<'_> {
+    type Value = SmolStr;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+    fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<SmolStr, E> {
+        Ok(self.0.intern(s))
+    }
+}
+
+/// Deserializes an [`InternedScores`]
+struct ScoresSeed<'a>(&'a Interner);
+
+impl<'de> DeserializeSeed<'de> for ScoresSeed<'_> {
+    type Value = InternedScores;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ScoresSeed<'_> {
+    type Value = InternedScores;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a scores object")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut run_info = None;
+        let mut test_scores = None;
+        while let Some(key) = map.next_key::<SmolStr>()? {
+            match key.as_str() {
+                "run_info" => run_info = Some(map.next_value()?),
+                "test_scores" => test_scores = Some(map.next_value_seed(TestScoresSeed(self.0))?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(InternedScores {
+            run_info: run_info.ok_or_else(|| serde::de::Error::missing_field("run_info"))?,
+            test_scores: test_scores
+                .ok_or_else(|| serde::de::Error::missing_field("test_scores"))?,
+        })
+    }
+}
+
+struct TestScoresSeed<'a>(&'a Interner);
+
+impl<'de> DeserializeSeed<'de> for TestScoresSeed<'_> {
+    type Value = IndexMap<SmolStr, InternedTestScore>;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TestScoresSeed<'_> {
+    type Value = IndexMap<SmolStr, InternedTestScore>;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of test names to scores")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut tests = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(name) = map.next_key_seed(NameSeed(self.0))? {
+            let test = map.next_value_seed(TestScoreSeed(self.0))?;
+            tests.insert(name, test);
+        }
+        Ok(tests)
+    }
+}
+
+struct TestScoreSeed<'a>(&'a Interner);
+
+impl<'de> DeserializeSeed<'de> for TestScoreSeed<'_> {
+    type Value = InternedTestScore;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TestScoreSeed<'_> {
+    type Value = InternedTestScore;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a test score object")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut score = None;
+        let mut subtests = None;
+        while let Some(key) = map.next_key::<SmolStr>()? {
+            match key.as_str() {
+                "score" => score = Some(map.next_value()?),
+                "subtests" => subtests = Some(map.next_value_seed(SubtestsSeed(self.0))?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(InternedTestScore {
+            score: score.ok_or_else(|| serde::de::Error::missing_field("score"))?,
+            subtests: subtests.ok_or_else(|| serde::de::Error::missing_field("subtests"))?,
+        })
+    }
+}
+
+struct SubtestsSeed<'a>(&'a Interner);
+
+impl<'de> DeserializeSeed<'de> for SubtestsSeed<'_> {
+    type Value = IndexMap<SmolStr, SubtestScore>;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SubtestsSeed<'_> {
+    type Value = IndexMap<SmolStr, SubtestScore>;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of subtest names to scores")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut subtests = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(name) = map.next_key_seed(NameSeed(self.0))? {
+            subtests.insert(name, map.next_value()?);
+        }
+        Ok(subtests)
+    }
+}
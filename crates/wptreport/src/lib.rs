@@ -1,14 +1,30 @@
 pub mod aggregate;
+pub mod bsf;
+pub mod error;
+pub mod expectations;
+pub mod intern;
+pub mod manifest;
 pub mod merge;
 pub mod reports;
 pub mod score;
 pub mod summarize;
+#[cfg(feature = "wpt-fyi")]
+pub mod wpt_fyi_api;
 
 use std::{iter::Sum, ops::Add};
 
-use reports::wpt_report::WptRunInfo;
-pub use reports::{score_summary, servo_test_scores, wpt_report};
-pub use score::score_wpt_report;
+pub use error::{from_reader, from_slice, from_str, Error, ErrorKind};
+#[cfg(feature = "arrow")]
+pub use reports::parquet;
+use reports::wpt_report::{TestResult, TestStatus, WptRunInfo};
+pub use reports::{
+    any_report, csv, interop_summary, junit, mozlog, score_summary, scores_history,
+    servo_test_scores, wpt_fyi_summary, wpt_report, wpt_report_stream,
+};
+pub use score::{
+    score_wpt_report, score_wpt_report_against, score_wpt_report_against_with,
+    score_wpt_report_with, ScoringPolicy,
+};
 use serde::{Deserialize, Serialize};
 
 pub trait HasRunInfo {
@@ -19,6 +35,19 @@ pub trait HasRunInfo {
 pub trait ScorableReport {
     type TestResultIter<'b>: TestResultIter + 'b where Self: 'b;
     fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>>;
+
+    /// The result of the test called `name`. Formats that are keyed by test name override
+    /// this (and [`is_keyed`](Self::is_keyed)) to look the test up in their own map. The
+    /// default searches [`results`](Self::results), so reports that aren't keyed are indexed
+    /// by the caller instead when many tests need to be looked up.
+    fn get(&self, name: &str) -> Option<Self::TestResultIter<'_>> {
+        self.results().find(|test| test.name() == name)
+    }
+
+    /// Whether [`get`](Self::get) is a lookup rather than a search of every result
+    fn is_keyed(&self) -> bool {
+        false
+    }
 }
 
 pub trait TestResultIter {
@@ -27,6 +56,23 @@ pub trait TestResultIter {
 
     fn subtest_exist_and_passes(&self, name: &str) -> bool;
     fn iter_subtests_results(&self) -> impl Iterator<Item = SubtestNameAndResult<'_>>;
+
+    /// Whether the test's own status is a pass, regardless of its subtests. Formats which
+    /// don't record the test's status treat a test as passing if it has no subtests and passes.
+    fn test_passes(&self) -> bool {
+        self.iter_subtests_results().next().is_none() && self.subtest_counts().pass > 0
+    }
+
+    /// The full wptreport result, for formats that record statuses rather than just whether
+    /// each test and subtest passed
+    fn as_test_result(&self) -> Option<&TestResult> {
+        None
+    }
+
+    /// The test's own status, for formats that record it
+    fn status(&self) -> Option<&TestStatus> {
+        self.as_test_result().map(|result| &result.status)
+    }
 }
 
 pub struct SubtestNameAndResult<'a> {
@@ -68,6 +114,20 @@ impl AreaScores {
     pub fn servo_score(&self) -> f64 {
         self.pass_fraction_sum
     }
+
+    /// Add a test with the given subtest counts to the scores
+    pub fn add_test(&mut self, counts: SubtestCounts) {
+        self.tests.pass += counts.all_passing() as u32;
+        self.tests.total += 1;
+        self.subtests.pass += counts.pass;
+        self.subtests.total += counts.total;
+        // The sum of the interop scores for each individual test, but not
+        // divided by the total number of tests
+        self.interop_score_sum += counts.passes_per_1000() as u64;
+        // The sum of the "fraction of passing subtests" for each individual test,
+        // but not divided by the total number of tests
+        self.pass_fraction_sum += counts.pass_fraction();
+    }
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -0,0 +1,427 @@
+//! The `MANIFEST.json` file (version 8) generated by `wpt manifest`, which lists every test in
+//! a WPT checkout.
+//!
+//! Items are stored in a tree of directories under each test type, with each file mapping to
+//! `[hash, [url, ...extras], ...]` (one entry per test generated from the file, where a `null`
+//! url means the url is the file's path).
+use indexmap::IndexMap;
+use serde::de::{self, DeserializeSeed, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use smol_str::SmolStr;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::Read;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::wpt_report::{TestResult, TestStatus};
+use crate::{Error, ScorableReport, SubtestCounts, SubtestNameAndResult, TestResultIter};
+
+/// The only manifest version that can be parsed
+pub const MANIFEST_VERSION: u32 = 8;
+
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    pub url_base: String,
+    /// Tests keyed by test id (the url path used as the test name in reports)
+    pub tests: IndexMap<String, ManifestTest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestTest {
+    /// The path of the file that the test is generated from, relative to the WPT root
+    pub path: String,
+    pub test_type: TestType,
+    /// The query string and/or fragment which distinguishes variants of the same test file
+    /// (e.g. "?1-10"), if any
+    pub variant: Option<String>,
+    pub timeout: Timeout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TestType {
+    Testharness,
+    Reftest,
+    PrintReftest,
+    Crashtest,
+    Wdspec,
+    Manual,
+    Visual,
+    Unknown(SmolStr),
+}
+
+impl TestType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TestType::Testharness => "testharness",
+            TestType::Reftest => "reftest",
+            TestType::PrintReftest => "print-reftest",
+            TestType::Crashtest => "crashtest",
+            TestType::Wdspec => "wdspec",
+            TestType::Manual => "manual",
+            TestType::Visual => "visual",
+            TestType::Unknown(test_type) => test_type,
+        }
+    }
+
+    /// Whether tests of this type are run by wptrunner (and so are expected to appear in reports)
+    pub fn is_automated(&self) -> bool {
+        match self {
+            TestType::Testharness
+            | TestType::Reftest
+            | TestType::PrintReftest
+            | TestType::Crashtest
+            | TestType::Wdspec => true,
+            TestType::Manual | TestType::Visual | TestType::Unknown(_) => false,
+        }
+    }
+}
+
+impl From<&str> for TestType {
+    fn from(value: &str) -> Self {
+        match value {
+            "testharness" => TestType::Testharness,
+            "reftest" => TestType::Reftest,
+            "print-reftest" => TestType::PrintReftest,
+            "crashtest" => TestType::Crashtest,
+            "wdspec" => TestType::Wdspec,
+            "manual" => TestType::Manual,
+            "visual" => TestType::Visual,
+            other => TestType::Unknown(SmolStr::from(other)),
+        }
+    }
+}
+
+impl fmt::Display for TestType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for TestType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TestType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(TestType::from(SmolStr::deserialize(deserializer)?.as_str()))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Timeout {
+    #[default]
+    Normal,
+    Long,
+}
+
+impl Manifest {
+    /// Parse a manifest from a reader containing JSON
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        let raw: RawManifest = crate::from_reader(reader)?;
+        raw.into_manifest()
+    }
+
+    pub fn get(&self, test_id: &str) -> Option<&ManifestTest> {
+        self.tests.get(test_id)
+    }
+
+    /// Wrap `report` so that automated tests which are in the manifest but missing from the
+    /// report are scored as failures (with a single failing subtest)
+    pub fn fill_missing<'a, R: ScorableReport>(&'a self, report: &'a R) -> WithMissingTests<'a, R> {
+        WithMissingTests {
+            manifest: self,
+            report,
+        }
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw: RawManifest = crate::from_str(s)?;
+        raw.into_manifest()
+    }
+}
+
+/// A report with the tests that it is missing (relative to a [`Manifest`]) added as failures.
+/// Created by [`Manifest::fill_missing`].
+pub struct WithMissingTests<'a, R> {
+    manifest: &'a Manifest,
+    report: &'a R,
+}
+
+pub enum FilledTestResult<'a, T> {
+    /// A test result from the report
+    Present(T),
+    /// The id of a test which is in the manifest but not in the report
+    Missing(&'a str),
+}
+
+#[rustfmt::skip]
+impl<R: ScorableReport> ScorableReport for WithMissingTests<'_, R> {
+    type TestResultIter<'b> = FilledTestResult<'b, R::TestResultIter<'b>> where Self: 'b;
+    fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
+        // Tests are marked as seen while the report is iterated (which may only be possible
+        // once), and the missing tests are only determined after that has finished
+        let seen = Rc::new(RefCell::new(vec![false; self.manifest.tests.len()]));
+
+        let present_seen = Rc::clone(&seen);
+        let present = self.report.results().map(move |test| {
+            if let Some(idx) = self.manifest.tests.get_index_of(test.name()) {
+                present_seen.borrow_mut()[idx] = true;
+            }
+            FilledTestResult::Present(test)
+        });
+
+        let missing = std::iter::once(()).flat_map(move |()| {
+            let seen = seen.take();
+            self.manifest
+                .tests
+                .iter()
+                .zip(seen)
+                .filter(|((_, test), seen)| !seen && test.test_type.is_automated())
+                .map(|((id, _), _)| FilledTestResult::Missing(id.as_str()))
+        });
+
+        present.chain(missing)
+    }
+    fn get(&self, name: &str) -> Option<Self::TestResultIter<'_>> {
+        if let Some(test) = self.report.get(name) {
+            return Some(FilledTestResult::Present(test));
+        }
+        let (id, test) = self.manifest.tests.get_key_value(name)?;
+        test.test_type.is_automated().then_some(FilledTestResult::Missing(id.as_str()))
+    }
+    fn is_keyed(&self) -> bool {
+        self.report.is_keyed()
+    }
+}
+
+impl<T: TestResultIter> TestResultIter for FilledTestResult<'_, T> {
+    fn name(&self) -> &str {
+        match self {
+            FilledTestResult::Present(test) => test.name(),
+            FilledTestResult::Missing(name) => name,
+        }
+    }
+
+    fn subtest_counts(&self) -> SubtestCounts {
+        match self {
+            FilledTestResult::Present(test) => test.subtest_counts(),
+            FilledTestResult::Missing(_) => SubtestCounts { pass: 0, total: 1 },
+        }
+    }
+
+    fn subtest_exist_and_passes(&self, name: &str) -> bool {
+        match self {
+            FilledTestResult::Present(test) => test.subtest_exist_and_passes(name),
+            FilledTestResult::Missing(_) => false,
+        }
+    }
+
+    fn iter_subtests_results(&self) -> impl Iterator<Item = SubtestNameAndResult<'_>> {
+        let subtests = match self {
+            FilledTestResult::Present(test) => Some(test.iter_subtests_results()),
+            FilledTestResult::Missing(_) => None,
+        };
+        subtests.into_iter().flatten()
+    }
+
+    fn test_passes(&self) -> bool {
+        match self {
+            FilledTestResult::Present(test) => test.test_passes(),
+            FilledTestResult::Missing(_) => false,
+        }
+    }
+
+    fn as_test_result(&self) -> Option<&TestResult> {
+        match self {
+            FilledTestResult::Present(test) => test.as_test_result(),
+            FilledTestResult::Missing(_) => None,
+        }
+    }
+    fn status(&self) -> Option<&TestStatus> {
+        match self {
+            FilledTestResult::Present(test) => test.status(),
+            FilledTestResult::Missing(_) => None,
+        }
+    }
+}
+
+/// The manifest as stored on disk. The url base may come after the items, so test ids are
+/// only built once the whole file has been read.
+#[derive(Deserialize)]
+struct RawManifest {
+    version: u32,
+    #[serde(default = "default_url_base")]
+    url_base: String,
+    items: RawItems,
+}
+
+fn default_url_base() -> String {
+    String::from("/")
+}
+
+struct RawItem {
+    test_type: TestType,
+    path: String,
+    url: Option<String>,
+    timeout: Timeout,
+}
+
+impl RawManifest {
+    fn into_manifest(self) -> Result<Manifest, Error> {
+        if self.version != MANIFEST_VERSION {
+            let err = serde_json::Error::custom(format!(
+                "unsupported manifest version {} (expected {MANIFEST_VERSION})",
+                self.version
+            ));
+            return Err(Error::json(String::from("version"), err));
+        }
+
+        let url_base = self.url_base;
+        let tests = self
+            .items
+            .0
+            .into_iter()
+            .map(|item| {
+                let url = item.url.as_deref().unwrap_or(&item.path);
+                let id = format!("{url_base}{}", url.trim_start_matches('/'));
+                let variant = id.find(['?', '#']).map(|idx| id[idx..].to_string());
+                let test = ManifestTest {
+                    path: item.path,
+                    test_type: item.test_type,
+                    variant,
+                    timeout: item.timeout,
+                };
+                (id, test)
+            })
+            .collect();
+
+        Ok(Manifest { url_base, tests })
+    }
+}
+
+/// All of the items in the manifest, flattened out of the directory tree
+struct RawItems(Vec<RawItem>);
+
+impl<'de> Deserialize<'de> for RawItems {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ItemsVisitor;
+
+        impl<'de> Visitor<'de> for ItemsVisitor {
+            type Value = RawItems;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of test types to directories")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut items = Vec::new();
+                while let Some(test_type) = map.next_key::<SmolStr>()? {
+                    // Support files aren't tests
+                    if test_type == "support" {
+                        map.next_value::<IgnoredAny>()?;
+                        continue;
+                    }
+                    map.next_value_seed(NodeSeed {
+                        test_type: &TestType::from(test_type.as_str()),
+                        path: String::new(),
+                        items: &mut items,
+                    })?;
+                }
+                Ok(RawItems(items))
+            }
+        }
+
+        deserializer.deserialize_map(ItemsVisitor)
+    }
+}
+
+/// A node in the directory tree: either a directory (a map of names to nodes) or a file
+/// (a list of a hash followed by the tests generated from the file)
+struct NodeSeed<'a> {
+    test_type: &'a TestType,
+    path: String,
+    items: &'a mut Vec<RawItem>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a directory or a list of manifest items")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(name) = map.next_key::<String>()? {
+            let path = match self.path.is_empty() {
+                true => name,
+                false => format!("{}/{name}", self.path),
+            };
+            map.next_value_seed(NodeSeed {
+                test_type: self.test_type,
+                path,
+                items: self.items,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        // The hash of the file's contents
+        seq.next_element::<IgnoredAny>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &"a hash followed by items"))?;
+
+        // Each item is `[url, ...extras]` where the last extra (if any) is an options object
+        while let Some(item) = seq.next_element::<Vec<serde_json::Value>>()? {
+            let mut item = item.into_iter();
+            let url = match item.next() {
+                Some(serde_json::Value::String(url)) => Some(url),
+                Some(serde_json::Value::Null) | None => None,
+                Some(other) => {
+                    let unexpected = de::Unexpected::Other(json_type(&other));
+                    return Err(de::Error::invalid_type(unexpected, &"a url or null"));
+                }
+            };
+            let timeout = match item
+                .last()
+                .as_ref()
+                .and_then(|options| options.get("timeout"))
+            {
+                Some(timeout) if timeout == "long" => Timeout::Long,
+                _ => Timeout::Normal,
+            };
+            self.items.push(RawItem {
+                test_type: self.test_type.clone(),
+                path: self.path.clone(),
+                url,
+                timeout,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
@@ -1,6 +1,11 @@
 use std::collections::BTreeMap;
+use std::io::BufRead;
+
+use indexmap::IndexMap;
 
 use crate::wpt_report::{TestResult, WptReport, WptRunInfo};
+use crate::wpt_report_stream::WptReportStream;
+use crate::{Error, ErrorKind};
 
 /// Allows multiple chunks of a WPT report format to be merged into a single WPT report
 /// The input and output formats are the same, but output contains the test results from all chunks
@@ -9,6 +14,7 @@ pub struct WptReportMerger {
     time_start: u64,
     time_end: u64,
     scores: BTreeMap<String, TestResult>,
+    extra: IndexMap<String, serde_json::Value>,
 }
 
 impl Default for WptReportMerger {
@@ -24,30 +30,96 @@ impl WptReportMerger {
             time_start: u64::MAX,
             time_end: 0,
             scores: BTreeMap::new(),
+            extra: IndexMap::new(),
         }
     }
 
-    pub fn add_chunk(&mut self, chunk: WptReport) {
-        // Check that run info matches
-        match &mut self.run_info {
-            // If this is the first chunk then just store the run info
-            None => {
-                self.run_info = Some(chunk.run_info);
-            }
-            // Else check that it matches
+    /// Add a chunk to the merged report. Fails if the chunk's `run_info` differs from that of
+    /// previously added chunks.
+    pub fn add_chunk(&mut self, chunk: WptReport) -> Result<(), Error> {
+        self.add_header(
+            chunk.run_info,
+            chunk.time_start,
+            chunk.time_end,
+            chunk.extra,
+        )?;
+
+        for result in chunk.results.into_iter() {
+            self.scores.insert(result.test.clone(), result);
+        }
+        Ok(())
+    }
+
+    /// Add a chunk read from a stream. If the chunk's `run_info` precedes its results (as it
+    /// does in wptrunner's reports), results are merged as they are read, so the chunk is never
+    /// held in memory in its entirety. Otherwise results are held until the `run_info` has been
+    /// checked. Either way, a chunk whose `run_info` doesn't match isn't merged at all.
+    pub fn add_chunk_stream<R: BufRead>(
+        &mut self,
+        mut stream: WptReportStream<R>,
+    ) -> Result<(), Error> {
+        let checked = match stream.run_info() {
             Some(run_info) => {
-                if *run_info != chunk.run_info {
-                    panic!("run_info doesn't match")
-                }
+                self.check_run_info(run_info)?;
+                true
             }
-        }
+            None => false,
+        };
 
-        self.time_start = self.time_start.min(chunk.time_start);
-        self.time_end = self.time_end.max(chunk.time_end);
+        let mut held = Vec::new();
+        for result in &mut stream {
+            let result = result?;
+            if checked {
+                self.scores.insert(result.test.clone(), result);
+            } else {
+                held.push(result);
+            }
+        }
 
-        for result in chunk.results.into_iter() {
+        let header = stream.finish()?;
+        self.add_header(
+            header.run_info,
+            header.time_start,
+            header.time_end,
+            header.extra,
+        )?;
+        for result in held {
             self.scores.insert(result.test.clone(), result);
         }
+        Ok(())
+    }
+
+    /// Check that a chunk's run info matches that of previously added chunks
+    fn check_run_info(&self, run_info: &WptRunInfo) -> Result<(), Error> {
+        match &self.run_info {
+            Some(existing) if existing != run_info => {
+                let keys = existing.differing_keys(run_info);
+                Err(Error::from(ErrorKind::RunInfoMismatch { keys }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn add_header(
+        &mut self,
+        run_info: WptRunInfo,
+        time_start: u64,
+        time_end: u64,
+        extra: IndexMap<String, serde_json::Value>,
+    ) -> Result<(), Error> {
+        self.check_run_info(&run_info)?;
+        // If this is the first chunk then store the run info
+        if self.run_info.is_none() {
+            self.run_info = Some(run_info);
+        }
+
+        self.time_start = self.time_start.min(time_start);
+        self.time_end = self.time_end.max(time_end);
+        // Other top-level keys are kept from the first chunk that has them
+        for (key, value) in extra {
+            self.extra.entry(key).or_insert(value);
+        }
+        Ok(())
     }
 
     pub fn into_merged_report(self) -> WptReport {
@@ -56,6 +128,7 @@ impl WptReportMerger {
             time_start: self.time_start,
             time_end: self.time_end,
             results: self.scores.into_values().collect(),
+            extra: self.extra,
         }
     }
 }
@@ -0,0 +1,204 @@
+//! A report in the wptreport, Servo scores or wpt.fyi summary format, for tools that accept
+//! any of them. The format is detected from the report's top-level keys.
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use super::servo_test_scores::{TestScore, WptScores};
+use super::wpt_fyi_summary::{TestSummary, WptFyiSummary};
+use super::wpt_report::{TestResult, TestStatus, WptReport, WptRunInfo};
+use super::wpt_report_stream::probe_top_level_keys;
+use crate::{
+    Error, HasRunInfo, ScorableReport, SubtestCounts, SubtestNameAndResult, TestResultIter,
+};
+
+#[derive(Debug)]
+pub enum AnyReport {
+    WptReport(WptReport),
+    WptScores(WptScores),
+    WptFyiSummary(WptFyiSummary),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Format {
+    Report,
+    Scores,
+    Summary,
+}
+
+impl Format {
+    /// The format of a report with the top-level key `key`, if only one format has it
+    fn of_key(key: &str) -> Option<Format> {
+        match key {
+            "results" | "time_start" | "time_end" => Some(Format::Report),
+            "test_scores" => Some(Format::Scores),
+            // wpt.fyi summaries are keyed by test name
+            _ if key.starts_with('/') => Some(Format::Summary),
+            _ => None,
+        }
+    }
+
+    /// Detect the format of a report from the first top-level key that tells them apart,
+    /// without parsing any values. If the document isn't an object, or no key tells the
+    /// formats apart, then parsing it as a wptreport gives the best error.
+    fn detect(s: &str) -> Format {
+        probe_top_level_keys(s.as_bytes(), Format::of_key)
+            .ok()
+            .flatten()
+            .unwrap_or(Format::Report)
+    }
+}
+
+impl AnyReport {
+    /// Parse a report from a reader containing JSON
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, Error> {
+        let mut s = String::new();
+        reader.read_to_string(&mut s)?;
+        s.parse()
+    }
+}
+
+impl FromStr for AnyReport {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Format::detect(s) {
+            Format::Report => crate::from_str(s).map(AnyReport::WptReport),
+            Format::Scores => crate::from_str(s).map(AnyReport::WptScores),
+            Format::Summary => crate::from_str(s).map(AnyReport::WptFyiSummary),
+        }
+    }
+}
+
+impl From<WptReport> for AnyReport {
+    fn from(report: WptReport) -> Self {
+        AnyReport::WptReport(report)
+    }
+}
+
+impl From<WptScores> for AnyReport {
+    fn from(scores: WptScores) -> Self {
+        AnyReport::WptScores(scores)
+    }
+}
+
+impl From<WptFyiSummary> for AnyReport {
+    fn from(summary: WptFyiSummary) -> Self {
+        AnyReport::WptFyiSummary(summary)
+    }
+}
+
+/// wpt.fyi summaries don't record the configuration of the run, so they all share a
+/// `run_info` with an empty product and revision
+impl HasRunInfo for AnyReport {
+    fn run_info(&self) -> &WptRunInfo {
+        static UNKNOWN: OnceLock<WptRunInfo> = OnceLock::new();
+        match self {
+            AnyReport::WptReport(report) => &report.run_info,
+            AnyReport::WptScores(scores) => &scores.run_info,
+            AnyReport::WptFyiSummary(_) => UNKNOWN.get_or_init(|| {
+                serde_json::from_str(r#"{"product": "", "revision": ""}"#).unwrap()
+            }),
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl ScorableReport for AnyReport {
+    type TestResultIter<'a> = AnyTestResult<'a> where Self: 'a;
+    fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
+        let (report, scores, summary) = match self {
+            AnyReport::WptReport(report) => (Some(report), None, None),
+            AnyReport::WptScores(scores) => (None, Some(scores), None),
+            AnyReport::WptFyiSummary(summary) => (None, None, Some(summary)),
+        };
+        let report = report.into_iter().flat_map(|report| report.results.iter().map(AnyTestResult::WptReport));
+        let scores = scores.into_iter().flat_map(|scores| scores.test_scores.iter().map(AnyTestResult::WptScores));
+        let summary = summary.into_iter().flat_map(|summary| summary.results.iter().map(AnyTestResult::WptFyiSummary));
+        report.chain(scores).chain(summary)
+    }
+    fn get(&self, name: &str) -> Option<Self::TestResultIter<'_>> {
+        match self {
+            AnyReport::WptReport(report) => report.get(name).map(AnyTestResult::WptReport),
+            AnyReport::WptScores(scores) => scores.get(name).map(AnyTestResult::WptScores),
+            AnyReport::WptFyiSummary(summary) => summary.get(name).map(AnyTestResult::WptFyiSummary),
+        }
+    }
+    fn is_keyed(&self) -> bool {
+        match self {
+            AnyReport::WptReport(report) => report.is_keyed(),
+            AnyReport::WptScores(scores) => scores.is_keyed(),
+            AnyReport::WptFyiSummary(summary) => summary.is_keyed(),
+        }
+    }
+}
+
+/// A test result from an [`AnyReport`]
+pub enum AnyTestResult<'a> {
+    WptReport(&'a TestResult),
+    WptScores((&'a String, &'a TestScore)),
+    WptFyiSummary((&'a String, &'a TestSummary)),
+}
+
+impl TestResultIter for AnyTestResult<'_> {
+    fn name(&self) -> &str {
+        match self {
+            AnyTestResult::WptReport(test) => TestResultIter::name(*test),
+            AnyTestResult::WptScores(test) => test.name(),
+            AnyTestResult::WptFyiSummary(test) => test.name(),
+        }
+    }
+
+    fn subtest_counts(&self) -> SubtestCounts {
+        match self {
+            AnyTestResult::WptReport(test) => TestResultIter::subtest_counts(*test),
+            AnyTestResult::WptScores(test) => test.subtest_counts(),
+            AnyTestResult::WptFyiSummary(test) => test.subtest_counts(),
+        }
+    }
+
+    fn subtest_exist_and_passes(&self, name: &str) -> bool {
+        match self {
+            AnyTestResult::WptReport(test) => TestResultIter::subtest_exist_and_passes(*test, name),
+            AnyTestResult::WptScores(test) => test.subtest_exist_and_passes(name),
+            AnyTestResult::WptFyiSummary(test) => test.subtest_exist_and_passes(name),
+        }
+    }
+
+    fn iter_subtests_results(&self) -> impl Iterator<Item = SubtestNameAndResult<'_>> {
+        // Summaries don't record subtests
+        let (report, scores) = match self {
+            AnyTestResult::WptReport(test) => {
+                (Some(TestResultIter::iter_subtests_results(*test)), None)
+            }
+            AnyTestResult::WptScores(test) => (None, Some(test.iter_subtests_results())),
+            AnyTestResult::WptFyiSummary(_) => (None, None),
+        };
+        report
+            .into_iter()
+            .flatten()
+            .chain(scores.into_iter().flatten())
+    }
+
+    fn test_passes(&self) -> bool {
+        match self {
+            AnyTestResult::WptReport(test) => TestResultIter::test_passes(*test),
+            AnyTestResult::WptScores(test) => test.test_passes(),
+            AnyTestResult::WptFyiSummary(test) => test.test_passes(),
+        }
+    }
+
+    fn as_test_result(&self) -> Option<&TestResult> {
+        match self {
+            AnyTestResult::WptReport(test) => Some(test),
+            AnyTestResult::WptScores(_) | AnyTestResult::WptFyiSummary(_) => None,
+        }
+    }
+
+    fn status(&self) -> Option<&TestStatus> {
+        match self {
+            AnyTestResult::WptReport(test) => Some(&test.status),
+            AnyTestResult::WptScores(test) => test.status(),
+            AnyTestResult::WptFyiSummary(test) => test.status(),
+        }
+    }
+}
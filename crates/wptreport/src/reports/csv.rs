@@ -0,0 +1,121 @@
+//! Writers for comma or tab separated tables of results, for pasting into spreadsheets.
+//! Fields containing the delimiter, quotes or newlines are quoted as in RFC 4180.
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use super::wpt_report::WptReport;
+use crate::{AreaScores, TestResultIter};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Delimiter {
+    /// Comma separated values (CSV)
+    #[default]
+    Comma,
+    /// Tab separated values (TSV)
+    Tab,
+}
+
+impl Delimiter {
+    fn as_char(self) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+}
+
+struct RowWriter<W: Write> {
+    out: io::BufWriter<W>,
+    delimiter: char,
+}
+
+impl<W: Write> RowWriter<W> {
+    fn new(writer: W, delimiter: Delimiter) -> Self {
+        Self {
+            out: io::BufWriter::new(writer),
+            delimiter: delimiter.as_char(),
+        }
+    }
+
+    fn write_row<T: AsRef<str>>(&mut self, fields: &[T]) -> io::Result<()> {
+        for (idx, field) in fields.iter().enumerate() {
+            if idx > 0 {
+                write!(self.out, "{}", self.delimiter)?;
+            }
+            let field = field.as_ref();
+            if field.contains([self.delimiter, '"', '\n', '\r']) {
+                write!(self.out, "\"{}\"", field.replace('"', "\"\""))?;
+            } else {
+                self.out.write_all(field.as_bytes())?;
+            }
+        }
+        self.out.write_all(b"\n")
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Write one row per test: name, status, passing and total subtests, duration (in
+/// milliseconds) and subsuite
+pub fn write_test_rows(
+    report: &WptReport,
+    delimiter: Delimiter,
+    writer: impl Write,
+) -> io::Result<()> {
+    let mut rows = RowWriter::new(writer, delimiter);
+    rows.write_row(&[
+        "test",
+        "status",
+        "subtests_passed",
+        "subtests_total",
+        "duration",
+        "subsuite",
+    ])?;
+    for test in &report.results {
+        let counts = test.subtest_counts();
+        rows.write_row(&[
+            test.test.as_str(),
+            test.status.as_str(),
+            &counts.pass.to_string(),
+            &counts.total.to_string(),
+            &test.duration.to_string(),
+            test.subsuite.as_deref().unwrap_or_default(),
+        ])?;
+    }
+    rows.finish()
+}
+
+/// Write one row per area (as returned by [`score_wpt_report`](crate::score_wpt_report)):
+/// passing and total tests and subtests, the servo score (the sum of each test's fraction of
+/// passing subtests) and the interop score (out of 1000)
+pub fn write_area_rows(
+    scores: &BTreeMap<String, AreaScores>,
+    delimiter: Delimiter,
+    writer: impl Write,
+) -> io::Result<()> {
+    let mut rows = RowWriter::new(writer, delimiter);
+    rows.write_row(&[
+        "area",
+        "tests_passed",
+        "tests_total",
+        "subtests_passed",
+        "subtests_total",
+        "servo_score",
+        "interop_score",
+    ])?;
+    for (area, scores) in scores {
+        rows.write_row(&[
+            // The root area is the empty string
+            if area.is_empty() { "/" } else { area },
+            &scores.tests.pass.to_string(),
+            &scores.tests.total.to_string(),
+            &scores.subtests.pass.to_string(),
+            &scores.subtests.total.to_string(),
+            &scores.servo_score().to_string(),
+            &scores.interop_score().to_string(),
+        ])?;
+    }
+    rows.finish()
+}
@@ -0,0 +1,38 @@
+//! An Interop-style summary of several browsers' scores, and of the tests they all pass
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::str::FromStr;
+
+use crate::Error;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InteropSummaryReport {
+    pub browsers: Vec<String>,
+    pub focus_areas: Vec<String>,
+    /// The scores for each focus area, in the same order as `focus_areas`
+    pub scores: Vec<InteropScores>,
+}
+
+impl InteropSummaryReport {
+    /// Parse a report from a reader containing JSON
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        crate::from_reader(reader)
+    }
+}
+
+impl FromStr for InteropSummaryReport {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::from_str(s)
+    }
+}
+
+/// Scores are a percentage expressed as a number out of 1000
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteropScores {
+    pub total_tests: u32,
+    /// Each browser's score, in the same order as `browsers`
+    pub browser_scores: Vec<u16>,
+    /// The score counting only the subtests that pass in every browser
+    pub interop_score: u16,
+}
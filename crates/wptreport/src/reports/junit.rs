@@ -0,0 +1,297 @@
+//! A writer for JUnit XML, which most CI systems can display natively.
+//!
+//! The report is written as a single `<testsuite>` (with the `run_info` as properties)
+//! containing a `<testcase>` for each test. Subtests are written either as testcases of
+//! their own or as properties of their test's testcase (see [`SubtestMode`]).
+use std::io::{self, Write};
+
+use super::wpt_report::{SubtestStatus, TestResult, TestStatus, WptReport};
+
+/// How subtest results are represented
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SubtestMode {
+    /// Each subtest is a testcase (with the test id as its classname)
+    #[default]
+    Testcases,
+    /// Subtest statuses are properties of their test's testcase, and the test fails if any
+    /// of its subtests do
+    Properties,
+}
+
+/// The JUnit outcome of a test or subtest
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Outcome {
+    Pass,
+    Failure,
+    Error,
+    Skipped,
+}
+
+fn test_outcome(status: &TestStatus) -> Outcome {
+    match status {
+        TestStatus::Pass | TestStatus::Ok => Outcome::Pass,
+        TestStatus::Fail => Outcome::Failure,
+        TestStatus::Error
+        | TestStatus::Timeout
+        | TestStatus::Crash
+        | TestStatus::Assert
+        | TestStatus::Unknown(_) => Outcome::Error,
+        TestStatus::PreconditionFailed | TestStatus::Skip => Outcome::Skipped,
+    }
+}
+
+fn subtest_outcome(status: &SubtestStatus) -> Outcome {
+    match status {
+        SubtestStatus::Pass => Outcome::Pass,
+        SubtestStatus::Fail => Outcome::Failure,
+        SubtestStatus::Error
+        | SubtestStatus::Timeout
+        | SubtestStatus::Assert
+        | SubtestStatus::Unknown(_) => Outcome::Error,
+        SubtestStatus::PreconditionFailed | SubtestStatus::Notrun | SubtestStatus::Skip => {
+            Outcome::Skipped
+        }
+    }
+}
+
+/// A testcase to be written: either a test or a subtest
+struct Case<'a> {
+    classname: &'a str,
+    name: &'a str,
+    /// The duration in milliseconds (tests only)
+    duration: Option<i64>,
+    outcome: Outcome,
+    status: &'a str,
+    message: Option<String>,
+}
+
+#[derive(Default)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+}
+
+impl Counts {
+    fn add(&mut self, outcome: Outcome) {
+        self.tests += 1;
+        match outcome {
+            Outcome::Pass => {}
+            Outcome::Failure => self.failures += 1,
+            Outcome::Error => self.errors += 1,
+            Outcome::Skipped => self.skipped += 1,
+        }
+    }
+}
+
+/// Split a test id into a classname (its directory) and a name (the rest)
+fn split_test_id(test: &str) -> (&str, &str) {
+    let path_end = test.find(['?', '#']).unwrap_or(test.len());
+    match test[..path_end].rfind('/') {
+        Some(0) => ("/", &test[1..]),
+        Some(idx) => (&test[..idx], &test[idx + 1..]),
+        None => ("", test),
+    }
+}
+
+fn test_case(test: &TestResult, subtests: SubtestMode) -> Case<'_> {
+    let (classname, name) = split_test_id(&test.test);
+    let mut outcome = test_outcome(&test.status);
+    let mut status = test.status.as_str();
+    let mut message = test.message.clone();
+
+    // The harness status of a testharness test is OK even when subtests fail
+    if subtests == SubtestMode::Properties && outcome == Outcome::Pass {
+        let failing = test
+            .subtests
+            .iter()
+            .filter(|subtest| {
+                let outcome = subtest_outcome(&subtest.status);
+                outcome == Outcome::Failure || outcome == Outcome::Error
+            })
+            .count();
+        if failing > 0 {
+            outcome = Outcome::Failure;
+            status = "FAIL";
+            message = Some(format!(
+                "{failing} of {} subtests failed",
+                test.subtests.len()
+            ));
+        }
+    }
+
+    Case {
+        classname,
+        name,
+        duration: Some(test.duration),
+        outcome,
+        status,
+        message,
+    }
+}
+
+/// Write `report` as JUnit XML
+pub fn write_junit(
+    report: &WptReport,
+    subtests: SubtestMode,
+    writer: impl Write,
+) -> io::Result<()> {
+    let mut out = io::BufWriter::new(writer);
+
+    let mut counts = Counts::default();
+    for test in &report.results {
+        counts.add(test_case(test, subtests).outcome);
+        if subtests == SubtestMode::Testcases {
+            for subtest in &test.subtests {
+                counts.add(subtest_outcome(&subtest.status));
+            }
+        }
+    }
+    let total_ms: i64 = report.results.iter().map(|test| test.duration).sum();
+
+    let name = escape(&report.run_info.product);
+    let attrs = format!(
+        r#"tests="{}" failures="{}" errors="{}" skipped="{}" time="{}""#,
+        counts.tests,
+        counts.failures,
+        counts.errors,
+        counts.skipped,
+        seconds(total_ms)
+    );
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<testsuites name="wpt" {attrs}>"#)?;
+    writeln!(out, r#"  <testsuite name="{name}" {attrs}>"#)?;
+
+    // run_info
+    writeln!(out, "    <properties>")?;
+    if let Ok(serde_json::Value::Object(run_info)) = serde_json::to_value(&report.run_info) {
+        for (key, value) in run_info {
+            let value = match value {
+                serde_json::Value::Null => continue,
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            write_property(&mut out, 6, &key, &value)?;
+        }
+    }
+    writeln!(out, "    </properties>")?;
+
+    for test in &report.results {
+        let case = test_case(test, subtests);
+        match subtests {
+            SubtestMode::Testcases => {
+                write_case(&mut out, &case, &[])?;
+                for subtest in &test.subtests {
+                    let case = Case {
+                        classname: &test.test,
+                        name: &subtest.name,
+                        duration: None,
+                        outcome: subtest_outcome(&subtest.status),
+                        status: subtest.status.as_str(),
+                        message: subtest.message.clone(),
+                    };
+                    write_case(&mut out, &case, &[])?;
+                }
+            }
+            SubtestMode::Properties => {
+                let properties: Vec<_> = test
+                    .subtests
+                    .iter()
+                    .map(|subtest| (subtest.name.as_str(), subtest.status.as_str()))
+                    .collect();
+                write_case(&mut out, &case, &properties)?;
+            }
+        }
+    }
+
+    writeln!(out, "  </testsuite>")?;
+    writeln!(out, "</testsuites>")?;
+    out.flush()
+}
+
+/// Write a testcase, with `properties` as (name, value) pairs
+fn write_case(out: &mut impl Write, case: &Case, properties: &[(&str, &str)]) -> io::Result<()> {
+    write!(
+        out,
+        r#"    <testcase classname="{}" name="{}""#,
+        escape(case.classname),
+        escape(case.name)
+    )?;
+    if let Some(duration) = case.duration {
+        write!(out, r#" time="{}""#, seconds(duration))?;
+    }
+
+    let element = match case.outcome {
+        Outcome::Pass => None,
+        Outcome::Failure => Some("failure"),
+        Outcome::Error => Some("error"),
+        Outcome::Skipped => Some("skipped"),
+    };
+    if element.is_none() && properties.is_empty() {
+        return writeln!(out, "/>");
+    }
+    writeln!(out, ">")?;
+
+    if !properties.is_empty() {
+        writeln!(out, "      <properties>")?;
+        for (name, value) in properties {
+            write_property(out, 8, name, value)?;
+        }
+        writeln!(out, "      </properties>")?;
+    }
+
+    if let Some(element) = element {
+        let message = case.message.as_deref().unwrap_or(case.status);
+        write!(out, r#"      <{element} message="{}""#, escape(message))?;
+        if element != "skipped" {
+            write!(out, r#" type="{}""#, escape(case.status))?;
+        }
+        match &case.message {
+            Some(message) if element != "skipped" => {
+                writeln!(out, ">{}</{element}>", escape(message))?
+            }
+            _ => writeln!(out, "/>")?,
+        }
+    }
+
+    writeln!(out, "    </testcase>")
+}
+
+fn write_property(out: &mut impl Write, indent: usize, name: &str, value: &str) -> io::Result<()> {
+    writeln!(
+        out,
+        r#"{:indent$}<property name="{}" value="{}"/>"#,
+        "",
+        escape(name),
+        escape(value)
+    )
+}
+
+/// Format a duration in milliseconds as seconds
+fn seconds(ms: i64) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+/// Escape text for use in XML content or attributes. Characters that can't appear in XML
+/// at all are replaced with U+FFFD.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {
+                escaped.push('\u{FFFD}')
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,3 +1,13 @@
+pub mod any_report;
+pub mod csv;
+pub mod interop_summary;
+pub mod junit;
+pub mod mozlog;
+#[cfg(feature = "arrow")]
+pub mod parquet;
 pub mod score_summary;
+pub mod scores_history;
 pub mod servo_test_scores;
+pub mod wpt_fyi_summary;
 pub mod wpt_report;
+pub mod wpt_report_stream;
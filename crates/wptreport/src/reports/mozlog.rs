@@ -0,0 +1,280 @@
+//! The mozlog structured log format (JSON lines) produced by wptrunner's `--log-raw` option.
+//! This module rebuilds a [`WptReport`] from such a log.
+use std::io::BufRead;
+
+use indexmap::IndexMap;
+use serde::de::Error as _;
+use serde::Deserialize;
+
+use super::wpt_report::{
+    AssertCounts, SubtestResult, SubtestStatus, TestResult, TestStatus, WptReport, WptRunInfo,
+};
+use crate::Error;
+
+/// The subset of mozlog actions that are relevant to building a report
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum LogEntry {
+    SuiteStart {
+        time: u64,
+        #[serde(default)]
+        run_info: Option<WptRunInfo>,
+    },
+    TestStart {
+        time: u64,
+        test: String,
+        #[serde(default)]
+        subsuite: Option<String>,
+    },
+    TestStatus {
+        time: u64,
+        test: String,
+        subtest: String,
+        status: SubtestStatus,
+        #[serde(default)]
+        expected: Option<SubtestStatus>,
+        #[serde(default)]
+        known_intermittent: Vec<String>,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    TestEnd {
+        time: u64,
+        test: String,
+        status: TestStatus,
+        #[serde(default)]
+        expected: Option<TestStatus>,
+        #[serde(default)]
+        known_intermittent: Vec<String>,
+        #[serde(default)]
+        message: Option<String>,
+        #[serde(default)]
+        extra: TestEndExtra,
+    },
+    AssertionCount {
+        time: u64,
+        test: String,
+        count: u32,
+        min_expected: u32,
+        max_expected: u32,
+    },
+    SuiteEnd {
+        time: u64,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TestEndExtra {
+    /// A list of screenshot objects (`{"url": ..., "hash": ...}`) interspersed with
+    /// relation strings (`"=="`, `"!="`)
+    #[serde(default)]
+    reftest_screenshots: Vec<serde_json::Value>,
+}
+
+/// Remove the scheme and host from a URL, leaving just the path (as wptrunner does)
+fn strip_server(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|idx| &rest[idx..]).unwrap_or("/"),
+        None => url,
+    }
+}
+
+/// A test which has started but not (yet) finished
+struct PendingTest {
+    start_time: u64,
+    result: TestResult,
+}
+
+impl PendingTest {
+    fn new(test: String, subsuite: Option<String>, start_time: u64) -> Self {
+        let result = TestResult {
+            test,
+            // Placeholder status which is overwritten by the test_end entry
+            status: TestStatus::Crash,
+            duration: 0,
+            expected: None,
+            message: None,
+            known_intermittent: Vec::new(),
+            subsuite,
+            subtests: Vec::new(),
+            // wptrunner always lists subtests
+            subtests_key: true,
+            screenshots: None,
+            asserts: None,
+            extra: IndexMap::new(),
+        };
+        Self { start_time, result }
+    }
+}
+
+/// The test that a log entry is for. If the test has no `test_start` entry (for example when
+/// a log was split part way through a test) then it is started at `time`.
+fn pending_test(
+    tests: &mut IndexMap<String, PendingTest>,
+    test: String,
+    time: u64,
+) -> &mut PendingTest {
+    tests
+        .entry(test)
+        .or_insert_with_key(|test| PendingTest::new(test.clone(), None, time))
+}
+
+/// Build a [`WptReport`] from a mozlog structured log.
+///
+/// Logs from crashed or incomplete runs are accepted: tests which have a `test_start` but no
+/// `test_end` are reported with a `CRASH` status, a truncated final line is ignored, and if
+/// there is no `suite_end` then the time of the last log entry is used as the end time.
+/// Entries for a test that has no `test_start` start the test at the time of the entry.
+pub fn read_mozlog(mut reader: impl BufRead) -> Result<WptReport, Error> {
+    let mut time_start = None;
+    let mut time_end = None;
+    let mut last_time = 0;
+    let mut run_info = None;
+    let mut tests: IndexMap<String, PendingTest> = IndexMap::new();
+    let mut results: Vec<TestResult> = Vec::new();
+
+    let mut line = String::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut deserializer = serde_json::Deserializer::from_str(&line);
+        let entry: LogEntry = match serde_path_to_error::deserialize(&mut deserializer) {
+            Ok(entry) => entry,
+            // The last line of the log of a crashed run may have been cut off part way through
+            Err(err) if !line.ends_with('\n') && err.inner().is_eof() => break,
+            Err(err) => {
+                let path = match err.path().to_string() {
+                    // The path is `?` if the line ended part way through a key
+                    inner if inner == "." || inner == "?" => format!("line {line_number}"),
+                    inner => format!("line {line_number}: {inner}"),
+                };
+                return Err(Error::json(path, err.into_inner()));
+            }
+        };
+
+        match entry {
+            LogEntry::SuiteStart {
+                time,
+                run_info: info,
+            } => {
+                time_start.get_or_insert(time);
+                if run_info.is_none() {
+                    run_info = info;
+                }
+                last_time = time;
+            }
+            LogEntry::TestStart {
+                time,
+                test,
+                subsuite,
+            } => {
+                tests.insert(test.clone(), PendingTest::new(test, subsuite, time));
+                last_time = time;
+            }
+            LogEntry::TestStatus {
+                time,
+                test,
+                subtest,
+                status,
+                expected,
+                known_intermittent,
+                message,
+            } => {
+                let pending = pending_test(&mut tests, test, time);
+                pending.result.subtests.push(SubtestResult {
+                    name: subtest,
+                    status,
+                    expected,
+                    message,
+                    known_intermittent,
+                    extra: IndexMap::new(),
+                });
+                last_time = time;
+            }
+            LogEntry::TestEnd {
+                time,
+                test,
+                status,
+                expected,
+                known_intermittent,
+                message,
+                extra,
+            } => {
+                let pending = match tests.shift_remove(&test) {
+                    Some(pending) => pending,
+                    None => PendingTest::new(test, None, time),
+                };
+                let mut result = pending.result;
+                result.status = status;
+                result.duration = time.saturating_sub(pending.start_time) as i64;
+                result.expected = expected;
+                result.known_intermittent = known_intermittent;
+                result.message = message;
+                if !extra.reftest_screenshots.is_empty() {
+                    let screenshots = extra
+                        .reftest_screenshots
+                        .iter()
+                        .filter_map(|item| {
+                            let url = item.get("url")?.as_str()?;
+                            let hash = item.get("hash")?.as_str()?;
+                            Some((strip_server(url).to_string(), format!("sha1:{hash}")))
+                        })
+                        .collect();
+                    result.screenshots = Some(screenshots);
+                }
+                results.push(result);
+                last_time = time;
+            }
+            LogEntry::AssertionCount {
+                time,
+                test,
+                count,
+                min_expected,
+                max_expected,
+            } => {
+                pending_test(&mut tests, test, time).result.asserts = Some(AssertCounts {
+                    count,
+                    min: min_expected,
+                    max: max_expected,
+                });
+                last_time = time;
+            }
+            LogEntry::SuiteEnd { time } => {
+                time_end = Some(time);
+                last_time = time;
+            }
+            LogEntry::Other => {}
+        }
+    }
+
+    // Tests that started but never finished (the run crashed or was killed)
+    for (_, pending) in tests {
+        let mut result = pending.result;
+        result.duration = last_time.saturating_sub(pending.start_time) as i64;
+        result.message = Some(String::from("Test did not finish (no test_end in log)"));
+        results.push(result);
+    }
+
+    let run_info = run_info.ok_or_else(|| {
+        let err = serde_json::Error::custom("no suite_start entry with run_info found in log");
+        Error::json(String::from("."), err)
+    })?;
+
+    Ok(WptReport {
+        time_start: time_start.unwrap_or(0),
+        time_end: time_end.unwrap_or(last_time),
+        run_info,
+        results,
+        extra: IndexMap::new(),
+    })
+}
@@ -0,0 +1,411 @@
+//! A writer for Apache Parquet tables of results, for analysis with tools such as DuckDB
+//! or pandas.
+//!
+//! Runs are written to three files in a directory, which can be joined on `run_id`:
+//!
+//!  - `runs.parquet`: one row per run, with its label, start and end times and a column per
+//!    `run_info` property (prefixed with `run_info.` if it would otherwise have the same name
+//!    as one of the other columns, e.g. `run_info.label`)
+//!  - `tests.parquet`: one row per test per run
+//!  - `subtests.parquet`: one row per subtest per run
+//!
+//! Tests and subtests are written out as each run is added, so a whole history of runs can
+//! be exported without holding it in memory. Fields that a format doesn't record (e.g. the
+//! statuses in a [`WptScores`] file) are null.
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::Compression;
+use ::parquet::file::properties::WriterProperties;
+use arrow_array::builder::{BooleanBuilder, Int64Builder, StringBuilder, UInt32Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+
+use super::servo_test_scores::WptScores;
+use super::wpt_report::{WptReport, WptRunInfo};
+use crate::{Error, TestResultIter};
+
+/// The file names of the tables, relative to the output directory
+pub const RUNS_FILE: &str = "runs.parquet";
+pub const TESTS_FILE: &str = "tests.parquet";
+pub const SUBTESTS_FILE: &str = "subtests.parquet";
+
+/// A run that has been added to a [`ParquetWriter`]. The runs table is only written on
+/// [`ParquetWriter::finish`], as its columns depend on the `run_info` of every run.
+struct Run {
+    label: String,
+    time_start: Option<u64>,
+    time_end: Option<u64>,
+    run_info: Map<String, Value>,
+}
+
+/// The columns of the runs table that aren't `run_info` properties
+const RUN_COLUMNS: &[&str] = &["run_id", "label", "time_start", "time_end"];
+
+/// The type of a `run_info` column, inferred from the values in every run
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ColumnType {
+    Boolean,
+    Int64,
+    Utf8,
+}
+
+impl ColumnType {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::Bool(_) => Some(ColumnType::Boolean),
+            Value::Number(n) if n.is_i64() => Some(ColumnType::Int64),
+            _ => Some(ColumnType::Utf8),
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Utf8 => DataType::Utf8,
+        }
+    }
+}
+
+/// Writes runs to Parquet files in a directory (see the [module docs](self))
+pub struct ParquetWriter {
+    dir: PathBuf,
+    runs: Vec<Run>,
+    tests: ArrowWriter<File>,
+    subtests: ArrowWriter<File>,
+}
+
+impl ParquetWriter {
+    /// Create the directory `dir` (if needed) and start writing tables to it
+    pub fn create(dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|err| Error::from(err).with_file(&dir))?;
+        let tests = create_writer(&dir.join(TESTS_FILE), tests_schema())?;
+        let subtests = create_writer(&dir.join(SUBTESTS_FILE), subtests_schema())?;
+        Ok(Self {
+            dir,
+            runs: Vec::new(),
+            tests,
+            subtests,
+        })
+    }
+
+    fn add_run(
+        &mut self,
+        label: &str,
+        time_start: Option<u64>,
+        time_end: Option<u64>,
+        run_info: &WptRunInfo,
+    ) -> u32 {
+        let run_info = match serde_json::to_value(run_info) {
+            Ok(Value::Object(run_info)) => run_info,
+            _ => Map::new(),
+        };
+        self.runs.push(Run {
+            label: label.to_string(),
+            time_start,
+            time_end,
+            run_info,
+        });
+        (self.runs.len() - 1) as u32
+    }
+
+    /// Add a run from a wptreport, returning its `run_id`. `label` identifies the run in
+    /// the runs table (e.g. the date or file name).
+    pub fn add_report(&mut self, label: &str, report: &WptReport) -> Result<u32, Error> {
+        let run_id = self.add_run(
+            label,
+            Some(report.time_start),
+            Some(report.time_end),
+            &report.run_info,
+        );
+
+        let mut tests = TestColumns::default();
+        let mut subtests = SubtestColumns::default();
+        for test in &report.results {
+            tests.run_id.append_value(run_id);
+            tests.test.append_value(&test.test);
+            tests.subsuite.append_option(test.subsuite.as_deref());
+            tests.status.append_value(test.status.as_str());
+            tests
+                .expected
+                .append_option(test.expected.as_ref().map(|s| s.as_str()));
+            tests.message.append_option(test.message.as_deref());
+            tests.duration.append_value(test.duration);
+            let counts = test.subtest_counts();
+            tests.subtests_passed.append_value(counts.pass);
+            tests.subtests_total.append_value(counts.total);
+
+            for subtest in &test.subtests {
+                subtests.run_id.append_value(run_id);
+                subtests.test.append_value(&test.test);
+                subtests.subtest.append_value(&subtest.name);
+                subtests.status.append_value(subtest.status.as_str());
+                subtests
+                    .expected
+                    .append_option(subtest.expected.as_ref().map(|s| s.as_str()));
+                subtests.message.append_option(subtest.message.as_deref());
+                subtests.passes.append_value(subtest.status.is_pass());
+            }
+        }
+
+        self.write_rows(tests, subtests)?;
+        Ok(run_id)
+    }
+
+    /// Add a run from a Servo scores file, returning its `run_id`. `label` identifies the
+    /// run in the runs table (e.g. the date or file name).
+    pub fn add_scores(&mut self, label: &str, scores: &WptScores) -> Result<u32, Error> {
+        let run_id = self.add_run(label, None, None, &scores.run_info);
+
+        let mut tests = TestColumns::default();
+        let mut subtests = SubtestColumns::default();
+        for test in &scores.test_scores {
+            tests.run_id.append_value(run_id);
+            tests.test.append_value(test.0);
+            tests.subsuite.append_null();
+            tests.status.append_null();
+            tests.expected.append_null();
+            tests.message.append_null();
+            tests.duration.append_null();
+            let counts = test.subtest_counts();
+            tests.subtests_passed.append_value(counts.pass);
+            tests.subtests_total.append_value(counts.total);
+
+            for (name, subtest) in &test.1.subtests {
+                subtests.run_id.append_value(run_id);
+                subtests.test.append_value(test.0);
+                subtests.subtest.append_value(name);
+                subtests.status.append_null();
+                subtests.expected.append_null();
+                subtests.message.append_null();
+                subtests.passes.append_value(subtest.score > 0);
+            }
+        }
+
+        self.write_rows(tests, subtests)?;
+        Ok(run_id)
+    }
+
+    fn write_rows(&mut self, tests: TestColumns, subtests: SubtestColumns) -> Result<(), Error> {
+        self.tests
+            .write(&tests.finish()?)
+            .map_err(|err| Error::from(err).with_file(self.dir.join(TESTS_FILE)))?;
+        self.subtests
+            .write(&subtests.finish()?)
+            .map_err(|err| Error::from(err).with_file(self.dir.join(SUBTESTS_FILE)))
+    }
+
+    /// Write the runs table and finish writing the test and subtest tables
+    pub fn finish(self) -> Result<(), Error> {
+        let with_file = |file: &str| {
+            let path = self.dir.join(file);
+            move |err: Error| err.with_file(path)
+        };
+        self.tests
+            .close()
+            .map_err(Error::from)
+            .map_err(with_file(TESTS_FILE))?;
+        self.subtests
+            .close()
+            .map_err(Error::from)
+            .map_err(with_file(SUBTESTS_FILE))?;
+
+        let path = self.dir.join(RUNS_FILE);
+        let batch = runs_batch(&self.runs).map_err(with_file(RUNS_FILE))?;
+        let mut writer = create_writer(&path, batch.schema())?;
+        writer
+            .write(&batch)
+            .map_err(Error::from)
+            .map_err(with_file(RUNS_FILE))?;
+        writer
+            .close()
+            .map_err(Error::from)
+            .map_err(with_file(RUNS_FILE))?;
+        Ok(())
+    }
+}
+
+fn create_writer(path: &Path, schema: SchemaRef) -> Result<ArrowWriter<File>, Error> {
+    let file = File::create(path).map_err(|err| Error::from(err).with_file(path))?;
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    ArrowWriter::try_new(file, schema, Some(props)).map_err(|err| Error::from(err).with_file(path))
+}
+
+fn tests_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("run_id", DataType::UInt32, false),
+        Field::new("test", DataType::Utf8, false),
+        Field::new("subsuite", DataType::Utf8, true),
+        Field::new("status", DataType::Utf8, true),
+        Field::new("expected", DataType::Utf8, true),
+        Field::new("message", DataType::Utf8, true),
+        // In milliseconds
+        Field::new("duration", DataType::Int64, true),
+        Field::new("subtests_passed", DataType::UInt32, false),
+        Field::new("subtests_total", DataType::UInt32, false),
+    ]))
+}
+
+fn subtests_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("run_id", DataType::UInt32, false),
+        Field::new("test", DataType::Utf8, false),
+        Field::new("subtest", DataType::Utf8, false),
+        Field::new("status", DataType::Utf8, true),
+        Field::new("expected", DataType::Utf8, true),
+        Field::new("message", DataType::Utf8, true),
+        Field::new("passes", DataType::Boolean, false),
+    ]))
+}
+
+#[derive(Default)]
+struct TestColumns {
+    run_id: UInt32Builder,
+    test: StringBuilder,
+    subsuite: StringBuilder,
+    status: StringBuilder,
+    expected: StringBuilder,
+    message: StringBuilder,
+    duration: Int64Builder,
+    subtests_passed: UInt32Builder,
+    subtests_total: UInt32Builder,
+}
+
+impl TestColumns {
+    fn finish(mut self) -> Result<RecordBatch, Error> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.run_id.finish()),
+            Arc::new(self.test.finish()),
+            Arc::new(self.subsuite.finish()),
+            Arc::new(self.status.finish()),
+            Arc::new(self.expected.finish()),
+            Arc::new(self.message.finish()),
+            Arc::new(self.duration.finish()),
+            Arc::new(self.subtests_passed.finish()),
+            Arc::new(self.subtests_total.finish()),
+        ];
+        Ok(RecordBatch::try_new(tests_schema(), columns)?)
+    }
+}
+
+#[derive(Default)]
+struct SubtestColumns {
+    run_id: UInt32Builder,
+    test: StringBuilder,
+    subtest: StringBuilder,
+    status: StringBuilder,
+    expected: StringBuilder,
+    message: StringBuilder,
+    passes: BooleanBuilder,
+}
+
+impl SubtestColumns {
+    fn finish(mut self) -> Result<RecordBatch, Error> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.run_id.finish()),
+            Arc::new(self.test.finish()),
+            Arc::new(self.subtest.finish()),
+            Arc::new(self.status.finish()),
+            Arc::new(self.expected.finish()),
+            Arc::new(self.message.finish()),
+            Arc::new(self.passes.finish()),
+        ];
+        Ok(RecordBatch::try_new(subtests_schema(), columns)?)
+    }
+}
+
+/// Build the runs table. Each `run_info` property becomes a column: boolean or integer if
+/// every run has a value of that type, and a string otherwise (with values that aren't
+/// strings written as JSON).
+fn runs_batch(runs: &[Run]) -> Result<RecordBatch, Error> {
+    let mut column_types: IndexMap<&str, ColumnType> = IndexMap::new();
+    for run in runs {
+        for (key, value) in &run.run_info {
+            let Some(ty) = ColumnType::of(value) else {
+                continue;
+            };
+            column_types
+                .entry(key)
+                .and_modify(|existing| {
+                    if *existing != ty {
+                        *existing = ColumnType::Utf8;
+                    }
+                })
+                .or_insert(ty);
+        }
+    }
+
+    let mut fields = vec![
+        Field::new("run_id", DataType::UInt32, false),
+        Field::new("label", DataType::Utf8, false),
+        Field::new("time_start", DataType::UInt64, true),
+        Field::new("time_end", DataType::UInt64, true),
+    ];
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(arrow_array::UInt32Array::from_iter_values(
+            0..runs.len() as u32,
+        )),
+        Arc::new(arrow_array::StringArray::from_iter_values(
+            runs.iter().map(|run| &run.label),
+        )),
+        Arc::new(arrow_array::UInt64Array::from_iter(
+            runs.iter().map(|run| run.time_start),
+        )),
+        Arc::new(arrow_array::UInt64Array::from_iter(
+            runs.iter().map(|run| run.time_end),
+        )),
+    ];
+
+    for (key, ty) in column_types {
+        let name = match RUN_COLUMNS.contains(&key) {
+            true => format!("run_info.{key}"),
+            false => key.to_string(),
+        };
+        fields.push(Field::new(name, ty.data_type(), true));
+        let values = runs
+            .iter()
+            .map(|run| run.run_info.get(key).filter(|v| !v.is_null()));
+        let column: ArrayRef = match ty {
+            ColumnType::Boolean => {
+                let mut builder = BooleanBuilder::with_capacity(runs.len());
+                for value in values {
+                    builder.append_option(value.and_then(Value::as_bool));
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnType::Int64 => {
+                let mut builder = Int64Builder::with_capacity(runs.len());
+                for value in values {
+                    builder.append_option(value.and_then(Value::as_i64));
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnType::Utf8 => {
+                let mut builder = StringBuilder::new();
+                for value in values {
+                    match value {
+                        None => builder.append_null(),
+                        Some(Value::String(s)) => builder.append_value(s),
+                        Some(other) => builder.append_value(other.to_string()),
+                    }
+                }
+                Arc::new(builder.finish())
+            }
+        };
+        columns.push(column);
+    }
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
@@ -1,7 +1,9 @@
 //! A score summary file as used
 use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::str::FromStr;
 
-use crate::AreaScores;
+use crate::{AreaScores, Error};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ScoreSummaryReport {
@@ -9,6 +11,20 @@ pub struct ScoreSummaryReport {
     pub runs: Vec<RunSummary>,
 }
 
+impl ScoreSummaryReport {
+    /// Parse a report from a reader containing JSON
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        crate::from_reader(reader)
+    }
+}
+
+impl FromStr for ScoreSummaryReport {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::from_str(s)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FocusArea {
     pub name: String,
@@ -31,32 +47,81 @@ pub struct RunSummary {
     pub wpt_revision: String,
     /// The version of the browser that was tested
     pub product_revision: String,
-    /// Scores are a percentage expressed a number between 0 and 1000
+    /// Scores are a percentage expressed as a number out of 1000
     pub scores: Vec<RunScores>,
 }
 
+/// A way of scoring an area's results. Each method adds its fields to [`RunScores`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScoringMethod {
+    /// The sum of each test's fraction of passing subtests (`total_score`)
+    Servo,
+    /// The mean of each test's passing subtests per 1000, as used by Interop (`interop_score`)
+    Interop,
+    /// The number of tests where every subtest passes (`total_tests_passed`)
+    TestsPassed,
+    /// The number of passing subtests out of all subtests (`total_subtests_passed`)
+    SubtestRatio,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct RunScores {
-    // pub interop_score: u16,
     pub total_tests: u32,
     #[serde(serialize_with = "as_int_if_int")]
     pub total_score: f64, // Servo score
-    // pub total_tests_passed: u32,
     pub total_subtests: u32,
     pub total_subtests_passed: u32,
+    /// Scores are a percentage expressed as a number out of 1000
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interop_score: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_tests_passed: Option<u32>,
 }
 
-impl From<AreaScores> for RunScores {
-    fn from(scores: AreaScores) -> Self {
+impl RunScores {
+    /// Summarise an area's scores with the given scoring methods. The Servo and subtest
+    /// ratio fields are always filled in as existing consumers rely on them.
+    pub fn with_methods(scores: AreaScores, methods: &[ScoringMethod]) -> Self {
         Self {
-            // interop_score: scores.interop_score(),
             total_tests: scores.tests.total,
             total_score: scores.servo_score(),
-            // total_tests_passed: scores.tests.pass,
             total_subtests: scores.subtests.total,
             total_subtests_passed: scores.subtests.pass,
+            interop_score: methods
+                .contains(&ScoringMethod::Interop)
+                .then(|| scores.interop_score()),
+            total_tests_passed: methods
+                .contains(&ScoringMethod::TestsPassed)
+                .then_some(scores.tests.pass),
         }
     }
+
+    /// The score using `method` as a fraction between 0 and 1, or `None` if the
+    /// summary wasn't computed with that method
+    pub fn score(&self, method: ScoringMethod) -> Option<f64> {
+        let fraction = |amount: f64, out_of: u32| match out_of {
+            0 => 0.0,
+            out_of => amount / out_of as f64,
+        };
+        match method {
+            ScoringMethod::Servo => Some(fraction(self.total_score, self.total_tests)),
+            ScoringMethod::Interop => self.interop_score.map(|score| score as f64 / 1000.0),
+            ScoringMethod::TestsPassed => self
+                .total_tests_passed
+                .map(|passed| fraction(passed as f64, self.total_tests)),
+            ScoringMethod::SubtestRatio => Some(fraction(
+                self.total_subtests_passed as f64,
+                self.total_subtests,
+            )),
+        }
+    }
+}
+
+impl From<AreaScores> for RunScores {
+    fn from(scores: AreaScores) -> Self {
+        Self::with_methods(scores, &[ScoringMethod::Servo])
+    }
 }
 
 /// Remove redundant decimal places
@@ -0,0 +1,627 @@
+//! A compact binary format for storing many runs of [`WptScores`] in a single file.
+//!
+//! Test and subtest names are stored once, in a string table shared by every run, along
+//! with a "layout" of every test (and subtest) seen in any run. Each run then only records
+//! which tests and subtests it contains (as spans of the layout, in the run's own order)
+//! and a bitset of which of them passed. Converting to and from [`WptScores`] is lossless.
+//!
+//! Runs can be scored against a reference run without looking up any names, which is much
+//! faster than re-parsing and scoring JSON files (see [`ScoresHistory::score_against`]).
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use indexmap::IndexMap;
+
+use super::servo_test_scores::{SubtestScore, TestScore, WptScores};
+use super::wpt_report::WptRunInfo;
+use crate::score::area_iter;
+use crate::{AreaScores, Error, ErrorKind, SubtestCounts};
+
+/// The bytes that every scores history file starts with
+pub const MAGIC: &[u8; 8] = b"WPTSCORE";
+const VERSION: u32 = 1;
+
+/// Many runs of [`WptScores`] sharing a single string table (see the [module docs](self))
+#[derive(Debug, Default)]
+pub struct ScoresHistory {
+    strings: Vec<String>,
+    tests: Vec<LayoutTest>,
+    runs: Vec<PackedRun>,
+    /// Lookups from names to indexes, built when the first run is added
+    index: Option<Index>,
+}
+
+/// A test and every subtest it has had in any run (as indexes into the string table)
+#[derive(Debug)]
+struct LayoutTest {
+    name: u32,
+    subtests: Vec<u32>,
+}
+
+#[derive(Debug)]
+struct PackedRun {
+    label: String,
+    run_info: WptRunInfo,
+    /// The offset of `data` in the file it was read from (for error messages)
+    offset: usize,
+    /// Spans of tests and subtests, followed by the pass bitset (see `ScoresHistory::push`)
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct Index {
+    strings: HashMap<String, u32>,
+    tests: HashMap<u32, u32>,
+    /// (test index, subtest name) to the subtest's index within its test
+    subtests: HashMap<(u32, u32), u32>,
+}
+
+impl Index {
+    fn new(strings: &[String], tests: &[LayoutTest]) -> Self {
+        let mut index = Index::default();
+        for (id, string) in strings.iter().enumerate() {
+            index.strings.insert(string.clone(), id as u32);
+        }
+        for (test_idx, test) in tests.iter().enumerate() {
+            index.tests.insert(test.name, test_idx as u32);
+            for (subtest_idx, name) in test.subtests.iter().enumerate() {
+                index
+                    .subtests
+                    .insert((test_idx as u32, *name), subtest_idx as u32);
+            }
+        }
+        index
+    }
+}
+
+/// A run decoded into layout indexes
+struct DecodedRun {
+    tests: Vec<DecodedTest>,
+    /// (subtest index, score) pairs, referenced by [`DecodedTest::subtests`]
+    subtests: Vec<(u32, u32)>,
+}
+
+struct DecodedTest {
+    test: u32,
+    score: u32,
+    subtests: Range<usize>,
+}
+
+impl ScoresHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of runs
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// The label of a run (e.g. the name of the file it was packed from)
+    pub fn label(&self, run: usize) -> &str {
+        &self.runs[run].label
+    }
+
+    pub fn run_info(&self, run: usize) -> &WptRunInfo {
+        &self.runs[run].run_info
+    }
+
+    /// Add a run to the end of the history
+    pub fn push(&mut self, label: impl Into<String>, scores: &WptScores) {
+        let index = self
+            .index
+            .get_or_insert_with(|| Index::new(&self.strings, &self.tests));
+        let mut intern = |s: &str| -> u32 {
+            if let Some(&id) = index.strings.get(s) {
+                return id;
+            }
+            let id = self.strings.len() as u32;
+            self.strings.push(s.to_string());
+            index.strings.insert(s.to_string(), id);
+            id
+        };
+
+        // Resolve names to layout indexes, adding new tests and subtests to the layout
+        let mut test_ids = Vec::with_capacity(scores.test_scores.len());
+        let mut subtest_ids = Vec::new();
+        for (name, test) in &scores.test_scores {
+            let name = intern(name);
+            let test_idx = *index.tests.entry(name).or_insert_with(|| {
+                self.tests.push(LayoutTest {
+                    name,
+                    subtests: Vec::new(),
+                });
+                (self.tests.len() - 1) as u32
+            });
+            test_ids.push(test_idx);
+            for subtest_name in test.subtests.keys() {
+                let subtest_name = intern(subtest_name);
+                let layout = &mut self.tests[test_idx as usize];
+                let subtest_idx = *index
+                    .subtests
+                    .entry((test_idx, subtest_name))
+                    .or_insert_with(|| {
+                        layout.subtests.push(subtest_name);
+                        (layout.subtests.len() - 1) as u32
+                    });
+                subtest_ids.push(subtest_idx);
+            }
+        }
+
+        // The run is stored as: spans of tests, then spans of each test's subtests, then one
+        // bit per test and subtest (in order) for whether it passed, then any scores greater
+        // than one as (position, score) pairs
+        let mut data = Vec::new();
+        let mut bits = BitWriter::default();
+        let mut extra_scores = Vec::new();
+        write_spans(&mut data, &test_ids);
+        let mut subtest_ids = subtest_ids.as_slice();
+        for test in scores.test_scores.values() {
+            let (ids, rest) = subtest_ids.split_at(test.subtests.len());
+            subtest_ids = rest;
+            write_spans(&mut data, ids);
+
+            let scores = std::iter::once(test.score).chain(test.subtests.values().map(|s| s.score));
+            for score in scores {
+                if score > 1 {
+                    extra_scores.push((bits.len, score));
+                }
+                bits.push(score > 0);
+            }
+        }
+        data.extend_from_slice(&bits.bytes);
+        write_varint(&mut data, extra_scores.len() as u64);
+        for (position, score) in extra_scores {
+            write_varint(&mut data, position as u64);
+            write_varint(&mut data, score as u64);
+        }
+
+        self.runs.push(PackedRun {
+            label: label.into(),
+            run_info: scores.run_info.clone(),
+            offset: 0,
+            data,
+        });
+    }
+
+    fn decode(&self, run: usize) -> Result<DecodedRun, Error> {
+        let packed = &self.runs[run];
+        let mut cursor = Cursor {
+            bytes: &packed.data,
+            pos: 0,
+            offset: packed.offset,
+        };
+
+        let mut tests = Vec::new();
+        let mut subtests = Vec::new();
+        for test in read_spans(&mut cursor, self.tests.len())? {
+            let layout = &self.tests[test as usize];
+            let start = subtests.len();
+            for subtest in read_spans(&mut cursor, layout.subtests.len())? {
+                subtests.push((subtest, 0));
+            }
+            tests.push(DecodedTest {
+                test,
+                score: 0,
+                subtests: start..subtests.len(),
+            });
+        }
+
+        // Scores are in the same order as they were written by `push`
+        let count = tests.len() + subtests.len();
+        let bits = cursor.bytes(count.div_ceil(8))?;
+        let mut scores: Vec<u32> = (0..count)
+            .map(|i| ((bits[i / 8] >> (i % 8)) & 1) as u32)
+            .collect();
+        for _ in 0..cursor.u32()? {
+            let position = cursor.u32()? as usize;
+            let score = cursor.u32()?;
+            match scores.get_mut(position) {
+                Some(slot) => *slot = score,
+                None => return Err(cursor.error("score position out of range")),
+            }
+        }
+        if cursor.pos != cursor.bytes.len() {
+            return Err(cursor.error("unexpected data at end of run"));
+        }
+
+        let mut scores = scores.into_iter();
+        for test in &mut tests {
+            test.score = scores.next().unwrap();
+            for subtest in &mut subtests[test.subtests.clone()] {
+                subtest.1 = scores.next().unwrap();
+            }
+        }
+        Ok(DecodedRun { tests, subtests })
+    }
+
+    /// Decode a run into a [`WptScores`] (identical to the one it was added from)
+    pub fn to_scores(&self, run: usize) -> Result<WptScores, Error> {
+        let decoded = self.decode(run)?;
+        let test_scores = decoded
+            .tests
+            .iter()
+            .map(|test| {
+                let layout = &self.tests[test.test as usize];
+                let subtests = decoded.subtests[test.subtests.clone()]
+                    .iter()
+                    .map(|&(subtest, score)| {
+                        let name = &self.strings[layout.subtests[subtest as usize] as usize];
+                        (name.clone(), SubtestScore { score })
+                    })
+                    .collect();
+                let name = self.strings[layout.name as usize].clone();
+                (
+                    name,
+                    TestScore {
+                        score: test.score,
+                        subtests,
+                    },
+                )
+            })
+            .collect::<IndexMap<_, _>>();
+        Ok(WptScores {
+            run_info: self.runs[run].run_info.clone(),
+            test_scores,
+        })
+    }
+
+    /// Prepare to score runs against `run` (see [`score_against`](Self::score_against))
+    pub fn reference(&self, run: usize) -> Result<Reference<'_>, Error> {
+        // Each test has a slot for its own score followed by one for each of its subtests
+        let mut slot_base = Vec::with_capacity(self.tests.len());
+        let mut total_slots = 0;
+        for test in &self.tests {
+            slot_base.push(total_slots);
+            total_slots += 1 + test.subtests.len() as u32;
+        }
+
+        let decoded = self.decode(run)?;
+        let mut reference = Reference {
+            history: self,
+            total_slots: total_slots as usize,
+            tests: Vec::with_capacity(decoded.tests.len()),
+            subtest_slots: Vec::with_capacity(decoded.subtests.len()),
+            slot_base,
+            area_ids: Vec::new(),
+            areas: Vec::new(),
+        };
+        let mut area_lookup: HashMap<&str, u32> = HashMap::new();
+        for test in &decoded.tests {
+            let base = reference.slot_base[test.test as usize];
+            let subtests_start = reference.subtest_slots.len() as u32;
+            for &(subtest, _) in &decoded.subtests[test.subtests.clone()] {
+                reference.subtest_slots.push(base + 1 + subtest);
+            }
+            let areas_start = reference.area_ids.len() as u32;
+            let name = &self.strings[self.tests[test.test as usize].name as usize];
+            for area in area_iter(name) {
+                let id = *area_lookup.entry(area).or_insert_with(|| {
+                    reference.areas.push(area.to_string());
+                    (reference.areas.len() - 1) as u32
+                });
+                reference.area_ids.push(id);
+            }
+            reference.tests.push(ReferenceTest {
+                slot: base,
+                subtests: subtests_start..reference.subtest_slots.len() as u32,
+                areas: areas_start..reference.area_ids.len() as u32,
+            });
+        }
+        Ok(reference)
+    }
+
+    /// Scores a run against a reference run. This gives the same result as
+    /// [`WptScores::score_against`] (with the default [`ScoringPolicy`](crate::ScoringPolicy)),
+    /// but is much faster as no names need to be looked up. `reference` must have been
+    /// prepared from this history.
+    pub fn score_against(
+        &self,
+        run: usize,
+        reference: &Reference<'_>,
+    ) -> Result<BTreeMap<String, AreaScores>, Error> {
+        if !std::ptr::eq(self, reference.history) {
+            let err = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "reference is from a different history",
+            );
+            return Err(Error::from(err));
+        }
+
+        let decoded = self.decode(run)?;
+        // Whether each test and subtest passed (any non-zero score is a pass)
+        let mut slot_scores = vec![0u32; reference.total_slots];
+        for test in &decoded.tests {
+            let base = reference.slot_base[test.test as usize];
+            slot_scores[base as usize] = (test.score > 0) as u32;
+            for &(subtest, score) in &decoded.subtests[test.subtests.clone()] {
+                slot_scores[(base + 1 + subtest) as usize] = (score > 0) as u32;
+            }
+        }
+
+        let mut area_scores = vec![AreaScores::default(); reference.areas.len()];
+        for test in &reference.tests {
+            let counts = if test.subtests.is_empty() {
+                SubtestCounts {
+                    pass: slot_scores[test.slot as usize],
+                    total: 1,
+                }
+            } else {
+                let slots = &reference.subtest_slots
+                    [test.subtests.start as usize..test.subtests.end as usize];
+                SubtestCounts {
+                    pass: slots.iter().map(|&slot| slot_scores[slot as usize]).sum(),
+                    total: slots.len() as u32,
+                }
+            };
+
+            // Update the scores for each area that the test belongs to
+            for &area in &reference.area_ids[test.areas.start as usize..test.areas.end as usize] {
+                area_scores[area as usize].add_test(counts);
+            }
+        }
+
+        Ok(reference.areas.iter().cloned().zip(area_scores).collect())
+    }
+
+    /// Write the history in the binary format
+    pub fn write(&self, writer: impl Write) -> io::Result<()> {
+        let mut out = io::BufWriter::new(writer);
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&VERSION.to_le_bytes());
+
+        write_varint(&mut buf, self.strings.len() as u64);
+        for string in &self.strings {
+            write_bytes(&mut buf, string.as_bytes());
+        }
+        write_varint(&mut buf, self.tests.len() as u64);
+        for test in &self.tests {
+            write_varint(&mut buf, test.name as u64);
+            write_varint(&mut buf, test.subtests.len() as u64);
+            for &subtest in &test.subtests {
+                write_varint(&mut buf, subtest as u64);
+            }
+        }
+        write_varint(&mut buf, self.runs.len() as u64);
+        out.write_all(&buf)?;
+
+        for run in &self.runs {
+            buf.clear();
+            write_bytes(&mut buf, run.label.as_bytes());
+            let run_info = serde_json::to_string(&run.run_info).map_err(io::Error::other)?;
+            write_bytes(&mut buf, run_info.as_bytes());
+            write_varint(&mut buf, run.data.len() as u64);
+            out.write_all(&buf)?;
+            out.write_all(&run.data)?;
+        }
+        out.flush()
+    }
+
+    /// Read a history from a reader containing the binary format
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_slice(&bytes)
+    }
+
+    /// Read a history from a byte slice containing the binary format
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        let mut cursor = Cursor {
+            bytes,
+            pos: 0,
+            offset: 0,
+        };
+        if !bytes.starts_with(MAGIC) {
+            return Err(cursor.error("not a scores history file"));
+        }
+        cursor.pos = MAGIC.len();
+        let version = u32::from_le_bytes(cursor.bytes(4)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(cursor.error(format!("unsupported version {version}")));
+        }
+
+        let string_count = cursor.u32()?;
+        let mut strings = Vec::with_capacity(cursor.capacity(string_count));
+        for _ in 0..string_count {
+            strings.push(cursor.string()?.to_string());
+        }
+        let check_string = |cursor: &Cursor, id: u32| match (id as usize) < strings.len() {
+            true => Ok(id),
+            false => Err(cursor.error("string index out of range")),
+        };
+
+        let test_count = cursor.u32()?;
+        let mut tests = Vec::with_capacity(cursor.capacity(test_count));
+        for _ in 0..test_count {
+            let name = cursor.u32()?;
+            let name = check_string(&cursor, name)?;
+            let subtest_count = cursor.u32()?;
+            let mut subtests = Vec::with_capacity(cursor.capacity(subtest_count));
+            for _ in 0..subtest_count {
+                let subtest = cursor.u32()?;
+                subtests.push(check_string(&cursor, subtest)?);
+            }
+            tests.push(LayoutTest { name, subtests });
+        }
+
+        let run_count = cursor.u32()?;
+        let mut runs = Vec::with_capacity(cursor.capacity(run_count));
+        for _ in 0..run_count {
+            let label = cursor.string()?.to_string();
+            let run_info_pos = cursor.pos;
+            let run_info = crate::from_str(cursor.string()?).map_err(|err| {
+                Error::from(ErrorKind::Binary {
+                    offset: run_info_pos,
+                    message: format!("invalid run_info: {err}"),
+                })
+            })?;
+            let len = cursor.u32()? as usize;
+            let offset = cursor.pos;
+            let data = cursor.bytes(len)?.to_vec();
+            runs.push(PackedRun {
+                label,
+                run_info,
+                offset,
+                data,
+            });
+        }
+        if cursor.pos != bytes.len() {
+            return Err(cursor.error("unexpected data at end of file"));
+        }
+
+        Ok(Self {
+            strings,
+            tests,
+            runs,
+            index: None,
+        })
+    }
+}
+
+/// A run to score other runs against, prepared by [`ScoresHistory::reference`]
+pub struct Reference<'a> {
+    history: &'a ScoresHistory,
+    /// The first slot of each test in the layout
+    slot_base: Vec<u32>,
+    total_slots: usize,
+    tests: Vec<ReferenceTest>,
+    subtest_slots: Vec<u32>,
+    area_ids: Vec<u32>,
+    areas: Vec<String>,
+}
+
+struct ReferenceTest {
+    slot: u32,
+    /// A range of `Reference::subtest_slots`
+    subtests: Range<u32>,
+    /// A range of `Reference::area_ids`
+    areas: Range<u32>,
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    fn push(&mut self, bit: bool) {
+        if self.len / 8 == self.bytes.len() {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 1 << (self.len % 8);
+        }
+        self.len += 1;
+    }
+}
+
+/// Write a LEB128 varint
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Write a list of indexes as runs of consecutive indexes: a count followed by
+/// (start, length) pairs
+fn write_spans(out: &mut Vec<u8>, ids: &[u32]) {
+    let mut spans: Vec<(u32, u32)> = Vec::new();
+    for &id in ids {
+        match spans.last_mut() {
+            Some((start, len)) if *start + *len == id => *len += 1,
+            _ => spans.push((id, 1)),
+        }
+    }
+    write_varint(out, spans.len() as u64);
+    for (start, len) in spans {
+        write_varint(out, start as u64);
+        write_varint(out, len as u64);
+    }
+}
+
+/// Read spans written by [`write_spans`], checking that every index is less than `limit`
+fn read_spans(cursor: &mut Cursor, limit: usize) -> Result<Vec<u32>, Error> {
+    let mut ids = Vec::new();
+    for _ in 0..cursor.u32()? {
+        let start = cursor.u32()?;
+        let len = cursor.u32()?;
+        if start as usize + len as usize > limit {
+            return Err(cursor.error("index out of range"));
+        }
+        ids.extend(start..start + len);
+    }
+    Ok(ids)
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// The offset of `bytes` in the file
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::from(ErrorKind::Binary {
+            offset: self.offset + self.pos,
+            message: message.into(),
+        })
+    }
+
+    /// The capacity to reserve for `count` items read from the data. Every item takes at
+    /// least a byte, so a corrupt count can't cause more than the remaining length to be
+    /// allocated.
+    fn capacity(&self, count: u32) -> usize {
+        (count as usize).min(self.bytes.len() - self.pos)
+    }
+
+    fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                return Err(self.error("unexpected end of data"));
+            };
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("invalid varint"))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let value = self.varint()?;
+        u32::try_from(value).map_err(|_| self.error("value out of range"))
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        match self.bytes.get(self.pos..self.pos.saturating_add(len)) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => Err(self.error("unexpected end of data")),
+        }
+    }
+
+    fn string(&mut self) -> Result<&'a str, Error> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        std::str::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8"))
+    }
+}
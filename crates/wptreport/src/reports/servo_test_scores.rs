@@ -1,11 +1,14 @@
 //! The cut-down version of the "wptreport" format used by Servo to store scores
 //! in the internal-wpt-dashboard repository
 
-use crate::{HasRunInfo, ScorableReport, SubtestCounts, TestResultIter};
+use crate::{Error, HasRunInfo, ScorableReport, SubtestCounts, TestResultIter};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Read};
+use std::str::FromStr;
 
-use super::wpt_report::{SubtestStatus, TestStatus, WptReport, WptRunInfo};
+use super::wpt_report::{TestResult, WptReport, WptRunInfo};
+use super::wpt_report_stream::WptReportStream;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WptScores {
@@ -24,12 +27,32 @@ pub struct SubtestScore {
     pub score: u32,
 }
 
+impl WptScores {
+    /// Parse a report from a reader containing JSON
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        crate::from_reader(reader)
+    }
+}
+
+impl FromStr for WptScores {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::from_str(s)
+    }
+}
+
 #[rustfmt::skip]
 impl ScorableReport for WptScores {
     type TestResultIter<'a> = (&'a String, &'a TestScore) where Self: 'a;
     fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
         self.test_scores.iter()
     }
+    fn get(&self, name: &str) -> Option<Self::TestResultIter<'_>> {
+        self.test_scores.get_key_value(name)
+    }
+    fn is_keyed(&self) -> bool {
+        true
+    }
 }
 
 impl HasRunInfo for WptScores {
@@ -44,19 +67,7 @@ impl TestResultIter for (&String, &TestScore) {
     }
 
     fn subtest_counts(&self) -> SubtestCounts {
-        let total = self.1.subtests.len() as u32;
-        if total == 0 {
-            SubtestCounts {
-                total: 1,
-                pass: self.1.score,
-            }
-        } else {
-            let pass = self.1.subtests.values().fold(0, |mut pass_count, subtest| {
-                pass_count += subtest.score;
-                pass_count
-            });
-            SubtestCounts { pass, total }
-        }
+        test_score_counts(self.1.score, self.1.subtests.values())
     }
 
     fn subtest_exist_and_passes(&self, name: &str) -> bool {
@@ -74,36 +85,72 @@ impl TestResultIter for (&String, &TestScore) {
                 passes: s.score > 0,
             })
     }
+
+    fn test_passes(&self) -> bool {
+        self.1.score > 0
+    }
+}
+
+/// The subtest counts of a test with the given score and subtest scores. Any non-zero score
+/// counts as a single pass, so malformed scores above 1 can't make `pass` exceed `total`.
+pub(crate) fn test_score_counts<'a>(
+    score: u32,
+    subtests: impl ExactSizeIterator<Item = &'a SubtestScore>,
+) -> SubtestCounts {
+    let total = subtests.len() as u32;
+    if total == 0 {
+        SubtestCounts {
+            total: 1,
+            pass: (score > 0) as u32,
+        }
+    } else {
+        let pass = subtests.filter(|subtest| subtest.score > 0).count() as u32;
+        SubtestCounts { pass, total }
+    }
+}
+
+/// Convert a test result into a `(test name, score)` pair
+fn test_score_entry(test: TestResult) -> (String, TestScore) {
+    let score = TestScore {
+        score: test.status.is_pass() as u32,
+        subtests: test
+            .subtests
+            .into_iter()
+            .map(|subtest| {
+                let score = SubtestScore {
+                    score: subtest.status.is_pass() as u32,
+                };
+                (subtest.name, score)
+            })
+            .collect(),
+    };
+    (test.test, score)
 }
 
 impl From<WptReport> for WptScores {
     fn from(report: WptReport) -> Self {
         WptScores {
             run_info: report.run_info,
-            test_scores: report
-                .results
-                .into_iter()
-                .map(|test| {
-                    let score = TestScore {
-                        score: (test.status == TestStatus::Pass) as u32,
-                        subtests: test
-                            .subtests
-                            .into_iter()
-                            .map(|subtest| {
-                                let score = SubtestScore {
-                                    score: (subtest.status == SubtestStatus::Pass) as u32,
-                                };
-                                (subtest.name, score)
-                            })
-                            .collect(),
-                    };
-                    (test.test, score)
-                })
-                .collect(),
+            test_scores: report.results.into_iter().map(test_score_entry).collect(),
         }
     }
 }
 
+impl<R: BufRead> TryFrom<WptReportStream<R>> for WptScores {
+    type Error = Error;
+
+    /// Convert a streamed report without materialising its full list of results
+    fn try_from(mut stream: WptReportStream<R>) -> Result<Self, Self::Error> {
+        let test_scores = (&mut stream)
+            .map(|test| test.map(test_score_entry))
+            .collect::<Result<_, Error>>()?;
+        Ok(WptScores {
+            run_info: stream.finish()?.run_info,
+            test_scores,
+        })
+    }
+}
+
 impl WptScores {
     /// In order to match the serialization order of a JavaScript object, it is useful to be be able to
     /// sort keys in the order in which JS seralizes things. We also first apply an alphabetic sort so
@@ -124,17 +171,15 @@ impl WptScores {
             _b_value: &V,
         ) -> Ordering {
             let a_is_int = a_key.as_bytes().iter().all(is_digit)
-                && (a_key.len() == 1 || a_key.as_bytes()[0] != b'0');
+                && (a_key.len() == 1 || a_key.as_bytes().first().is_some_and(|c| *c != b'0'));
             let b_is_int = b_key.as_bytes().iter().all(is_digit)
-                && (b_key.len() == 1 || b_key.as_bytes()[0] != b'0');
+                && (b_key.len() == 1 || b_key.as_bytes().first().is_some_and(|c| *c != b'0'));
             match (a_is_int, b_is_int) {
                 (true, false) => Ordering::Less,
                 (false, true) => Ordering::Greater,
-                (true, true) => {
-                    let a = a_key.parse::<u64>().unwrap();
-                    let b = b_key.parse::<u64>().unwrap();
-                    a.cmp(&b)
-                }
+                // Integer keys don't have leading zeros, so can be compared without parsing
+                // them (which could overflow)
+                (true, true) => a_key.len().cmp(&b_key.len()).then_with(|| a_key.cmp(b_key)),
                 (false, false) => Ordering::Equal,
             }
         }
@@ -165,32 +210,18 @@ mod score {
     use std::collections::BTreeMap;
 
     use super::{SubtestCounts, TestScore, WptScores};
-    use crate::{score::area_iter, AreaScores};
+    use crate::score::{score_wpt_report_against_with, ScoringPolicy};
+    use crate::AreaScores;
 
     impl TestScore {
         /// Scores a test against a reference test
         /// This means that we only count subtests that were run in the reference test
         pub fn score_against(&self, reference: &TestScore) -> SubtestCounts {
-            if reference.subtests.is_empty() {
-                SubtestCounts {
-                    pass: self.score,
-                    total: 1,
-                }
-            } else {
-                SubtestCounts {
-                    pass: reference
-                        .subtests
-                        .keys()
-                        .map(|subtest_name| {
-                            self.subtests
-                                .get(subtest_name)
-                                .map(|subtest| subtest.score)
-                                .unwrap_or(0)
-                        })
-                        .sum::<u32>(),
-                    total: reference.subtests.len() as u32,
-                }
-            }
+            // The name isn't needed to score a single test
+            let name = String::new();
+            ScoringPolicy::default()
+                .test_counts_against(Some(&(&name, self)), &(&name, reference))
+                .unwrap()
         }
     }
 
@@ -198,49 +229,17 @@ mod score {
         /// Scores a test run against a reference test run
         /// This means that we only count tests and subtests that were run in the reference run
         pub fn score_against(&self, reference: &WptScores) -> BTreeMap<String, AreaScores> {
-            // let mut scores = AreaScores::default();
-            let mut results = BTreeMap::<String, AreaScores>::new();
-
-            for (test_name, reference_test) in reference.test_scores.iter() {
-                let counts = match self.test_scores.get(test_name) {
-                    Some(test) => test.score_against(reference_test),
-                    None => SubtestCounts {
-                        pass: 0,
-                        total: reference_test.subtests.len().max(1) as u32,
-                    },
-                };
-                let passes = counts.all_passing();
-
-                // Update the scores for each area that the test belongs to
-                for area in area_iter(test_name) {
-                    if results.contains_key(area) {
-                        let test_scores = results.get_mut(area).unwrap();
-                        test_scores.tests.pass += passes as u32;
-                        test_scores.tests.total += 1;
-                        test_scores.subtests.pass += counts.pass;
-                        test_scores.subtests.total += counts.total;
-                        test_scores.interop_score_sum += counts.passes_per_1000() as u64;
-                        test_scores.pass_fraction_sum += counts.pass_fraction();
-                    } else {
-                        let test_scores = AreaScores {
-                            tests: SubtestCounts {
-                                pass: passes as u32,
-                                total: 1,
-                            },
-                            subtests: counts,
-                            // The sum of the interop scores for each individual test, but not
-                            // divided by the total number of tests
-                            interop_score_sum: counts.passes_per_1000() as u64,
-                            // The sum of the "fraction of passing subtests" for each individual test,
-                            // but not divided by the total number of tests
-                            pass_fraction_sum: counts.pass_fraction(),
-                        };
-                        results.insert(area.to_string(), test_scores);
-                    };
-                }
-            }
+            self.score_against_with(reference, &ScoringPolicy::default())
+        }
 
-            results
+        /// Scores a test run against a reference test run, weighting tests with `policy`
+        /// (see [`score_wpt_report_against_with`])
+        pub fn score_against_with(
+            &self,
+            reference: &WptScores,
+            policy: &ScoringPolicy,
+        ) -> BTreeMap<String, AreaScores> {
+            score_wpt_report_against_with(self, reference, policy)
         }
 
         pub fn score(&self) -> BTreeMap<String, AreaScores> {
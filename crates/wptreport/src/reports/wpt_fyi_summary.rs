@@ -0,0 +1,152 @@
+//! The compact per-run summary format (`summary_v2`) published by wpt.fyi.
+//! This maps test names to `{"s": [passing subtests, total subtests], "c": status}`.
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use smol_str::SmolStr;
+use std::io::Read;
+use std::str::FromStr;
+
+use super::wpt_report::{TestStatus, WptReport};
+use crate::{Error, ScorableReport, SubtestCounts, SubtestNameAndResult, TestResultIter};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WptFyiSummary {
+    pub results: IndexMap<String, TestSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestSummary {
+    /// Counts of passing and total subtests, serialized as `[pass, total]`.
+    /// Tests without subtests count as a single subtest.
+    #[serde(rename = "s", with = "counts_as_pair")]
+    pub counts: SubtestCounts,
+    /// The status of the test, serialized in abbreviated form (e.g. "O" for OK)
+    #[serde(rename = "c", with = "abbreviated_status")]
+    pub status: TestStatus,
+}
+
+impl WptFyiSummary {
+    /// Parse a summary from a reader containing JSON
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        crate::from_reader(reader)
+    }
+}
+
+impl FromStr for WptFyiSummary {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::from_str(s)
+    }
+}
+
+#[rustfmt::skip]
+impl ScorableReport for WptFyiSummary {
+    type TestResultIter<'a> = (&'a String, &'a TestSummary) where Self: 'a;
+    fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
+        self.results.iter()
+    }
+    fn get(&self, name: &str) -> Option<Self::TestResultIter<'_>> {
+        self.results.get_key_value(name)
+    }
+    fn is_keyed(&self) -> bool {
+        true
+    }
+}
+
+/// The summary format only records subtest counts and the test's status, so individual
+/// subtests can't be looked up: they are always reported as missing.
+impl TestResultIter for (&String, &TestSummary) {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn subtest_counts(&self) -> SubtestCounts {
+        self.1.counts
+    }
+
+    fn subtest_exist_and_passes(&self, _name: &str) -> bool {
+        false
+    }
+
+    fn iter_subtests_results(&self) -> impl Iterator<Item = SubtestNameAndResult<'_>> {
+        std::iter::empty()
+    }
+
+    fn test_passes(&self) -> bool {
+        self.1.status.is_pass()
+    }
+
+    fn status(&self) -> Option<&TestStatus> {
+        Some(&self.1.status)
+    }
+}
+
+impl From<WptReport> for WptFyiSummary {
+    fn from(report: WptReport) -> Self {
+        WptFyiSummary {
+            results: report
+                .results
+                .into_iter()
+                .map(|test| {
+                    let summary = TestSummary {
+                        counts: test.subtest_counts(),
+                        status: test.status,
+                    };
+                    (test.test, summary)
+                })
+                .collect(),
+        }
+    }
+}
+
+mod counts_as_pair {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(counts: &SubtestCounts, s: S) -> Result<S::Ok, S::Error> {
+        [counts.pass, counts.total].serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<SubtestCounts, D::Error> {
+        let [pass, total] = <[u32; 2]>::deserialize(d)?;
+        Ok(SubtestCounts { pass, total })
+    }
+}
+
+/// Statuses are abbreviated as in wpt.fyi's results processor. Statuses without an
+/// abbreviation are written out in full, and unrecognised abbreviations are preserved.
+mod abbreviated_status {
+    use super::*;
+
+    const ABBREVIATIONS: &[(&str, &str)] = &[
+        ("PASS", "P"),
+        ("OK", "O"),
+        ("FAIL", "F"),
+        ("SKIP", "S"),
+        ("ERROR", "E"),
+        ("NOTRUN", "N"),
+        ("CRASH", "C"),
+        ("TIMEOUT", "T"),
+        ("PRECONDITION_FAILED", "PF"),
+    ];
+
+    pub fn serialize<S: Serializer>(status: &TestStatus, s: S) -> Result<S::Ok, S::Error> {
+        let status = status.as_str();
+        let abbreviation = ABBREVIATIONS
+            .iter()
+            .find(|(full, _)| *full == status)
+            .map(|(_, abbreviation)| *abbreviation)
+            .unwrap_or(status);
+        s.serialize_str(abbreviation)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<TestStatus, D::Error> {
+        let abbreviation = SmolStr::deserialize(d)?;
+        let status = ABBREVIATIONS
+            .iter()
+            .find(|(_, abbr)| *abbr == abbreviation)
+            .map(|(full, _)| TestStatus::from(*full))
+            .unwrap_or_else(|| TestStatus::from(abbreviation));
+        Ok(status)
+    }
+}
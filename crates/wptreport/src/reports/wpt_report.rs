@@ -1,10 +1,21 @@
 //! The standard "wptreport" format produced by the official wptrunner as well
 //! as other wpt test runners.
-use crate::{HasRunInfo, ScorableReport, SubtestCounts, SubtestNameAndResult, TestResultIter};
-use serde::{Deserialize, Serialize};
+use crate::{
+    Error, HasRunInfo, ScorableReport, SubtestCounts, SubtestNameAndResult, TestResultIter,
+};
+use indexmap::IndexMap;
+use serde::de::{Error as _, MapAccess, Visitor};
+use serde::ser::SerializeMap as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use smol_str::SmolStr;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+/// The overall status of a test. Statuses not known to this library are preserved
+/// as [`TestStatus::Unknown`] so that reports from newer or non-standard runners can
+/// still be read (and written back out unchanged).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TestStatus {
     Pass,
     Fail,
@@ -15,10 +26,87 @@ pub enum TestStatus {
     Assert,
     PreconditionFailed,
     Skip,
+    Unknown(SmolStr),
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+impl TestStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TestStatus::Pass => "PASS",
+            TestStatus::Fail => "FAIL",
+            TestStatus::Ok => "OK",
+            TestStatus::Error => "ERROR",
+            TestStatus::Timeout => "TIMEOUT",
+            TestStatus::Crash => "CRASH",
+            TestStatus::Assert => "ASSERT",
+            TestStatus::PreconditionFailed => "PRECONDITION_FAILED",
+            TestStatus::Skip => "SKIP",
+            TestStatus::Unknown(status) => status,
+        }
+    }
+
+    /// Whether the status counts as a pass for scoring purposes.
+    /// Unknown statuses never count as passing.
+    pub fn is_pass(&self) -> bool {
+        match self {
+            TestStatus::Pass => true,
+            TestStatus::Fail
+            | TestStatus::Ok
+            | TestStatus::Error
+            | TestStatus::Timeout
+            | TestStatus::Crash
+            | TestStatus::Assert
+            | TestStatus::PreconditionFailed
+            | TestStatus::Skip
+            | TestStatus::Unknown(_) => false,
+        }
+    }
+}
+
+impl From<SmolStr> for TestStatus {
+    fn from(status: SmolStr) -> Self {
+        match status.as_str() {
+            "PASS" => TestStatus::Pass,
+            "FAIL" => TestStatus::Fail,
+            "OK" => TestStatus::Ok,
+            "ERROR" => TestStatus::Error,
+            "TIMEOUT" => TestStatus::Timeout,
+            "CRASH" => TestStatus::Crash,
+            "ASSERT" => TestStatus::Assert,
+            "PRECONDITION_FAILED" => TestStatus::PreconditionFailed,
+            "SKIP" => TestStatus::Skip,
+            _ => TestStatus::Unknown(status),
+        }
+    }
+}
+
+impl From<&str> for TestStatus {
+    fn from(status: &str) -> Self {
+        Self::from(SmolStr::new(status))
+    }
+}
+
+impl fmt::Display for TestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for TestStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TestStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SmolStr::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// The status of a subtest. Statuses not known to this library are preserved
+/// as [`SubtestStatus::Unknown`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SubtestStatus {
     Pass,
     Fail,
@@ -28,83 +116,361 @@ pub enum SubtestStatus {
     PreconditionFailed,
     Notrun,
     Skip,
+    Unknown(SmolStr),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl SubtestStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SubtestStatus::Pass => "PASS",
+            SubtestStatus::Fail => "FAIL",
+            SubtestStatus::Error => "ERROR",
+            SubtestStatus::Timeout => "TIMEOUT",
+            SubtestStatus::Assert => "ASSERT",
+            SubtestStatus::PreconditionFailed => "PRECONDITION_FAILED",
+            SubtestStatus::Notrun => "NOTRUN",
+            SubtestStatus::Skip => "SKIP",
+            SubtestStatus::Unknown(status) => status,
+        }
+    }
+
+    /// Whether the status counts as a pass for scoring purposes.
+    /// Unknown statuses never count as passing.
+    pub fn is_pass(&self) -> bool {
+        match self {
+            SubtestStatus::Pass => true,
+            SubtestStatus::Fail
+            | SubtestStatus::Error
+            | SubtestStatus::Timeout
+            | SubtestStatus::Assert
+            | SubtestStatus::PreconditionFailed
+            | SubtestStatus::Notrun
+            | SubtestStatus::Skip
+            | SubtestStatus::Unknown(_) => false,
+        }
+    }
+}
+
+impl From<SmolStr> for SubtestStatus {
+    fn from(status: SmolStr) -> Self {
+        match status.as_str() {
+            "PASS" => SubtestStatus::Pass,
+            "FAIL" => SubtestStatus::Fail,
+            "ERROR" => SubtestStatus::Error,
+            "TIMEOUT" => SubtestStatus::Timeout,
+            "ASSERT" => SubtestStatus::Assert,
+            "PRECONDITION_FAILED" => SubtestStatus::PreconditionFailed,
+            "NOTRUN" => SubtestStatus::Notrun,
+            "SKIP" => SubtestStatus::Skip,
+            _ => SubtestStatus::Unknown(status),
+        }
+    }
+}
+
+impl From<&str> for SubtestStatus {
+    fn from(status: &str) -> Self {
+        Self::from(SmolStr::new(status))
+    }
+}
+
+impl fmt::Display for SubtestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for SubtestStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SubtestStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SmolStr::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct WptReport {
     pub time_start: u64,
     pub time_end: u64,
     pub run_info: WptRunInfo,
     pub results: Vec<TestResult>,
+    /// Any other top-level keys
+    #[serde(flatten)]
+    pub extra: IndexMap<String, serde_json::Value>,
+}
+
+// Deserialized by hand because `#[serde(flatten)]` would buffer the whole `results` array
+impl<'de> Deserialize<'de> for WptReport {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReportVisitor;
+
+        impl<'de> Visitor<'de> for ReportVisitor {
+            type Value = WptReport;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a wptreport object")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut time_start = None;
+                let mut time_end = None;
+                let mut run_info = None;
+                let mut results = None;
+                let mut extra = IndexMap::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "time_start" => time_start = Some(map.next_value()?),
+                        "time_end" => time_end = Some(map.next_value()?),
+                        "run_info" => run_info = Some(map.next_value()?),
+                        "results" => results = Some(map.next_value()?),
+                        _ => {
+                            let value = map.next_value()?;
+                            extra.insert(key, value);
+                        }
+                    }
+                }
+                Ok(WptReport {
+                    time_start: time_start.ok_or_else(|| A::Error::missing_field("time_start"))?,
+                    time_end: time_end.ok_or_else(|| A::Error::missing_field("time_end"))?,
+                    run_info: run_info.ok_or_else(|| A::Error::missing_field("run_info"))?,
+                    results: results.ok_or_else(|| A::Error::missing_field("results"))?,
+                    extra,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(ReportVisitor)
+    }
+}
+
+impl WptReport {
+    /// Parse a report from a reader containing JSON
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        crate::from_reader(reader)
+    }
+}
+
+impl FromStr for WptReport {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::from_str(s)
+    }
 }
 
+/// Information about the configuration a test run was performed with.
+///
+/// Only `product` and `revision` are required so that reports from runners other than
+/// wptrunner can be read. Keys that are not modelled here are preserved in `extra`.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct WptRunInfo {
     /// The browser engine tested (e.g. "servo")
     pub product: String,
     /// The version of the browser engine tested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub browser_version: Option<String>,
     /// The revision of the WPT test suite that run
     pub revision: String,
 
     // Flags
-    pub automation: bool,
-    pub debug: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub automation: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub display: Option<String>,
-    pub has_sandbox: bool,
-    pub headless: bool,
-    pub verify: bool,
-    pub wasm: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_sandbox: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headless: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm: Option<bool>,
 
     /// The OS that the tests were run on (e.g. "macos")
-    pub os: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
     /// OS version number
-    pub os_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os_version: Option<String>,
     /// Linux distro (if linux)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub linux_distro: Option<String>,
     /// OS version String
-    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     /// The processor architecture the tests were run on (e.g. "arm")
-    pub processor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processor: Option<String>,
     /// The number of bits that the processor has (e.g. 64 for x86_64)
-    pub bits: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits: Option<i64>,
     /// The Python version used to run the tests
-    pub python_version: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_version: Option<i64>,
 
     // OS Flags
-    #[serde(default)]
-    pub apple_catalina: bool,
-    #[serde(default)]
-    pub apple_silicon: bool,
-    #[serde(default)]
-    pub win10_2004: bool,
-    #[serde(default)]
-    pub win10_2009: bool,
-    #[serde(default)]
-    pub win11_2009: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apple_catalina: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apple_silicon: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub win10_2004: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub win10_2009: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub win11_2009: Option<bool>,
+
+    /// Any other keys (e.g. product-specific flags such as `layout_2020`)
+    #[serde(flatten)]
+    pub extra: IndexMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl WptRunInfo {
+    /// The keys whose values differ between two run infos
+    pub fn differing_keys(&self, other: &WptRunInfo) -> Vec<String> {
+        let to_map = |run_info: &WptRunInfo| match serde_json::to_value(run_info) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => unreachable!("WptRunInfo always serializes to a JSON object"),
+        };
+        let a = to_map(self);
+        let b = to_map(other);
+
+        let mut keys: Vec<String> = a
+            .iter()
+            .filter(|(key, value)| b.get(*key) != Some(value))
+            .map(|(key, _)| key.clone())
+            .chain(b.keys().filter(|key| !a.contains_key(*key)).cloned())
+            .collect();
+        keys.sort();
+        keys
+    }
+}
+
+/// The result of a single test. Optional fields are only written if they are set, and other
+/// fields are preserved in `extra`, so that reports round-trip losslessly.
+#[derive(Debug)]
 pub struct TestResult {
     pub test: String,
     pub status: TestStatus,
     pub duration: i64,
-
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
+    /// The expected status (only present if it differs from `status`)
+    pub expected: Option<TestStatus>,
     pub message: Option<String>,
-
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(default)]
     pub known_intermittent: Vec<String>,
+    /// The subsuite the test was run in (`""` for the default subsuite)
+    pub subsuite: Option<String>,
+    pub subtests: Vec<SubtestResult>,
+    /// Whether `subtests` is written even if it is empty (wptrunner always writes it). This is
+    /// set if the result was read with a `subtests` key.
+    pub subtests_key: bool,
+    /// Screenshots taken by reftests, as a map from URL to screenshot hash
+    pub screenshots: Option<IndexMap<String, String>>,
+    /// The number of assertions hit by the test and the range that was expected
+    pub asserts: Option<AssertCounts>,
+    /// Any other fields
+    pub extra: IndexMap<String, serde_json::Value>,
+}
 
-    #[serde(skip_serializing_if = "String::is_empty")]
-    #[serde(default)]
-    pub subsuite: String,
+impl Serialize for TestResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("test", &self.test)?;
+        map.serialize_entry("status", &self.status)?;
+        map.serialize_entry("duration", &self.duration)?;
+        if let Some(expected) = &self.expected {
+            map.serialize_entry("expected", expected)?;
+        }
+        if let Some(message) = &self.message {
+            map.serialize_entry("message", message)?;
+        }
+        if !self.known_intermittent.is_empty() {
+            map.serialize_entry("known_intermittent", &self.known_intermittent)?;
+        }
+        if let Some(subsuite) = &self.subsuite {
+            map.serialize_entry("subsuite", subsuite)?;
+        }
+        if self.subtests_key || !self.subtests.is_empty() {
+            map.serialize_entry("subtests", &self.subtests)?;
+        }
+        if let Some(screenshots) = &self.screenshots {
+            map.serialize_entry("screenshots", screenshots)?;
+        }
+        if let Some(asserts) = &self.asserts {
+            map.serialize_entry("asserts", asserts)?;
+        }
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(default)]
-    pub subtests: Vec<SubtestResult>,
+impl<'de> Deserialize<'de> for TestResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ResultVisitor;
+
+        impl<'de> Visitor<'de> for ResultVisitor {
+            type Value = TestResult;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a test result object")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut test = None;
+                let mut status = None;
+                let mut duration = None;
+                let mut result = TestResult {
+                    test: String::new(),
+                    status: TestStatus::Pass,
+                    duration: 0,
+                    expected: None,
+                    message: None,
+                    known_intermittent: Vec::new(),
+                    subsuite: None,
+                    subtests: Vec::new(),
+                    subtests_key: false,
+                    screenshots: None,
+                    asserts: None,
+                    extra: IndexMap::new(),
+                };
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "test" => test = Some(map.next_value()?),
+                        "status" => status = Some(map.next_value()?),
+                        "duration" => duration = Some(map.next_value()?),
+                        "expected" => result.expected = map.next_value()?,
+                        "message" => result.message = map.next_value()?,
+                        "known_intermittent" => result.known_intermittent = map.next_value()?,
+                        "subsuite" => result.subsuite = map.next_value()?,
+                        "subtests" => {
+                            result.subtests = map.next_value()?;
+                            result.subtests_key = true;
+                        }
+                        "screenshots" => result.screenshots = map.next_value()?,
+                        "asserts" => result.asserts = map.next_value()?,
+                        _ => {
+                            let value = map.next_value()?;
+                            result.extra.insert(key, value);
+                        }
+                    }
+                }
+                result.test = test.ok_or_else(|| A::Error::missing_field("test"))?;
+                result.status = status.ok_or_else(|| A::Error::missing_field("status"))?;
+                result.duration = duration.ok_or_else(|| A::Error::missing_field("duration"))?;
+                Ok(result)
+            }
+        }
+
+        deserializer.deserialize_map(ResultVisitor)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssertCounts {
+    pub count: u32,
+    pub min: u32,
+    pub max: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -112,6 +478,11 @@ pub struct SubtestResult {
     pub name: String,
     pub status: SubtestStatus,
 
+    /// The expected status (only present if it differs from `status`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub expected: Option<SubtestStatus>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub message: Option<String>,
@@ -119,6 +490,10 @@ pub struct SubtestResult {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub known_intermittent: Vec<String>,
+
+    /// Any other fields (preserved so that reports round-trip losslessly)
+    #[serde(flatten)]
+    pub extra: IndexMap<String, serde_json::Value>,
 }
 
 #[rustfmt::skip]
@@ -135,7 +510,7 @@ impl HasRunInfo for WptReport {
     }
 }
 
-impl TestResultIter for &TestResult {
+impl TestResultIter for TestResult {
     fn name(&self) -> &str {
         &self.test
     }
@@ -146,11 +521,11 @@ impl TestResultIter for &TestResult {
         if total == 0 {
             SubtestCounts {
                 total: 1,
-                pass: (self.status == TestStatus::Pass) as u32,
+                pass: self.status.is_pass() as u32,
             }
         } else {
             let pass = self.subtests.iter().fold(0, |mut pass_count, subtest| {
-                pass_count += (subtest.status == SubtestStatus::Pass) as u32;
+                pass_count += subtest.status.is_pass() as u32;
                 pass_count
             });
             SubtestCounts { pass, total }
@@ -161,7 +536,7 @@ impl TestResultIter for &TestResult {
         self.subtests
             .iter()
             .find(|s| s.name == name)
-            .map(|s| s.status == SubtestStatus::Pass)
+            .map(|s| s.status.is_pass())
             .unwrap_or(false)
     }
 
@@ -170,7 +545,41 @@ impl TestResultIter for &TestResult {
             .iter()
             .map(|s: &SubtestResult| SubtestNameAndResult {
                 name: &s.name,
-                passes: s.status == SubtestStatus::Pass,
+                passes: s.status.is_pass(),
             })
     }
+
+    fn test_passes(&self) -> bool {
+        self.status.is_pass()
+    }
+
+    fn as_test_result(&self) -> Option<&TestResult> {
+        Some(self)
+    }
+}
+
+impl TestResultIter for &TestResult {
+    fn name(&self) -> &str {
+        TestResultIter::name(*self)
+    }
+
+    fn subtest_counts(&self) -> SubtestCounts {
+        TestResultIter::subtest_counts(*self)
+    }
+
+    fn subtest_exist_and_passes(&self, name: &str) -> bool {
+        TestResultIter::subtest_exist_and_passes(*self, name)
+    }
+
+    fn iter_subtests_results(&self) -> impl Iterator<Item = SubtestNameAndResult<'_>> {
+        TestResultIter::iter_subtests_results(*self)
+    }
+
+    fn test_passes(&self) -> bool {
+        self.status.is_pass()
+    }
+
+    fn as_test_result(&self) -> Option<&TestResult> {
+        Some(self)
+    }
 }
@@ -0,0 +1,419 @@
+//! A streaming reader for the "wptreport" format which yields test results one at a time
+//! without ever holding the whole `results` array in memory.
+use std::cell::RefCell;
+use std::io::BufRead;
+
+use indexmap::IndexMap;
+use serde::de::{DeserializeOwned, Error as _};
+
+use super::wpt_report::{TestResult, WptReport, WptRunInfo};
+use crate::error::{Error, Result};
+use crate::ScorableReport;
+
+/// The top-level fields of a `WptReport` (everything except `results`)
+#[derive(Debug, Clone)]
+pub struct WptReportHeader {
+    pub time_start: u64,
+    pub time_end: u64,
+    pub run_info: WptRunInfo,
+    /// Any other top-level keys
+    pub extra: IndexMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum State {
+    /// Reading top-level keys before the `results` array
+    Header,
+    /// Inside the `results` array
+    Results,
+    /// The document has been fully read
+    Done,
+    /// An error has been returned and no further results will be produced
+    Failed,
+}
+
+/// Reads a `WptReport` from any [`BufRead`], yielding one [`TestResult`] at a time.
+///
+/// Top-level fields that precede `results` in the document are available as soon as the
+/// stream is created. Fields that follow `results` (typically `time_end`) only become
+/// available once every result has been read. Use [`WptReportStream::finish`] to retrieve
+/// all of them.
+pub struct WptReportStream<R> {
+    reader: R,
+    state: State,
+    /// Whether the next array element or object member is the first one
+    first: bool,
+    /// Number of results yielded so far
+    index: usize,
+    /// Number of bytes consumed from the reader (used in error messages)
+    offset: u64,
+    /// Scratch buffer holding the raw JSON of the value currently being parsed
+    buf: Vec<u8>,
+
+    time_start: Option<u64>,
+    time_end: Option<u64>,
+    run_info: Option<WptRunInfo>,
+    extra: IndexMap<String, serde_json::Value>,
+}
+
+impl<R: BufRead> WptReportStream<R> {
+    /// Create a stream, reading the top-level fields that precede the `results` array
+    pub fn new(reader: R) -> Result<Self> {
+        let mut stream = Self::unstarted(reader);
+        stream.skip_whitespace()?;
+        stream.expect(b'{')?;
+        stream.read_header()?;
+        Ok(stream)
+    }
+
+    /// A stream that hasn't read anything from `reader` yet
+    fn unstarted(reader: R) -> Self {
+        Self {
+            reader,
+            state: State::Header,
+            first: true,
+            index: 0,
+            offset: 0,
+            buf: Vec::new(),
+            time_start: None,
+            time_end: None,
+            run_info: None,
+            extra: IndexMap::new(),
+        }
+    }
+
+    pub fn run_info(&self) -> Option<&WptRunInfo> {
+        self.run_info.as_ref()
+    }
+
+    pub fn time_start(&self) -> Option<u64> {
+        self.time_start
+    }
+
+    pub fn time_end(&self) -> Option<u64> {
+        self.time_end
+    }
+
+    /// Read (and discard) any remaining results and return the top-level fields of the report
+    pub fn finish(mut self) -> Result<WptReportHeader> {
+        for result in &mut self {
+            result?;
+        }
+
+        fn missing(field: &'static str) -> Error {
+            let err = serde_json::Error::missing_field(field);
+            Error::json(String::from("."), err)
+        }
+
+        Ok(WptReportHeader {
+            time_start: self.time_start.ok_or_else(|| missing("time_start"))?,
+            time_end: self.time_end.ok_or_else(|| missing("time_end"))?,
+            run_info: self.run_info.ok_or_else(|| missing("run_info"))?,
+            extra: self.extra,
+        })
+    }
+
+    /// Read the whole report into memory
+    pub fn into_report(mut self) -> Result<WptReport> {
+        let results = (&mut self).collect::<Result<Vec<_>>>()?;
+        let header = self.finish()?;
+        Ok(WptReport {
+            time_start: header.time_start,
+            time_end: header.time_end,
+            run_info: header.run_info,
+            results,
+            extra: header.extra,
+        })
+    }
+
+    /// Read top-level object members until the start of the `results` array or the end of
+    /// the document
+    fn read_header(&mut self) -> Result<()> {
+        loop {
+            let Some(key) = self.read_key()? else {
+                self.state = State::Done;
+                return Ok(());
+            };
+            match key.as_str() {
+                "results" => {
+                    self.expect(b'[')?;
+                    self.state = State::Results;
+                    self.first = true;
+                    return Ok(());
+                }
+                "time_start" => {
+                    self.read_raw_value()?;
+                    self.time_start = Some(self.parse_buf("time_start")?);
+                }
+                "time_end" => {
+                    self.read_raw_value()?;
+                    self.time_end = Some(self.parse_buf("time_end")?);
+                }
+                "run_info" => {
+                    self.read_raw_value()?;
+                    self.run_info = Some(self.parse_buf("run_info")?);
+                }
+                _ => {
+                    self.read_raw_value()?;
+                    let value = self.parse_buf(&key)?;
+                    self.extra.insert(key, value);
+                }
+            }
+        }
+    }
+
+    /// Read the key of the next top-level object member, up to the start of its value.
+    /// Returns `None` at the end of the object.
+    fn read_key(&mut self) -> Result<Option<String>> {
+        self.skip_whitespace()?;
+        if self.peek()? == Some(b'}') {
+            self.consume(1);
+            return Ok(None);
+        }
+        if !self.first {
+            self.expect(b',')?;
+        }
+        self.first = false;
+
+        self.read_raw_value()?;
+        let key = self.parse_buf(".")?;
+        self.skip_whitespace()?;
+        self.expect(b':')?;
+        self.skip_whitespace()?;
+        Ok(Some(key))
+    }
+
+    /// Read the next element of the `results` array. Returns `None` at the end of the array.
+    fn read_result(&mut self) -> Result<Option<TestResult>> {
+        self.skip_whitespace()?;
+        if self.peek()? == Some(b']') {
+            self.consume(1);
+            self.state = State::Header;
+            self.first = false;
+            self.read_header()?;
+            return Ok(None);
+        }
+        if !self.first {
+            self.expect(b',')?;
+        }
+        self.first = false;
+
+        self.read_raw_value()?;
+        let path = format!("results[{}]", self.index);
+        let result = self.parse_buf(&path)?;
+        self.index += 1;
+        Ok(Some(result))
+    }
+
+    /// Parse the contents of `buf`, prefixing the path of any error with `path`
+    fn parse_buf<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let mut deserializer = serde_json::Deserializer::from_slice(&self.buf);
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+            let full_path = match (path, err.path().to_string()) {
+                (".", inner) => inner,
+                (outer, inner) if inner == "." => outer.to_string(),
+                (outer, inner) => format!("{outer}.{inner}"),
+            };
+            Error::json(full_path, err.into_inner())
+        })
+    }
+
+    /// Copy the raw bytes of the next JSON value into `buf`. The value is not validated
+    /// beyond tracking nesting and string boundaries.
+    fn read_raw_value(&mut self) -> Result<()> {
+        self.skip_whitespace()?;
+        self.buf.clear();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Err(self.syntax_error("unexpected end of file"));
+            }
+
+            let mut end = None;
+            for (i, &byte) in available.iter().enumerate() {
+                if in_string {
+                    if escaped {
+                        escaped = false;
+                    } else if byte == b'\\' {
+                        escaped = true;
+                    } else if byte == b'"' {
+                        in_string = false;
+                        if depth == 0 {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    }
+                    continue;
+                }
+
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' if depth == 0 => {
+                        end = Some(i);
+                        break;
+                    }
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    }
+                    b',' | b' ' | b'\n' | b'\r' | b'\t' if depth == 0 => {
+                        end = Some(i);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            let len = end.unwrap_or(available.len());
+            self.buf.extend_from_slice(&available[..len]);
+            self.consume(len);
+            if end.is_some() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.reader.consume(amount);
+        self.offset += amount as u64;
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(());
+            }
+            let whitespace = available
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+            let done = whitespace < available.len();
+            self.consume(whitespace);
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<()> {
+        match self.peek()? {
+            Some(byte) if byte == expected => {
+                self.consume(1);
+                Ok(())
+            }
+            Some(byte) => Err(self.syntax_error(&format!(
+                "expected `{}`, found `{}`",
+                expected as char, byte as char
+            ))),
+            None => Err(self.syntax_error(&format!(
+                "expected `{}`, found end of file",
+                expected as char
+            ))),
+        }
+    }
+
+    fn syntax_error(&self, msg: &str) -> Error {
+        let err = serde_json::Error::custom(format_args!("{msg} at byte {}", self.offset));
+        let path = match self.state {
+            State::Results => format!("results[{}]", self.index),
+            _ => String::from("."),
+        };
+        Error::json(path, err)
+    }
+}
+
+/// Read the keys of the top-level object of any JSON document until `probe` recognises one.
+/// The values of the other keys are skipped over without being parsed.
+pub(crate) fn probe_top_level_keys<R: BufRead, T>(
+    reader: R,
+    mut probe: impl FnMut(&str) -> Option<T>,
+) -> Result<Option<T>> {
+    let mut stream = WptReportStream::unstarted(reader);
+    stream.skip_whitespace()?;
+    stream.expect(b'{')?;
+    while let Some(key) = stream.read_key()? {
+        if let Some(found) = probe(&key) {
+            return Ok(Some(found));
+        }
+        stream.read_raw_value()?;
+    }
+    Ok(None)
+}
+
+impl<R: BufRead> Iterator for WptReportStream<R> {
+    type Item = Result<TestResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state != State::Results {
+            return None;
+        }
+        match self.read_result() {
+            Ok(result) => result.map(Ok),
+            Err(err) => {
+                self.state = State::Failed;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Adapts a [`WptReportStream`] to the [`ScorableReport`] interface so that it can be
+/// scored without loading the whole report into memory.
+///
+/// The results can only be iterated once: subsequent calls to `results()` yield nothing.
+/// If an error is encountered, iteration stops early and the error is returned from
+/// [`StreamingReport::finish`].
+pub struct StreamingReport<R> {
+    stream: RefCell<WptReportStream<R>>,
+    error: RefCell<Option<Error>>,
+}
+
+impl<R: BufRead> StreamingReport<R> {
+    pub fn new(stream: WptReportStream<R>) -> Self {
+        Self {
+            stream: RefCell::new(stream),
+            error: RefCell::new(None),
+        }
+    }
+
+    /// Return the first error encountered while iterating (if any), or else the top-level
+    /// fields of the report
+    pub fn finish(self) -> Result<WptReportHeader> {
+        if let Some(err) = self.error.into_inner() {
+            return Err(err);
+        }
+        self.stream.into_inner().finish()
+    }
+}
+
+#[rustfmt::skip]
+impl<R: BufRead> ScorableReport for StreamingReport<R> {
+    type TestResultIter<'a> = TestResult where Self: 'a;
+    fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
+        std::iter::from_fn(move || {
+            if self.error.borrow().is_some() {
+                return None;
+            }
+            match self.stream.borrow_mut().next()? {
+                Ok(result) => Some(result),
+                Err(err) => {
+                    *self.error.borrow_mut() = Some(err);
+                    None
+                }
+            }
+        })
+    }
+}
@@ -1,56 +1,284 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use serde::Serialize;
+
+use crate::aggregate::{DiffStatus, TestDiff};
+use crate::wpt_report::{SubtestStatus, TestResult, TestStatus};
 use crate::{AreaScores, ScorableReport, SubtestCounts, TestResultIter};
 
+/// Score a report with the default [`ScoringPolicy`]
 pub fn score_wpt_report<Report>(report: &Report) -> BTreeMap<String, AreaScores>
 where
     Report: ScorableReport,
 {
-    let mut results = BTreeMap::<String, AreaScores>::new();
+    score_wpt_report_with(report, &ScoringPolicy::default())
+}
 
+/// Score a report, deciding which results pass with `policy`
+pub fn score_wpt_report_with<Report>(
+    report: &Report,
+    policy: &ScoringPolicy,
+) -> BTreeMap<String, AreaScores>
+where
+    Report: ScorableReport,
+{
+    let mut scorer = AreaScorer::new();
     for test in report.results() {
-        // Compute scores for the test
-        let counts = test.subtest_counts();
-        let passes = counts.all_passing();
-        // let pass_fraction = counts.pass_fraction();
-
-        // Update the scores for each area that the test belongs to
-        for area in area_iter(test.name()) {
-            if results.contains_key(area) {
-                let test_scores = results.get_mut(area).unwrap();
-                test_scores.tests.pass += passes as u32;
-                test_scores.tests.total += 1;
-                test_scores.subtests.pass += counts.pass;
-                test_scores.subtests.total += counts.total;
-                test_scores.interop_score_sum += counts.passes_per_1000() as u64;
-                test_scores.pass_fraction_sum += counts.pass_fraction();
-            } else {
-                let test_scores = AreaScores {
-                    tests: SubtestCounts {
-                        pass: passes as u32,
-                        total: 1,
-                    },
-                    subtests: counts,
-                    // The sum of the interop scores for each individual test, but not
-                    // divided by the total number of tests
-                    interop_score_sum: counts.passes_per_1000() as u64,
-                    // The sum of the "fraction of passing subtests" for each individual test,
-                    // but not divided by the total number of tests
-                    pass_fraction_sum: counts.pass_fraction(),
-                };
-                results.insert(area.to_string(), test_scores);
+        if let Some(counts) = policy.test_counts(&test) {
+            scorer.add_test(test.name(), counts);
+        }
+    }
+    scorer.finish()
+}
+
+/// Score a report against a reference report with the default [`ScoringPolicy`].
+/// See [`score_wpt_report_against_with`].
+pub fn score_wpt_report_against<Report, Reference>(
+    report: &Report,
+    reference: &Reference,
+) -> BTreeMap<String, AreaScores>
+where
+    Report: ScorableReport,
+    Reference: ScorableReport,
+{
+    score_wpt_report_against_with(report, reference, &ScoringPolicy::default())
+}
+
+/// Score a report against a reference report, deciding which results pass with `policy`.
+/// Only tests and subtests that are in the reference are counted, and those that are
+/// missing from the report count as failures. Where the reference only records how many
+/// subtests a test has (as wpt.fyi summaries do), the report's own counts are used for the
+/// test instead. This gives the same scores as
+/// [`WptScores::score_against`](crate::servo_test_scores::WptScores::score_against) but works
+/// for any pair of report formats.
+pub fn score_wpt_report_against_with<Report, Reference>(
+    report: &Report,
+    reference: &Reference,
+    policy: &ScoringPolicy,
+) -> BTreeMap<String, AreaScores>
+where
+    Report: ScorableReport,
+    Reference: ScorableReport,
+{
+    let mut scorer = AreaScorer::new();
+    if report.is_keyed() {
+        for reference_test in reference.results() {
+            let test = report.get(reference_test.name());
+            add_test_against(&mut scorer, policy, test.as_ref(), &reference_test);
+        }
+    } else {
+        // Names may be borrowed from the test results, so they are indexed once collected
+        let tests: Vec<_> = report.results().collect();
+        let tests: HashMap<&str, _> = tests.iter().map(|test| (test.name(), test)).collect();
+        for reference_test in reference.results() {
+            let test = tests.get(reference_test.name()).copied();
+            add_test_against(&mut scorer, policy, test, &reference_test);
+        }
+    }
+    scorer.finish()
+}
+
+/// Score `reference_test` by the result of the same test in the report (if it has one)
+fn add_test_against<T: TestResultIter, R: TestResultIter>(
+    scorer: &mut AreaScorer,
+    policy: &ScoringPolicy,
+    test: Option<&T>,
+    reference_test: &R,
+) {
+    if let Some(counts) = policy.test_counts_against(test, reference_test) {
+        scorer.add_test(reference_test.name(), counts);
+    }
+}
+
+/// Decides which results count as passing and how tests are weighted when scoring.
+/// The default policy only counts PASS as passing and counts a test without subtests as
+/// a single subtest.
+///
+/// Formats which only record whether each test and subtest passed (such as Servo scores
+/// files) were already reduced to pass or fail when they were created, so only
+/// `tests_without_subtests` applies to them.
+#[derive(Debug, Clone)]
+pub struct ScoringPolicy {
+    /// The statuses that count as passing for a test without subtests
+    pub passing_test_statuses: Vec<TestStatus>,
+    /// The statuses that count as passing for a subtest
+    pub passing_subtest_statuses: Vec<SubtestStatus>,
+    /// How tests without subtests are weighted
+    pub tests_without_subtests: TestsWithoutSubtests,
+    /// Whether a test with subtests only scores if its harness status is OK. If false then
+    /// e.g. a test which times out still scores for the subtests that passed before it did.
+    pub require_harness_ok: bool,
+}
+
+impl Default for ScoringPolicy {
+    fn default() -> Self {
+        Self {
+            passing_test_statuses: vec![TestStatus::Pass],
+            passing_subtest_statuses: vec![SubtestStatus::Pass],
+            tests_without_subtests: TestsWithoutSubtests::OneSubtest,
+            require_harness_ok: false,
+        }
+    }
+}
+
+/// How a test without subtests is weighted when scoring
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TestsWithoutSubtests {
+    /// Count the test as a single subtest which passes if the test passes
+    #[default]
+    OneSubtest,
+    /// Leave the test out of the scores
+    Exclude,
+}
+
+impl ScoringPolicy {
+    /// The subtest counts that a test scores, or `None` if the test is left out of the scores
+    pub fn test_counts<T: TestResultIter>(&self, test: &T) -> Option<SubtestCounts> {
+        let Some(result) = test.as_test_result() else {
+            // Formats which only record subtest counts (such as wpt.fyi summaries) don't list
+            // subtest names, so a test without subtests is one that counts a single subtest
+            // (and whose status, if recorded, isn't the harness status OK)
+            let counts = test.subtest_counts();
+            let no_subtests = test.iter_subtests_results().next().is_none()
+                && counts.total <= 1
+                && test.status() != Some(&TestStatus::Ok);
+            if no_subtests {
+                return self.subtestless_counts(self.test_passes(test) as u32);
+            }
+            // The policy can still apply to the harness status if the format records it
+            if self.require_harness_ok && test.status().is_some_and(|s| *s != TestStatus::Ok) {
+                return Some(SubtestCounts { pass: 0, ..counts });
+            }
+            return Some(counts);
+        };
+        self.result_counts(result)
+    }
+
+    /// The subtest counts that a test scores against the same test in a reference run (see
+    /// [`score_wpt_report_against_with`]). `test` is `None` if the report doesn't contain it.
+    pub fn test_counts_against<T: TestResultIter, R: TestResultIter>(
+        &self,
+        test: Option<&T>,
+        reference_test: &R,
+    ) -> Option<SubtestCounts> {
+        let mut reference_subtests = reference_test.iter_subtests_results().peekable();
+        if reference_subtests.peek().is_some() {
+            let passing = test
+                .and_then(|test| test.as_test_result())
+                .map(|result| self.passing_subtests(result));
+            let mut counts = SubtestCounts::default();
+            for subtest in reference_subtests {
+                counts.total += 1;
+                counts.pass += match (&passing, test) {
+                    (Some(passing), _) => passing.contains(subtest.name),
+                    // Formats without statuses can look up whether a subtest passed directly
+                    (None, Some(test)) => test.subtest_exist_and_passes(subtest.name),
+                    (None, None) => false,
+                } as u32;
+            }
+            return Some(counts);
+        }
+
+        let reference_counts = reference_test.subtest_counts();
+        if reference_counts.total > 1 {
+            // The reference only records how many subtests the test has, so they can't be
+            // matched by name
+            return match test {
+                Some(test) => self.test_counts(test),
+                None => Some(SubtestCounts {
+                    pass: 0,
+                    total: reference_counts.total,
+                }),
+            };
+        }
+
+        let pass = test.is_some_and(|test| self.test_passes(test));
+        self.subtestless_counts(pass as u32)
+    }
+
+    fn result_counts(&self, result: &TestResult) -> Option<SubtestCounts> {
+        if result.subtests.is_empty() {
+            let passes = self.passing_test_statuses.contains(&result.status);
+            return self.subtestless_counts(passes as u32);
+        }
+        let pass = if self.require_harness_ok && result.status != TestStatus::Ok {
+            0
+        } else {
+            result
+                .subtests
+                .iter()
+                .filter(|subtest| self.passing_subtest_statuses.contains(&subtest.status))
+                .count() as u32
+        };
+        Some(SubtestCounts {
+            pass,
+            total: result.subtests.len() as u32,
+        })
+    }
+
+    /// Whether the test itself (rather than its subtests) passes
+    fn test_passes<T: TestResultIter>(&self, test: &T) -> bool {
+        match test.status() {
+            Some(status) => self.passing_test_statuses.contains(status),
+            None => test.test_passes(),
+        }
+    }
+
+    /// The names of the subtests of a test which pass
+    fn passing_subtests<'a>(&self, result: &'a TestResult) -> HashSet<&'a str> {
+        if self.require_harness_ok && result.status != TestStatus::Ok {
+            return HashSet::new();
+        }
+        result
+            .subtests
+            .iter()
+            .filter(|subtest| self.passing_subtest_statuses.contains(&subtest.status))
+            .map(|subtest| subtest.name.as_str())
+            .collect()
+    }
+
+    /// The subtest counts for a test without subtests which scored `pass` (0 or 1)
+    pub fn subtestless_counts(&self, pass: u32) -> Option<SubtestCounts> {
+        match self.tests_without_subtests {
+            TestsWithoutSubtests::OneSubtest => Some(SubtestCounts { pass, total: 1 }),
+            TestsWithoutSubtests::Exclude => None,
+        }
+    }
+}
+
+/// Adds up the subtest counts of each test into scores for each area that the test
+/// belongs to. This is shared by every way of scoring a report.
+#[derive(Debug, Default)]
+pub struct AreaScorer {
+    results: BTreeMap<String, AreaScores>,
+}
+
+impl AreaScorer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_test(&mut self, test_name: &str, counts: SubtestCounts) {
+        for area in area_iter(test_name) {
+            // Only allocate the area name the first time that the area is seen
+            let scores = match self.results.get_mut(area) {
+                Some(scores) => scores,
+                None => self.results.entry(area.to_string()).or_default(),
             };
+            scores.add_test(counts);
         }
     }
 
-    results
+    pub fn finish(self) -> BTreeMap<String, AreaScores> {
+        self.results
+    }
 }
 
 pub(crate) fn area_iter(test_path: &str) -> impl Iterator<Item = &str> {
+    // A test name without a '/' is only in the root area
     let stripped_path = test_path
         .rsplit_once('/')
-        .expect("Test name will contain at least one '/' character")
-        .0
+        .map_or("", |(dir, _)| dir)
         .trim_end_matches('/');
 
     stripped_path
@@ -59,3 +287,147 @@ pub(crate) fn area_iter(test_path: &str) -> impl Iterator<Item = &str> {
         .chain(std::iter::once(stripped_path.len()))
         .map(|idx| &stripped_path[0..idx])
 }
+
+/// The change in an area's scores between two runs
+#[derive(Debug, Clone, Serialize)]
+pub struct AreaDelta {
+    pub area: String,
+    /// The area's scores in the first run (all zero if the area didn't exist)
+    pub before: AreaScores,
+    /// The area's scores in the second run (all zero if the area doesn't exist)
+    pub after: AreaScores,
+    /// The change in the number of passing tests
+    pub tests_passing: i64,
+    /// The change in the number of passing subtests
+    pub subtests_passing: i64,
+    /// The change in the Servo score (the sum of each test's fraction of passing subtests)
+    pub servo_score: f64,
+    /// The change in the Servo score as a fraction of the area's tests, which is the change in
+    /// the percentage shown on the Servo dashboard (divided by 100)
+    pub servo_score_fraction: f64,
+    /// The change in the interop score (a number between -1000 and 1000)
+    pub interop_score: i32,
+    /// The tests that contributed most to the change, largest change in Servo score first
+    pub top_tests: Vec<TestDelta>,
+}
+
+impl AreaDelta {
+    /// The size of the change, for ranking areas. This is the absolute change in Servo score,
+    /// so that large areas aren't hidden by small areas with big percentage changes.
+    pub fn impact(&self) -> f64 {
+        self.servo_score.abs()
+    }
+}
+
+/// One test's contribution to the change in an area's scores
+#[derive(Debug, Clone, Serialize)]
+pub struct TestDelta {
+    pub test: String,
+    pub status: DiffStatus,
+    /// The change in whether the test passes (-1, 0 or 1)
+    pub tests_passing: i64,
+    /// The change in the number of passing subtests
+    pub subtests_passing: i64,
+    /// The change in the test's fraction of passing subtests
+    pub servo_score: f64,
+}
+
+impl TestDelta {
+    fn new(diff: &TestDiff) -> Self {
+        let before = diff.before_counts.unwrap_or_default();
+        let after = diff.after_counts.unwrap_or_default();
+        let passes = |counts: Option<SubtestCounts>| counts.is_some_and(|c| c.all_passing()) as i64;
+        Self {
+            test: diff.test.clone(),
+            status: diff.status,
+            tests_passing: passes(diff.after_counts) - passes(diff.before_counts),
+            subtests_passing: after.pass as i64 - before.pass as i64,
+            servo_score: after.pass_fraction() - before.pass_fraction(),
+        }
+    }
+}
+
+/// Compute the change in scores for each area in either `before` or `after`, in area order.
+/// Use [`sort_by_impact`] to put the biggest changes first.
+///
+/// `diffs` (from [`crate::aggregate::diff`] on the runs the scores came from) is used to find
+/// the tests that caused each change. Up to `max_tests` of them are kept per area. Their
+/// contributions are computed from each test's own results, so may not add up to the area's
+/// change when the scores were computed against a reference run.
+pub fn area_deltas(
+    before: &BTreeMap<String, AreaScores>,
+    after: &BTreeMap<String, AreaScores>,
+    diffs: &[TestDiff],
+    max_tests: usize,
+) -> Vec<AreaDelta> {
+    // Attribute each changed test to every area that it belongs to
+    let mut tests_by_area = BTreeMap::<&str, Vec<TestDelta>>::new();
+    for diff in diffs.iter().filter(|diff| diff.status != DiffStatus::Same) {
+        let delta = TestDelta::new(diff);
+        // Added and removed tests always count as they change the number of tests in the area
+        let unchanged = delta.servo_score == 0.0 && delta.subtests_passing == 0;
+        if unchanged && diff.status == DiffStatus::Changed {
+            continue;
+        }
+        for area in area_iter(&diff.test) {
+            tests_by_area.entry(area).or_default().push(delta.clone());
+        }
+    }
+
+    let mut areas: Vec<&String> = before.keys().chain(after.keys()).collect();
+    areas.sort_unstable();
+    areas.dedup();
+
+    areas
+        .into_iter()
+        .map(|area| {
+            let before = before.get(area).copied().unwrap_or_default();
+            let after = after.get(area).copied().unwrap_or_default();
+            let mut top_tests = tests_by_area.remove(area.as_str()).unwrap_or_default();
+            top_tests.sort_by(|a, b| {
+                b.servo_score
+                    .abs()
+                    .total_cmp(&a.servo_score.abs())
+                    .then(b.subtests_passing.abs().cmp(&a.subtests_passing.abs()))
+                    .then_with(|| a.test.cmp(&b.test))
+            });
+            top_tests.truncate(max_tests);
+            AreaDelta {
+                area: area.clone(),
+                before,
+                after,
+                tests_passing: after.tests.pass as i64 - before.tests.pass as i64,
+                subtests_passing: after.subtests.pass as i64 - before.subtests.pass as i64,
+                servo_score: after.servo_score() - before.servo_score(),
+                servo_score_fraction: servo_fraction(&after) - servo_fraction(&before),
+                interop_score: interop_score(&after) - interop_score(&before),
+                top_tests,
+            }
+        })
+        .collect()
+}
+
+/// Sort area deltas so that the biggest changes (by [`AreaDelta::impact`]) come first
+pub fn sort_by_impact(deltas: &mut [AreaDelta]) {
+    deltas.sort_by(|a, b| {
+        b.impact()
+            .total_cmp(&a.impact())
+            .then_with(|| a.area.cmp(&b.area))
+    });
+}
+
+fn servo_fraction(scores: &AreaScores) -> f64 {
+    if scores.tests.total == 0 {
+        0.0
+    } else {
+        scores.servo_score() / scores.tests.total as f64
+    }
+}
+
+fn interop_score(scores: &AreaScores) -> i32 {
+    if scores.tests.total == 0 {
+        0
+    } else {
+        scores.interop_score() as i32
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::{BTreeMap, HashSet};
 
-use crate::score_summary::{FocusArea, RunScores, RunSummary, ScoreSummaryReport};
+use crate::aggregate::aggregate_rows;
+use crate::interop_summary::{InteropScores, InteropSummaryReport};
+use crate::score::AreaScorer;
+use crate::score_summary::{FocusArea, RunScores, RunSummary, ScoreSummaryReport, ScoringMethod};
 use crate::wpt_report::WptRunInfo;
-use crate::AreaScores;
+use crate::{AreaScores, HasRunInfo, ScorableReport, SubtestCounts, TestResultIter};
 
 pub struct RunInfoWithScores {
     pub date: String,
@@ -10,9 +13,12 @@ pub struct RunInfoWithScores {
     pub scores: BTreeMap<String, AreaScores>,
 }
 
+/// Summarise the scores of each run for each focus area (or each area if `focus_areas` is
+/// `None`), including the scores for each of `methods`
 pub fn summarize_results(
     runs: &[RunInfoWithScores],
     focus_areas: Option<&[FocusArea]>,
+    methods: &[ScoringMethod],
 ) -> ScoreSummaryReport {
     let focus_areas = focus_areas
         .map(|areas| areas.to_vec())
@@ -22,7 +28,12 @@ pub fn summarize_results(
         .iter()
         .map(|run| RunSummary {
             date: run.date.clone(),
-            wpt_revision: run.info.revision[0..9].to_string(),
+            wpt_revision: run
+                .info
+                .revision
+                .get(0..9)
+                .unwrap_or(&run.info.revision)
+                .to_string(),
             product_revision: run
                 .info
                 .browser_version
@@ -31,12 +42,13 @@ pub fn summarize_results(
             scores: focus_areas
                 .iter()
                 .map(|focus_area| {
-                    RunScores::from(
+                    RunScores::with_methods(
                         focus_area
                             .areas
                             .iter()
                             .map(|area| run.scores.get(area).cloned().unwrap_or_default())
                             .sum::<AreaScores>(),
+                        methods,
                     )
                 })
                 .collect(),
@@ -71,3 +83,140 @@ pub fn default_focus_areas(runs: &[RunInfoWithScores]) -> Vec<FocusArea> {
 
     focus_areas
 }
+
+/// Score runs of the same tests in several browsers (one report per browser) in the way that
+/// Interop does. Each browser is scored on every test in any of the reports, with tests it
+/// didn't run counting as failures. The interop score only counts the subtests that pass in
+/// every browser.
+///
+/// See: https://github.com/web-platform-tests/results-analysis/blob/0357bcf8973a6de5f544e1f82e50e7322805e214/interop-scoring/main.js
+pub fn summarize_interop<R>(
+    reports: &[R],
+    focus_areas: Option<&[FocusArea]>,
+) -> InteropSummaryReport
+where
+    R: ScorableReport + HasRunInfo,
+{
+    let mut browser_scorers: Vec<AreaScorer> = reports.iter().map(|_| AreaScorer::new()).collect();
+    let mut interop_scorer = AreaScorer::new();
+
+    for row in aggregate_rows(reports) {
+        let name = row.iter().flatten().next().unwrap().name();
+        let interop = interop_counts(&row);
+        for (scorer, test) in browser_scorers.iter_mut().zip(&row) {
+            let counts = match test {
+                Some(test) => test.subtest_counts(),
+                None => SubtestCounts {
+                    pass: 0,
+                    total: interop.total,
+                },
+            };
+            scorer.add_test(name, counts);
+        }
+        interop_scorer.add_test(name, interop);
+    }
+
+    let browser_scores: Vec<_> = browser_scorers
+        .into_iter()
+        .map(AreaScorer::finish)
+        .collect();
+    let interop_scores = interop_scorer.finish();
+
+    let focus_areas = focus_areas.map(|areas| areas.to_vec()).unwrap_or_else(|| {
+        interop_scores
+            .keys()
+            .map(|area| FocusArea::from(area.as_str()))
+            .collect()
+    });
+    let focus_area_scores = |scores: &BTreeMap<String, AreaScores>, focus_area: &FocusArea| {
+        focus_area
+            .areas
+            .iter()
+            .map(|area| scores.get(area).cloned().unwrap_or_default())
+            .sum::<AreaScores>()
+    };
+    let interop_score = |scores: AreaScores| match scores.tests.total {
+        0 => 0,
+        _ => scores.interop_score(),
+    };
+
+    InteropSummaryReport {
+        browsers: reports
+            .iter()
+            .map(|report| report.run_info().product.clone())
+            .collect(),
+        focus_areas: focus_areas.iter().map(|a| a.name.to_string()).collect(),
+        scores: focus_areas
+            .iter()
+            .map(|focus_area| {
+                let interop = focus_area_scores(&interop_scores, focus_area);
+                InteropScores {
+                    total_tests: interop.tests.total,
+                    browser_scores: browser_scores
+                        .iter()
+                        .map(|scores| interop_score(focus_area_scores(scores, focus_area)))
+                        .collect(),
+                    interop_score: interop_score(interop),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// The subtests of a test which pass in every browser, out of the subtests that any browser
+/// ran. A test without subtests (in every browser) counts as a single subtest, and one whose
+/// subtests are only counted passes in every browser only if all of its subtests do.
+fn interop_counts<T: TestResultIter>(row: &[Option<T>]) -> SubtestCounts {
+    let passing: Vec<Option<HashSet<&str>>> = row
+        .iter()
+        .map(|test| {
+            let test = test.as_ref()?;
+            Some(
+                test.iter_subtests_results()
+                    .filter(|subtest| subtest.passes)
+                    .map(|subtest| subtest.name)
+                    .collect(),
+            )
+        })
+        .collect();
+    let all_subtests: HashSet<&str> = row
+        .iter()
+        .flatten()
+        .flat_map(|test| test.iter_subtests_results().map(|subtest| subtest.name))
+        .collect();
+
+    if all_subtests.is_empty() {
+        // Either the test has no subtests, or the reports only record subtest counts (as
+        // wpt.fyi summaries do) so subtests can't be matched up. Either way, the test only
+        // counts as passing if every subtest passes in every browser.
+        let counts: Vec<_> = row
+            .iter()
+            .flatten()
+            .map(|test| test.subtest_counts())
+            .collect();
+        let total = counts
+            .iter()
+            .map(|counts| counts.total)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let all_pass = counts.len() == row.len() && counts.iter().all(|c| c.all_passing());
+        return SubtestCounts {
+            pass: if all_pass { total } else { 0 },
+            total,
+        };
+    }
+
+    let pass = all_subtests
+        .iter()
+        .filter(|name| {
+            passing
+                .iter()
+                .all(|passing| passing.as_ref().is_some_and(|p| p.contains(*name)))
+        })
+        .count();
+    SubtestCounts {
+        pass: pass as u32,
+        total: all_subtests.len() as u32,
+    }
+}
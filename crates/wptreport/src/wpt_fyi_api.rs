@@ -0,0 +1,275 @@
+//! A client for the wpt.fyi API (<https://github.com/web-platform-tests/wpt.fyi/blob/main/api/README.md>)
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+use std::collections::BTreeMap;
+
+use crate::wpt_fyi_summary::WptFyiSummary;
+use crate::wpt_report::WptReport;
+use crate::{
+    score_wpt_report, AreaScores, Error, ErrorKind, ScorableReport, SubtestCounts,
+    SubtestNameAndResult, TestResultIter,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://wpt.fyi";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub browser_name: String,
+    pub browser_version: String,
+    pub created_at: String,
+    pub full_revision_hash: String,
+    pub id: u64,
+    pub labels: Vec<String>,
+    pub os_name: String,
+    pub os_version: String,
+    pub raw_results_url: String,
+    pub results_url: String,
+    pub revision: String,
+    pub time_end: String,
+    pub time_start: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchTestResults {
+    pub test: String,
+    /// One result for each run that was searched (in the same order as `SearchResults::runs`)
+    pub legacy_status: Vec<SearchTestResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchTestResult {
+    pub passes: u32,
+    pub total: u32,
+    pub status: SmolStr,
+    #[serde(rename = "newAggProcess")]
+    pub new_agg_process: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub runs: Vec<RunMetadata>,
+    pub results: Vec<SearchTestResults>,
+}
+
+impl SearchResults {
+    /// The results for the run at `index` in `runs`
+    pub fn run_results(&self, index: usize) -> RunSearchResults<'_> {
+        RunSearchResults {
+            results: self,
+            index,
+        }
+    }
+}
+
+/// The results of a single run within a set of [`SearchResults`]
+pub struct RunSearchResults<'a> {
+    results: &'a SearchResults,
+    index: usize,
+}
+
+#[rustfmt::skip]
+impl<'a> ScorableReport for RunSearchResults<'a> {
+    type TestResultIter<'b> = SearchTestResultIter<'b> where Self: 'b;
+    fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
+        self.results.results.iter().map(|results| SearchTestResultIter {
+            results,
+            index: self.index,
+        })
+    }
+}
+
+pub struct SearchTestResultIter<'a> {
+    results: &'a SearchTestResults,
+    index: usize,
+}
+
+/// Search results only record subtest counts, so individual subtests are always reported
+/// as missing.
+impl TestResultIter for SearchTestResultIter<'_> {
+    fn name(&self) -> &str {
+        &self.results.test
+    }
+
+    fn subtest_counts(&self) -> SubtestCounts {
+        let result = &self.results.legacy_status[self.index];
+        SubtestCounts {
+            pass: result.passes,
+            total: result.total,
+        }
+    }
+
+    fn subtest_exist_and_passes(&self, _name: &str) -> bool {
+        false
+    }
+
+    fn iter_subtests_results(&self) -> impl Iterator<Item = SubtestNameAndResult<'_>> {
+        std::iter::empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SummarisedResults {
+    pub metadata: RunMetadata,
+    pub scores: BTreeMap<String, AreaScores>,
+}
+
+/// Parameters for querying runs with [`WptFyiClient::get_runs`]
+#[derive(Debug, Clone, Default)]
+pub struct RunsQuery {
+    /// Only return runs for these products (e.g. "chrome", "firefox")
+    pub products: Vec<String>,
+    /// Only return runs with all of these labels (e.g. "experimental", "master")
+    pub labels: Vec<String>,
+    /// Only return runs for this WPT revision
+    pub sha: Option<String>,
+    /// Only return runs for revisions that were run for all of the requested products
+    pub aligned: bool,
+    /// The maximum number of runs to return for each product
+    pub max_count: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WptFyiClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl Default for WptFyiClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WptFyiClient {
+    /// Create a client for the public wpt.fyi instance
+    pub fn new() -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL)
+    }
+
+    /// Create a client for the wpt.fyi instance at `base_url` (e.g. a staging instance)
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        let mut base_url = base_url.into();
+        while base_url.ends_with('/') {
+            base_url.pop();
+        }
+        Self {
+            base_url,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The latest runs of the default products that ran against the same WPT revision
+    pub async fn get_latest_runs(&self) -> Result<Vec<RunMetadata>, Error> {
+        self.get_runs(&RunsQuery {
+            aligned: true,
+            ..RunsQuery::default()
+        })
+        .await
+    }
+
+    /// Runs matching a query (by product, label, revision, etc)
+    pub async fn get_runs(&self, query: &RunsQuery) -> Result<Vec<RunMetadata>, Error> {
+        let mut url = self.api_url("/api/runs")?;
+        {
+            let mut pairs = url.query_pairs_mut();
+            for product in &query.products {
+                pairs.append_pair("product", product);
+            }
+            for label in &query.labels {
+                pairs.append_pair("label", label);
+            }
+            if let Some(sha) = &query.sha {
+                pairs.append_pair("sha", sha);
+            }
+            if query.aligned {
+                pairs.append_pair("aligned", "true");
+            }
+            if let Some(max_count) = query.max_count {
+                pairs.append_pair("max-count", &max_count.to_string());
+            }
+        }
+        self.get_json(url).await
+    }
+
+    /// Per-test results for the given runs. `query` is an optional wpt.fyi search query
+    /// (e.g. a test path prefix such as "/css/css-grid/") which restricts the tests returned.
+    pub async fn search(
+        &self,
+        run_ids: &[u64],
+        query: Option<&str>,
+    ) -> Result<SearchResults, Error> {
+        let mut url = self.api_url("/api/search")?;
+        {
+            let run_ids: Vec<String> = run_ids.iter().map(|id| id.to_string()).collect();
+            let mut pairs = url.query_pairs_mut();
+            pairs.append_pair("run_ids", &run_ids.join(","));
+            if let Some(query) = query {
+                pairs.append_pair("q", query);
+            }
+        }
+
+        let mut results: SearchResults = self.get_json(url).await?;
+        results.results.sort_by(|a, b| a.test.cmp(&b.test));
+        Ok(results)
+    }
+
+    /// Per-area scores for each of the given runs
+    pub async fn get_run_data(&self, run_ids: &[u64]) -> Result<Vec<SummarisedResults>, Error> {
+        let results = self.search(run_ids, None).await?;
+
+        let summary = results
+            .runs
+            .iter()
+            .enumerate()
+            .map(|(index, metadata)| SummarisedResults {
+                metadata: metadata.clone(),
+                scores: score_wpt_report(&results.run_results(index)),
+            })
+            .collect();
+
+        Ok(summary)
+    }
+
+    /// Download the full wptreport for a run
+    pub async fn get_raw_results(&self, run: &RunMetadata) -> Result<WptReport, Error> {
+        self.get_json(parse_url(&run.raw_results_url)?).await
+    }
+
+    /// Download the `summary_v2` results file for a run
+    pub async fn get_summary(&self, run: &RunMetadata) -> Result<WptFyiSummary, Error> {
+        self.get_json(parse_url(&run.results_url)?).await
+    }
+
+    fn api_url(&self, path: &str) -> Result<Url, Error> {
+        parse_url(&format!("{}{path}", self.base_url))
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: Url) -> Result<T, Error> {
+        let body = async {
+            self.http
+                .get(url.clone())
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+        }
+        .await
+        .map_err(|err| Error::from(ErrorKind::Http(err)).with_url(url.as_str()))?;
+
+        crate::from_slice(&body).map_err(|err| err.with_url(url.as_str()))
+    }
+}
+
+fn parse_url(url: &str) -> Result<Url, Error> {
+    Url::parse(url).map_err(|err| {
+        let err = std::io::Error::new(std::io::ErrorKind::InvalidInput, err);
+        Error::from(err).with_url(url)
+    })
+}
@@ -0,0 +1,231 @@
+use wptreport::aggregate::{aggregate_rows, aggregate_subtests};
+use wptreport::any_report::AnyReport;
+use wptreport::wpt_fyi_summary::WptFyiSummary;
+use wptreport::wpt_report::SubtestStatus;
+use wptreport::TestResultIter;
+
+mod common;
+
+use common::{report, status_report, with_subtests, RUN_INFO};
+
+/// The rows of a join, as the name of each report's result (or `None`)
+fn row_names<T: TestResultIter>(row: &[Option<T>]) -> Vec<Option<&str>> {
+    row.iter()
+        .map(|result| result.as_ref().map(|result| result.name()))
+        .collect()
+}
+
+#[test]
+fn rows_are_joined_by_test_name_in_order() {
+    let reports = [
+        status_report(&[("/c.html", "PASS"), ("/a.html", "PASS")]),
+        status_report(&[("/b.html", "FAIL"), ("/a.html", "FAIL")]),
+        report(&[]),
+    ];
+    let rows: Vec<_> = aggregate_rows(&reports).collect();
+    let names: Vec<_> = rows.iter().map(|row| row_names(row)).collect();
+    assert_eq!(
+        names,
+        [
+            [Some("/a.html"), Some("/a.html"), None],
+            [None, Some("/b.html"), None],
+            [Some("/c.html"), None, None],
+        ]
+    );
+
+    // Each cell is that report's own result
+    assert_eq!(rows[0][0].unwrap().status.as_str(), "PASS");
+    assert_eq!(rows[0][1].unwrap().status.as_str(), "FAIL");
+
+    // The reports are not reordered
+    assert_eq!(reports[0].results[0].test, "/c.html");
+}
+
+#[test]
+fn any_number_of_reports() {
+    let reports: Vec<_> = (0..100)
+        .map(|i| {
+            status_report(&[
+                ("/all.html", "PASS"),
+                (&format!("/only-{i:03}.html"), "PASS"),
+            ])
+        })
+        .collect();
+    let rows: Vec<_> = aggregate_rows(&reports).collect();
+    assert_eq!(rows.len(), 101);
+    assert!(rows[0].iter().all(|result| result.is_some()));
+    for (i, row) in rows[1..].iter().enumerate() {
+        let present: Vec<_> = (0..100).filter(|&j| row[j].is_some()).collect();
+        assert_eq!(present, [i]);
+        assert_eq!(row[i].unwrap().test, format!("/only-{i:03}.html"));
+    }
+}
+
+#[test]
+fn rows_are_streamed() {
+    let reports = [
+        status_report(&[("/a.html", "PASS"), ("/b.html", "PASS")]),
+        status_report(&[("/b.html", "PASS")]),
+    ];
+    let mut rows = aggregate_rows(&reports);
+    assert_eq!(row_names(&rows.next().unwrap()), [Some("/a.html"), None]);
+    assert_eq!(
+        row_names(&rows.next().unwrap()),
+        [Some("/b.html"), Some("/b.html")]
+    );
+    assert!(rows.next().is_none());
+}
+
+#[test]
+fn rows_of_mixed_formats() {
+    let scores: AnyReport = format!(
+        r#"{{"run_info": {RUN_INFO}, "test_scores": {{
+            "/b.html": {{"score": 0, "subtests": {{}}}},
+            "/a.html": {{"score": 1, "subtests": {{}}}}
+        }}}}"#
+    )
+    .parse()
+    .unwrap();
+    let reports = [
+        AnyReport::from(status_report(&[("/a.html", "PASS")])),
+        scores,
+    ];
+    let rows: Vec<_> = aggregate_rows(&reports).collect();
+    let names: Vec<_> = rows.iter().map(|row| row_names(row)).collect();
+    assert_eq!(
+        names,
+        [[Some("/a.html"), Some("/a.html")], [None, Some("/b.html")]]
+    );
+    assert!(rows[0][0].as_ref().unwrap().as_test_result().is_some());
+    assert!(rows[0][1].as_ref().unwrap().as_test_result().is_none());
+}
+
+/// The rows of a subtest-level join as `(test, subtest, statuses)`
+fn subtest_rows<R: wptreport::ScorableReport>(
+    reports: &[R],
+) -> Vec<(String, Option<String>, Vec<Option<String>>)> {
+    aggregate_subtests(reports, |row| {
+        let statuses = row
+            .results
+            .iter()
+            .map(|status| status.as_ref().map(|status| status.to_string()))
+            .collect();
+        (
+            row.test.to_string(),
+            row.subtest.map(String::from),
+            statuses,
+        )
+    })
+}
+
+fn strings<const N: usize>(statuses: [Option<&str>; N]) -> Vec<Option<String>> {
+    statuses.iter().map(|s| s.map(String::from)).collect()
+}
+
+#[test]
+fn subtests_are_aligned_by_name() {
+    let reports = [
+        report(&[&with_subtests(
+            "/t.html",
+            "OK",
+            &[("c", "PASS"), ("a", "FAIL")],
+        )]),
+        report(&[&with_subtests(
+            "/t.html",
+            "OK",
+            &[("b", "TIMEOUT"), ("a", "PASS")],
+        )]),
+        report(&[&with_subtests("/t.html", "ERROR", &[])]),
+    ];
+    let rows = subtest_rows(&reports);
+    let t = String::from("/t.html");
+    assert_eq!(
+        rows,
+        [
+            (t.clone(), None, strings([None, None, Some("ERROR")])),
+            (
+                t.clone(),
+                Some(String::from("a")),
+                strings([Some("FAIL"), Some("PASS"), None])
+            ),
+            (
+                t.clone(),
+                Some(String::from("b")),
+                strings([None, Some("TIMEOUT"), None])
+            ),
+            (
+                t,
+                Some(String::from("c")),
+                strings([Some("PASS"), None, None])
+            ),
+        ]
+    );
+}
+
+#[test]
+fn tests_without_subtests_are_a_pseudo_subtest() {
+    let reports = [
+        AnyReport::from(status_report(&[("/a.html", "PASS"), ("/b.html", "CRASH")])),
+        format!(
+            r#"{{"run_info": {RUN_INFO}, "test_scores": {{
+                "/a.html": {{"score": 0, "subtests": {{}}}},
+                "/b.html": {{"score": 1, "subtests": {{"x": {{"score": 1}}}}}}
+            }}}}"#
+        )
+        .parse()
+        .unwrap(),
+    ];
+    let rows = subtest_rows(&reports);
+    assert_eq!(
+        rows,
+        [
+            (
+                String::from("/a.html"),
+                None,
+                strings([Some("PASS"), Some("FAIL")])
+            ),
+            (
+                String::from("/b.html"),
+                None,
+                strings([Some("CRASH"), None])
+            ),
+            (
+                String::from("/b.html"),
+                Some(String::from("x")),
+                strings([None, Some("PASS")])
+            ),
+        ]
+    );
+}
+
+#[test]
+fn count_only_tests_pass_only_if_every_subtest_passes() {
+    let summary: WptFyiSummary = r#"{
+        "/partial.html": {"s": [3, 4], "c": "O"},
+        "/all.html": {"s": [4, 4], "c": "O"}
+    }"#
+    .parse()
+    .unwrap();
+    let rows = aggregate_subtests(&[summary], |row| {
+        (
+            row.test.to_string(),
+            row.subtest.is_none(),
+            row.results.clone(),
+        )
+    });
+    assert_eq!(
+        rows,
+        [
+            (
+                String::from("/all.html"),
+                true,
+                vec![Some(SubtestStatus::Pass)]
+            ),
+            (
+                String::from("/partial.html"),
+                true,
+                vec![Some(SubtestStatus::Fail)]
+            ),
+        ]
+    );
+}
@@ -0,0 +1,63 @@
+use wptreport::any_report::AnyReport;
+use wptreport::wpt_report::TestStatus;
+use wptreport::{HasRunInfo, ScorableReport, SubtestCounts, TestResultIter};
+
+mod common;
+
+use common::RUN_INFO;
+
+fn format(json: &str) -> &'static str {
+    match json.parse::<AnyReport>().unwrap() {
+        AnyReport::WptReport(_) => "wptreport",
+        AnyReport::WptScores(_) => "scores",
+        AnyReport::WptFyiSummary(_) => "summary",
+    }
+}
+
+#[test]
+fn formats_are_detected_from_their_keys() {
+    // Keys that only appear inside run_info don't count
+    let run_info = RUN_INFO.replace('}', r#", "results": 1, "test_scores": 2}"#);
+    assert_eq!(
+        format(&format!(
+            r#"{{"run_info": {run_info}, "results": [], "time_start": 1, "time_end": 2}}"#
+        )),
+        "wptreport"
+    );
+    assert_eq!(
+        format(&format!(
+            r#"{{"run_info": {run_info}, "test_scores": {{}}}}"#
+        )),
+        "scores"
+    );
+    assert_eq!(
+        format(r#"{"/css/a.html": {"s": [1, 2], "c": "O"}}"#),
+        "summary"
+    );
+}
+
+#[test]
+fn undetected_formats_are_parsed_as_wptreports() {
+    // Without a key that tells the formats apart, the error is the wptreport one
+    let json = format!(r#"{{"run_info": {RUN_INFO}}}"#);
+    let err = json.parse::<AnyReport>().unwrap_err();
+    assert!(err.to_string().contains("missing field"), "{err}");
+    assert!("[]".parse::<AnyReport>().is_err());
+}
+
+#[test]
+fn summaries_keep_their_statuses() {
+    let summary: AnyReport = r#"{
+        "/css/a.html": {"s": [1, 2], "c": "O"},
+        "/css/b.html": {"s": [0, 1], "c": "T"}
+    }"#
+    .parse()
+    .unwrap();
+    assert!(summary.is_keyed());
+    let b = summary.get("/css/b.html").unwrap();
+    assert_eq!(b.status(), Some(&TestStatus::from("TIMEOUT")));
+    assert_eq!(b.subtest_counts(), SubtestCounts { pass: 0, total: 1 });
+    assert!(b.as_test_result().is_none());
+    assert_eq!(summary.results().count(), 2);
+    assert_eq!(summary.run_info().product, "");
+}
@@ -0,0 +1,86 @@
+use wptreport::aggregate::{diff, DiffStatus};
+use wptreport::score::{area_deltas, sort_by_impact, AreaDelta};
+use wptreport::score_wpt_report;
+
+mod common;
+
+use common::report;
+
+const HALF_PASSING: &str = r#"{"test": "/css/a.html", "status": "OK", "duration": 1, "subtests": [
+    {"name": "x", "status": "PASS"}, {"name": "y", "status": "FAIL"}
+]}"#;
+const ALL_PASSING: &str = r#"{"test": "/css/a.html", "status": "OK", "duration": 1, "subtests": [
+    {"name": "x", "status": "PASS"}, {"name": "y", "status": "PASS"}
+]}"#;
+
+fn deltas() -> Vec<AreaDelta> {
+    let before = report(&[
+        HALF_PASSING,
+        r#"{"test": "/css/b.html", "status": "PASS", "duration": 1}"#,
+        r#"{"test": "/css/c.html", "status": "PASS", "duration": 1}"#,
+        r#"{"test": "/dom/d.html", "status": "FAIL", "duration": 1}"#,
+    ]);
+    let after = report(&[
+        ALL_PASSING,
+        r#"{"test": "/css/b.html", "status": "FAIL", "duration": 1}"#,
+        r#"{"test": "/css/c.html", "status": "PASS", "duration": 1}"#,
+        r#"{"test": "/dom/d.html", "status": "TIMEOUT", "duration": 1}"#,
+        r#"{"test": "/html/new.html", "status": "PASS", "duration": 1}"#,
+    ]);
+    area_deltas(
+        &score_wpt_report(&before),
+        &score_wpt_report(&after),
+        &diff(&before, &after),
+        1,
+    )
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn deltas_for_each_area() {
+    let deltas = deltas();
+    let areas: Vec<_> = deltas.iter().map(|delta| delta.area.as_str()).collect();
+    assert_eq!(areas, ["", "/css", "/dom", "/html"]);
+
+    // /css/a.html gained a passing subtest, /css/b.html lost its only one
+    let css = &deltas[1];
+    assert_eq!((css.tests_passing, css.subtests_passing), (0, 0));
+    assert!(approx_eq(css.servo_score, -0.5));
+    assert!(approx_eq(css.servo_score_fraction, -0.5 / 3.0));
+    assert_eq!(css.interop_score, 666 - 833);
+    assert_eq!((css.before.tests.total, css.after.tests.total), (3, 3));
+
+    // Only the biggest change is kept
+    assert_eq!(css.top_tests.len(), 1);
+    let top = &css.top_tests[0];
+    assert_eq!(top.test, "/css/b.html");
+    assert_eq!((top.tests_passing, top.subtests_passing), (-1, -1));
+    assert!(approx_eq(top.servo_score, -1.0));
+
+    // A status change that doesn't change any scores isn't a contributing test
+    let dom = &deltas[2];
+    assert!(approx_eq(dom.servo_score, 0.0));
+    assert!(dom.top_tests.is_empty());
+
+    // An added area is compared against empty scores
+    let html = &deltas[3];
+    assert_eq!(html.before.tests.total, 0);
+    assert_eq!((html.tests_passing, html.interop_score), (1, 1000));
+    assert_eq!(html.top_tests[0].status, DiffStatus::Added);
+
+    let root = &deltas[0];
+    assert_eq!((root.tests_passing, root.subtests_passing), (1, 1));
+    assert!(approx_eq(root.servo_score, 0.5));
+}
+
+#[test]
+fn deltas_sort_by_impact() {
+    let mut deltas = deltas();
+    sort_by_impact(&mut deltas);
+    let areas: Vec<_> = deltas.iter().map(|delta| delta.area.as_str()).collect();
+    // Ties are broken by area name
+    assert_eq!(areas, ["/html", "", "/css", "/dom"]);
+}
@@ -0,0 +1,100 @@
+use wptreport::bsf::{browser_specific_failures, BsfReference};
+use wptreport::wpt_fyi_summary::WptFyiSummary;
+use wptreport::wpt_report::WptReport;
+
+mod common;
+
+use common::{report, with_subtests};
+
+fn ours() -> WptReport {
+    report(&[
+        &with_subtests(
+            "/css/a.html",
+            "OK",
+            &[("x", "FAIL"), ("y", "FAIL"), ("z", "PASS")],
+        ),
+        r#"{"test": "/css/b.html", "status": "FAIL"}"#,
+        r#"{"test": "/dom/c.html", "status": "FAIL"}"#,
+        r#"{"test": "/dom/only-ours.html", "status": "FAIL"}"#,
+    ])
+}
+
+#[test]
+fn failures_only_in_this_browser() {
+    let chrome = report(&[
+        &with_subtests(
+            "/css/a.html",
+            "OK",
+            &[("x", "PASS"), ("y", "PASS"), ("z", "PASS")],
+        ),
+        r#"{"test": "/css/b.html", "status": "PASS"}"#,
+        r#"{"test": "/dom/c.html", "status": "PASS"}"#,
+    ]);
+    let firefox = report(&[
+        &with_subtests("/css/a.html", "OK", &[("x", "PASS"), ("y", "FAIL")]),
+        r#"{"test": "/css/b.html", "status": "PASS"}"#,
+        r#"{"test": "/dom/c.html", "status": "FAIL"}"#,
+    ]);
+    let references = [BsfReference::new(&chrome), BsfReference::new(&firefox)];
+    let summary = browser_specific_failures(&ours(), &references);
+
+    // Only x fails in just this browser: y also fails in Firefox, and z passes
+    let tests: Vec<_> = summary
+        .tests
+        .iter()
+        .map(|test| (test.test.as_str(), test.score, test.subtests.clone()))
+        .collect();
+    assert_eq!(
+        tests,
+        [
+            ("/css/a.html", 1.0 / 3.0, vec![String::from("x")]),
+            ("/css/b.html", 1.0, vec![]),
+        ]
+    );
+
+    // Tests that aren't in every report aren't scored
+    let css = summary.areas["/css"];
+    assert_eq!(css.tests, 2);
+    assert!((css.score - 4.0 / 3.0).abs() < 1e-9);
+    let dom = summary.areas["/dom"];
+    assert_eq!((dom.tests, dom.score), (1, 0.0));
+    assert_eq!(summary.areas[""].tests, 3);
+}
+
+#[test]
+fn wpt_fyi_summary_references() {
+    let chrome: WptFyiSummary = r#"{
+        "/css/a.html": {"s": [3, 3], "c": "O"},
+        "/css/b.html": {"s": [1, 1], "c": "P"},
+        "/dom/c.html": {"s": [1, 1], "c": "P"}
+    }"#
+    .parse()
+    .unwrap();
+    let firefox: WptFyiSummary = r#"{
+        "/css/a.html": {"s": [3, 3], "c": "O"},
+        "/css/b.html": {"s": [1, 1], "c": "P"},
+        "/dom/c.html": {"s": [0, 1], "c": "F"}
+    }"#
+    .parse()
+    .unwrap();
+    let references = [BsfReference::new(&chrome), BsfReference::new(&firefox)];
+    let summary = browser_specific_failures(&ours(), &references);
+
+    // Without subtest names, a test that fully passes in every other browser scores the
+    // fraction of its subtests that fail in this one
+    let tests: Vec<_> = summary
+        .tests
+        .iter()
+        .map(|test| (test.test.as_str(), test.subtests.is_empty()))
+        .collect();
+    assert_eq!(tests, [("/css/a.html", true), ("/css/b.html", true)]);
+    assert!((summary.tests[0].score - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(summary.tests[1].score, 1.0);
+    assert_eq!(summary.areas["/dom"].score, 0.0);
+
+    // A reference that doesn't fully pass the test means it isn't browser-specific
+    let partial: WptFyiSummary = r#"{"/css/a.html": {"s": [2, 3], "c": "O"}}"#.parse().unwrap();
+    let summary = browser_specific_failures(&ours(), &[BsfReference::new(&partial)]);
+    assert_eq!(summary.areas["/css"].tests, 1);
+    assert!(summary.tests.is_empty());
+}
@@ -0,0 +1,64 @@
+//! Reports shared by the integration tests
+// Each test crate only uses some of these
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use wptreport::wpt_report::WptReport;
+
+/// The `run_info` of every report and scores file, unless another product is asked for
+pub const RUN_INFO: &str =
+    r#"{"product": "servo", "revision": "abc", "os": "linux", "debug": false}"#;
+
+/// [`RUN_INFO`] for `product`
+pub fn run_info(product: &str) -> Value {
+    let mut run_info: Value = serde_json::from_str(RUN_INFO).unwrap();
+    run_info["product"] = json!(product);
+    run_info
+}
+
+/// A wptreport for `product` with the given results (as JSON objects). Results without a
+/// `duration` are given one.
+pub fn report_for(product: &str, results: &[&str]) -> WptReport {
+    let results: Vec<Value> = results
+        .iter()
+        .map(|result| {
+            let mut result: Value = serde_json::from_str(result).unwrap();
+            let fields = result.as_object_mut().expect("results are objects");
+            fields.entry("duration").or_insert(json!(1));
+            result
+        })
+        .collect();
+    json!({
+        "time_start": 1,
+        "time_end": 2,
+        "run_info": run_info(product),
+        "results": results,
+    })
+    .to_string()
+    .parse()
+    .unwrap()
+}
+
+/// A servo wptreport with the given results
+pub fn report(results: &[&str]) -> WptReport {
+    report_for("servo", results)
+}
+
+/// A servo wptreport with a result for each `(test, status)` pair, in the given order
+pub fn status_report(tests: &[(&str, &str)]) -> WptReport {
+    let results: Vec<String> = tests
+        .iter()
+        .map(|(test, status)| json!({"test": test, "status": status}).to_string())
+        .collect();
+    let results: Vec<&str> = results.iter().map(String::as_str).collect();
+    report(&results)
+}
+
+/// A result for `test` with the given status and `(subtest, status)` pairs
+pub fn with_subtests(test: &str, status: &str, subtests: &[(&str, &str)]) -> String {
+    let subtests: Vec<Value> = subtests
+        .iter()
+        .map(|(name, status)| json!({"name": name, "status": status}))
+        .collect();
+    json!({"test": test, "status": status, "subtests": subtests}).to_string()
+}
@@ -0,0 +1,62 @@
+use wptreport::csv::{write_area_rows, write_test_rows, Delimiter};
+use wptreport::score_wpt_report;
+use wptreport::wpt_report::WptReport;
+
+const REPORT: &str = r#"{
+    "time_start": 1,
+    "time_end": 2,
+    "run_info": {"product": "servo", "revision": "abc", "os": "linux"},
+    "results": [
+        {"test": "/dom/a,b.html", "status": "OK", "duration": 15, "subsuite": "say \"hi\"", "subtests": [
+            {"name": "x", "status": "PASS"},
+            {"name": "y", "status": "FAIL"},
+            {"name": "z", "status": "PASS"}
+        ]},
+        {"test": "/css/tab\there.html", "status": "PASS", "duration": 3}
+    ]
+}"#;
+
+fn rows(delimiter: Delimiter, areas: bool) -> String {
+    let report: WptReport = REPORT.parse().unwrap();
+    let mut out = Vec::new();
+    match areas {
+        true => write_area_rows(&score_wpt_report(&report), delimiter, &mut out).unwrap(),
+        false => write_test_rows(&report, delimiter, &mut out).unwrap(),
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_rows_quote_fields_containing_the_delimiter() {
+    assert_eq!(
+        rows(Delimiter::Comma, false),
+        concat!(
+            "test,status,subtests_passed,subtests_total,duration,subsuite\n",
+            "\"/dom/a,b.html\",OK,2,3,15,\"say \"\"hi\"\"\"\n",
+            "/css/tab\there.html,PASS,1,1,3,\n",
+        )
+    );
+    assert_eq!(
+        rows(Delimiter::Tab, false),
+        concat!(
+            "test\tstatus\tsubtests_passed\tsubtests_total\tduration\tsubsuite\n",
+            "/dom/a,b.html\tOK\t2\t3\t15\t\"say \"\"hi\"\"\"\n",
+            "\"/css/tab\there.html\"\tPASS\t1\t1\t3\t\n",
+        )
+    );
+}
+
+#[test]
+fn area_rows() {
+    let csv = rows(Delimiter::Comma, true);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("area,tests_passed,tests_total,subtests_passed,subtests_total,servo_score,interop_score")
+    );
+    // The root area is written as "/"
+    assert_eq!(lines.next(), Some("/,1,2,3,4,1.6666666666666665,833"));
+    assert_eq!(lines.next(), Some("/css,1,1,1,1,1,1000"));
+    assert_eq!(lines.next(), Some("/dom,0,1,2,3,0.6666666666666666,666"));
+    assert_eq!(lines.next(), None);
+}
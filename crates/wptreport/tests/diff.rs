@@ -0,0 +1,219 @@
+use serde_json::json;
+use wptreport::aggregate::{diff, DiffKind, DiffStatus};
+use wptreport::servo_test_scores::WptScores;
+use wptreport::wpt_report::{SubtestStatus, TestStatus};
+use wptreport::SubtestCounts;
+
+mod common;
+
+use common::{report, RUN_INFO};
+
+#[test]
+fn diff_classifies_tests() {
+    let before = report(&[
+        r#"{"test": "/same.html", "status": "PASS"}"#,
+        r#"{"test": "/removed.html", "status": "PASS"}"#,
+        r#"{"test": "/regressed.html", "status": "OK", "subtests": [
+            {"name": "a", "status": "PASS"},
+            {"name": "b", "status": "PASS"},
+            {"name": "gone", "status": "FAIL"}
+        ]}"#,
+        r#"{"test": "/improved.html", "status": "FAIL"}"#,
+        r#"{"test": "/status-only.html", "status": "FAIL"}"#,
+    ]);
+    let after = report(&[
+        r#"{"test": "/added.html", "status": "FAIL"}"#,
+        r#"{"test": "/same.html", "status": "PASS"}"#,
+        r#"{"test": "/regressed.html", "status": "OK", "subtests": [
+            {"name": "a", "status": "PASS"},
+            {"name": "b", "status": "TIMEOUT"},
+            {"name": "new", "status": "FAIL"}
+        ]}"#,
+        r#"{"test": "/improved.html", "status": "PASS"}"#,
+        r#"{"test": "/status-only.html", "status": "TIMEOUT"}"#,
+    ]);
+
+    let diffs = diff(&before, &after);
+    let summary: Vec<_> = diffs
+        .iter()
+        .map(|diff| (diff.test.as_str(), diff.status, diff.kind))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("/added.html", DiffStatus::Added, DiffKind::Neutral),
+            ("/improved.html", DiffStatus::Changed, DiffKind::Improvement),
+            ("/regressed.html", DiffStatus::Changed, DiffKind::Regression),
+            ("/removed.html", DiffStatus::Removed, DiffKind::Neutral),
+            ("/same.html", DiffStatus::Same, DiffKind::Neutral),
+            ("/status-only.html", DiffStatus::Changed, DiffKind::Neutral),
+        ]
+    );
+
+    let added = &diffs[0];
+    assert_eq!(
+        (&added.before, &added.after),
+        (&None, &Some(TestStatus::Fail))
+    );
+    assert_eq!(added.before_counts, None);
+    assert_eq!(
+        added.after_counts,
+        Some(SubtestCounts { pass: 0, total: 1 })
+    );
+
+    let regressed = &diffs[2];
+    assert_eq!(
+        regressed.before_counts,
+        Some(SubtestCounts { pass: 2, total: 3 })
+    );
+    assert_eq!(
+        regressed.after_counts,
+        Some(SubtestCounts { pass: 1, total: 3 })
+    );
+    let subtests: Vec<_> = regressed
+        .subtests
+        .iter()
+        .map(|subtest| {
+            (
+                subtest.name.as_str(),
+                subtest.status,
+                subtest.before.clone(),
+                subtest.after.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        subtests,
+        [
+            (
+                "b",
+                DiffStatus::Changed,
+                Some(SubtestStatus::Pass),
+                Some(SubtestStatus::Timeout)
+            ),
+            ("gone", DiffStatus::Removed, Some(SubtestStatus::Fail), None),
+            ("new", DiffStatus::Added, None, Some(SubtestStatus::Fail)),
+        ]
+    );
+
+    let status_only = &diffs[5];
+    assert_eq!(status_only.after, Some(TestStatus::Timeout));
+    assert!(status_only.subtests.is_empty());
+}
+
+#[test]
+fn diff_serializes() {
+    let before = report(&[r#"{"test": "/t.html", "status": "OK", "subtests": [
+        {"name": "a", "status": "PASS"}
+    ]}"#]);
+    let after = report(&[r#"{"test": "/t.html", "status": "OK", "subtests": [
+        {"name": "a", "status": "FAIL"}
+    ]}"#]);
+    let diffs = diff(&before, &after);
+    assert_eq!(
+        serde_json::to_value(&diffs).unwrap(),
+        json!([{
+            "test": "/t.html",
+            "status": "changed",
+            "kind": "regression",
+            "before": "OK",
+            "after": "OK",
+            "before_counts": {"pass": 1, "total": 1},
+            "after_counts": {"pass": 0, "total": 1},
+            "subtests": [
+                {"name": "a", "status": "changed", "before": "PASS", "after": "FAIL"}
+            ]
+        }])
+    );
+}
+
+#[test]
+fn diff_mixed_formats_degrades_to_pass_fail() {
+    let before = report(&[
+        r#"{"test": "/t.html", "status": "OK", "subtests": [
+            {"name": "timeout", "status": "TIMEOUT"},
+            {"name": "fixed", "status": "FAIL"},
+            {"name": "broken", "status": "PASS"}
+        ]}"#,
+        r#"{"test": "/crash.html", "status": "CRASH"}"#,
+    ]);
+    let after: WptScores = format!(
+        r#"{{"run_info": {RUN_INFO}, "test_scores": {{
+            "/t.html": {{"score": 1, "subtests": {{
+                "timeout": {{"score": 0}},
+                "fixed": {{"score": 1}},
+                "broken": {{"score": 0}}
+            }}}},
+            "/crash.html": {{"score": 0, "subtests": {{}}}}
+        }}}}"#
+    )
+    .parse()
+    .unwrap();
+
+    let diffs = diff(&before, &after);
+    assert_eq!(diffs.len(), 2);
+
+    // A crash and a score of 0 are both just failures
+    let crash = &diffs[0];
+    assert_eq!(crash.status, DiffStatus::Same);
+    assert_eq!(crash.before, Some(TestStatus::Fail));
+
+    // A timeout and a score of 0 are both failures, so only two subtests changed
+    let t = &diffs[1];
+    assert_eq!((t.status, t.kind), (DiffStatus::Changed, DiffKind::Neutral));
+    assert_eq!(
+        (&t.before, &t.after),
+        (&Some(TestStatus::Fail), &Some(TestStatus::Fail))
+    );
+    let subtests: Vec<_> = t
+        .subtests
+        .iter()
+        .map(|subtest| {
+            (
+                subtest.name.as_str(),
+                subtest.before.clone(),
+                subtest.after.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        subtests,
+        [
+            (
+                "fixed",
+                Some(SubtestStatus::Fail),
+                Some(SubtestStatus::Pass)
+            ),
+            (
+                "broken",
+                Some(SubtestStatus::Pass),
+                Some(SubtestStatus::Fail)
+            ),
+        ]
+    );
+
+    // The other way around gives the opposite changes
+    let reversed = diff(&after, &before);
+    assert_eq!(reversed[1].subtests[0].before, Some(SubtestStatus::Pass));
+    assert_eq!(reversed[1].subtests[0].after, Some(SubtestStatus::Fail));
+}
+
+#[test]
+fn diff_scores_files() {
+    let scores = |score: u32| -> WptScores {
+        format!(
+            r#"{{"run_info": {RUN_INFO}, "test_scores": {{
+                "/t.html": {{"score": 1, "subtests": {{"a": {{"score": {score}}}}}}}
+            }}}}"#
+        )
+        .parse()
+        .unwrap()
+    };
+    let diffs = diff(&scores(0), &scores(1));
+    assert_eq!(diffs[0].kind, DiffKind::Improvement);
+    assert_eq!(diffs[0].before, Some(TestStatus::Fail));
+    assert_eq!(diffs[0].after, Some(TestStatus::Pass));
+    assert_eq!(diffs[0].subtests[0].after, Some(SubtestStatus::Pass));
+
+    assert_eq!(diff(&scores(1), &scores(1))[0].status, DiffStatus::Same);
+}
@@ -0,0 +1,315 @@
+use std::fs;
+use std::path::PathBuf;
+
+use wptreport::expectations::{
+    run_info_properties, ExpectationFile, Expr, Metadata, UnexpectedResult, Value,
+};
+use wptreport::wpt_report::WptReport;
+
+const EXPECTATIONS: &str = r#"
+# A comment which isn't preserved
+[test.html]
+  expected: TIMEOUT
+  [subtest with \] bracket]
+    expected:
+      if (os == "linux") and debug: [FAIL, PASS]
+      if os == 'mac': "FAIL"
+      FAIL  # trailing comment
+
+  [passing subtest]
+    bug: https://example.com/#123
+"#;
+
+/// A report for a run on `os` with a single test result (given as JSON)
+fn report(os: &str, debug: bool, result: &str) -> WptReport {
+    format!(
+        r#"{{
+          "time_start": 1,
+          "time_end": 2,
+          "run_info": {{"product": "servo", "revision": "abc", "os": "{os}", "debug": {debug}}},
+          "results": [{result}]
+        }}"#
+    )
+    .parse()
+    .unwrap()
+}
+
+fn test_result(status: &str, subtests: &[(&str, &str)]) -> String {
+    let subtests: Vec<String> = subtests
+        .iter()
+        .map(|(name, status)| format!(r#"{{"name": "{name}", "status": "{status}"}}"#))
+        .collect();
+    format!(
+        r#"{{"test": "/dir/test.html", "status": "{status}", "duration": 1, "subtests": [{}]}}"#,
+        subtests.join(", ")
+    )
+}
+
+/// An empty metadata directory that is unique to the calling test
+fn metadata_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "wptreport-expectations-{}-{name}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("dir")).unwrap();
+    dir
+}
+
+#[test]
+fn parse_and_write_expectations() {
+    let file: ExpectationFile = EXPECTATIONS.parse().unwrap();
+    let test = file.section("test.html").unwrap();
+    assert_eq!(
+        test.key("expected").unwrap().values[0].value,
+        Value::Atom(String::from("TIMEOUT"))
+    );
+
+    let subtest = test.section("subtest with ] bracket").unwrap();
+    let values = &subtest.key("expected").unwrap().values;
+    assert_eq!(values.len(), 3);
+    assert_eq!(
+        values[0].condition,
+        Some(Expr::parse(r#"(os == "linux") and debug"#).unwrap())
+    );
+    assert_eq!(
+        values[0].value,
+        Value::List(vec![String::from("FAIL"), String::from("PASS")])
+    );
+    assert_eq!(values[1].value, Value::Atom(String::from("FAIL")));
+    assert_eq!(values[2].condition, None);
+
+    let bug = test.section("passing subtest").unwrap().key("bug").unwrap();
+    assert_eq!(
+        bug.values[0].value,
+        Value::Atom(String::from("https://example.com/#123"))
+    );
+
+    // Writing normalizes quotes and whitespace and drops comments, and is otherwise lossless
+    let written = file.to_string();
+    assert_eq!(
+        written,
+        r#"[test.html]
+  expected: TIMEOUT
+  [subtest with \] bracket]
+    expected:
+      if (os == "linux") and debug: [FAIL, PASS]
+      if os == "mac": FAIL
+      FAIL
+
+  [passing subtest]
+    bug: https://example.com/#123
+
+"#
+    );
+    assert_eq!(written.parse::<ExpectationFile>().unwrap(), file);
+}
+
+#[test]
+fn parse_errors_report_the_line() {
+    for (ini, line) in [
+        ("[test.html\n", 1),
+        ("[test.html]\n  expected: FAIL\n expected: PASS\n", 3),
+        ("[test.html]\n  expected:\n    if os ==: FAIL\n", 3),
+        ("[test.html]\n  expected:\n", 2),
+    ] {
+        let err = ini.parse::<ExpectationFile>().unwrap_err();
+        assert!(
+            err.to_string().starts_with(&format!("line {line}:")),
+            "{ini:?}: {err}"
+        );
+    }
+}
+
+#[test]
+fn conditions_evaluate_against_run_info() {
+    let linux_debug = run_info_properties(&report("linux", true, "").run_info);
+    let mac = run_info_properties(&report("mac", false, "").run_info);
+
+    for (condition, on_linux_debug, on_mac) in [
+        (r#"os == "linux""#, true, false),
+        (r#"os != "linux""#, false, true),
+        ("debug", true, false),
+        ("not debug", false, true),
+        (r#"(os == "linux") and not debug"#, false, false),
+        (r#"(os == "mac") or debug"#, true, true),
+        (r#"not (os == "mac" or debug)"#, false, false),
+        // Unknown properties are null, which isn't equal to anything but itself
+        (r#"unknown == "x""#, false, false),
+        ("not unknown", true, true),
+        (r#"product == "servo" and debug == true"#, true, false),
+    ] {
+        let expr = Expr::parse(condition).unwrap();
+        assert_eq!(expr.matches(&linux_debug), on_linux_debug, "{condition}");
+        assert_eq!(expr.matches(&mac), on_mac, "{condition}");
+    }
+
+    // The first matching value applies
+    let file: ExpectationFile = EXPECTATIONS.parse().unwrap();
+    let subtest = file
+        .section("test.html")
+        .unwrap()
+        .section("subtest with ] bracket")
+        .unwrap();
+    let linux = run_info_properties(&report("linux", false, "").run_info);
+    assert_eq!(
+        subtest.get("expected", &linux_debug).unwrap().as_slice(),
+        ["FAIL", "PASS"]
+    );
+    assert_eq!(subtest.get("expected", &mac).unwrap().as_slice(), ["FAIL"]);
+    assert_eq!(
+        subtest.get("expected", &linux).unwrap().as_slice(),
+        ["FAIL"]
+    );
+}
+
+#[test]
+fn unexpected_results_skip_disabled_tests() {
+    let dir = metadata_dir("disabled");
+    fs::write(
+        dir.join("dir/test.html.ini"),
+        "[test.html]\n  disabled:\n    if os == \"mac\": https://bugs.example/1\n",
+    )
+    .unwrap();
+    let result = test_result("OK", &[("a", "FAIL")]);
+
+    let mut metadata = Metadata::new(&dir, None);
+    let on_mac = metadata
+        .unexpected_results(&report("mac", false, &result))
+        .unwrap();
+    assert_eq!(on_mac, []);
+
+    let on_linux = metadata
+        .unexpected_results(&report("linux", false, &result))
+        .unwrap();
+    assert_eq!(
+        on_linux,
+        [UnexpectedResult {
+            test: String::from("/dir/test.html"),
+            subtest: Some(String::from("a")),
+            status: String::from("FAIL"),
+            expected: vec![String::from("PASS")],
+        }]
+    );
+}
+
+#[test]
+fn update_leaves_correct_files_unchanged() {
+    let dir = metadata_dir("unchanged");
+    let path = dir.join("dir/test.html.ini");
+    let ini = "[test.html]\n  expected: TIMEOUT\n  [a]\n    expected:\n      if os == \"mac\": FAIL\n      [PASS, FAIL]\n\n";
+    fs::write(&path, ini).unwrap();
+
+    for os in ["linux", "mac"] {
+        let report = report(
+            os,
+            false,
+            &test_result("TIMEOUT", &[("a", "FAIL"), ("b", "PASS")]),
+        );
+        let mut metadata = Metadata::new(&dir, None);
+        assert_eq!(metadata.unexpected_results(&report).unwrap(), []);
+        metadata.update(&report).unwrap();
+        assert_eq!(metadata.write().unwrap(), Vec::<PathBuf>::new());
+        assert_eq!(fs::read_to_string(&path).unwrap(), ini);
+    }
+}
+
+#[test]
+fn update_adds_a_condition_for_the_run() {
+    let dir = metadata_dir("update");
+    let path = dir.join("dir/test.html.ini");
+    // The condition is shared by debug and non-debug runs
+    fs::write(
+        &path,
+        "[test.html]\n  [a]\n    expected:\n      if os == \"linux\": FAIL\n",
+    )
+    .unwrap();
+    let linux_debug = report("linux", true, &test_result("OK", &[("a", "TIMEOUT")]));
+
+    let mut metadata = Metadata::new(&dir, None);
+    metadata.update(&linux_debug).unwrap();
+    assert_eq!(metadata.write().unwrap(), [path.as_path()]);
+    let updated = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        updated,
+        "[test.html]\n  [a]\n    expected:\n      if (os == \"linux\") and debug: TIMEOUT\n      if os == \"linux\": FAIL\n\n"
+    );
+
+    // Other configurations that shared the condition still expect the old status
+    let linux = report("linux", false, &test_result("OK", &[("a", "FAIL")]));
+    let mut metadata = Metadata::new(&dir, None);
+    assert_eq!(metadata.unexpected_results(&linux).unwrap(), []);
+    assert_eq!(metadata.unexpected_results(&linux_debug).unwrap(), []);
+
+    // Updating again with the same results changes nothing
+    metadata.update(&linux_debug).unwrap();
+    metadata.update(&linux).unwrap();
+    assert_eq!(metadata.write().unwrap(), Vec::<PathBuf>::new());
+    assert_eq!(fs::read_to_string(&path).unwrap(), updated);
+}
+
+#[test]
+fn directory_metadata_disables_tests() {
+    let dir = metadata_dir("dir-disabled");
+    fs::write(
+        dir.join("__dir__.ini"),
+        "disabled: https://bugs.example/2\n",
+    )
+    .unwrap();
+    // Re-enabled on linux for the directory the test is in
+    fs::write(
+        dir.join("dir/__dir__.ini"),
+        "disabled:\n  if os == \"linux\": @False\n",
+    )
+    .unwrap();
+    let result = test_result("OK", &[("a", "FAIL")]);
+
+    let mut metadata = Metadata::new(&dir, None);
+    let on_mac = metadata
+        .unexpected_results(&report("mac", false, &result))
+        .unwrap();
+    assert_eq!(on_mac, []);
+    let on_linux = metadata
+        .unexpected_results(&report("linux", false, &result))
+        .unwrap();
+    assert_eq!(on_linux.len(), 1);
+
+    // Nothing is written for directory metadata that was only read
+    assert_eq!(metadata.write().unwrap(), Vec::<PathBuf>::new());
+}
+
+#[test]
+fn update_keeps_the_default_for_other_configurations() {
+    let dir = metadata_dir("default");
+    let path = dir.join("dir/test.html.ini");
+    fs::write(&path, "[test.html]\n  [a]\n    expected: FAIL\n  [b]\n    expected:\n      if os == \"mac\": FAIL\n").unwrap();
+    let linux = report(
+        "linux",
+        false,
+        &test_result("OK", &[("a", "TIMEOUT"), ("b", "TIMEOUT")]),
+    );
+
+    let mut metadata = Metadata::new(&dir, None);
+    metadata.update(&linux).unwrap();
+    assert_eq!(metadata.write().unwrap(), [path.as_path()]);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "[test.html]\n  [a]\n    expected:\n      if (os == \"linux\") and not debug: TIMEOUT\n      FAIL\n\n  [b]\n    expected:\n      if os == \"mac\": FAIL\n      if (os == \"linux\") and not debug: TIMEOUT\n\n"
+    );
+
+    // Other configurations keep their expectations
+    let mut metadata = Metadata::new(&dir, None);
+    let mac = report(
+        "mac",
+        false,
+        &test_result("OK", &[("a", "FAIL"), ("b", "FAIL")]),
+    );
+    let linux_debug = report(
+        "linux",
+        true,
+        &test_result("OK", &[("a", "FAIL"), ("b", "PASS")]),
+    );
+    assert_eq!(metadata.unexpected_results(&linux).unwrap(), []);
+    assert_eq!(metadata.unexpected_results(&mac).unwrap(), []);
+    assert_eq!(metadata.unexpected_results(&linux_debug).unwrap(), []);
+}
@@ -0,0 +1,144 @@
+use wptreport::score_summary::FocusArea;
+use wptreport::summarize::summarize_interop;
+use wptreport::wpt_fyi_summary::{TestSummary, WptFyiSummary};
+use wptreport::wpt_report::{WptReport, WptRunInfo};
+use wptreport::{HasRunInfo, ScorableReport};
+
+mod common;
+
+use common::{report_for, run_info, with_subtests};
+
+fn reports() -> Vec<WptReport> {
+    vec![
+        report_for(
+            "chrome",
+            &[
+                &with_subtests("/css/a.html", "OK", &[("x", "PASS"), ("y", "PASS")]),
+                r#"{"test": "/css/b.html", "status": "PASS"}"#,
+                r#"{"test": "/dom/c.html", "status": "PASS"}"#,
+            ],
+        ),
+        report_for(
+            "firefox",
+            &[
+                &with_subtests("/css/a.html", "OK", &[("x", "PASS"), ("y", "FAIL")]),
+                r#"{"test": "/css/b.html", "status": "PASS"}"#,
+            ],
+        ),
+        report_for(
+            "safari",
+            &[
+                &with_subtests(
+                    "/css/a.html",
+                    "OK",
+                    &[("x", "FAIL"), ("y", "PASS"), ("z", "PASS")],
+                ),
+                r#"{"test": "/css/b.html", "status": "FAIL"}"#,
+                r#"{"test": "/dom/c.html", "status": "PASS"}"#,
+            ],
+        ),
+    ]
+}
+
+#[test]
+fn interop_scores_per_area() {
+    let summary = summarize_interop(&reports(), None);
+    assert_eq!(summary.browsers, ["chrome", "firefox", "safari"]);
+    assert_eq!(summary.focus_areas, ["", "/css", "/dom"]);
+
+    // Each browser is scored on its own subtests. /css/a.html has subtests x, y and z
+    // between the browsers, and none pass everywhere. /css/b.html fails in Safari.
+    let css = &summary.scores[1];
+    assert_eq!(css.total_tests, 2);
+    assert_eq!(css.browser_scores, [1000, 750, 333]);
+    assert_eq!(css.interop_score, 0);
+
+    // /dom/c.html wasn't run in Firefox, so fails there
+    let dom = &summary.scores[2];
+    assert_eq!(dom.browser_scores, [1000, 0, 1000]);
+    assert_eq!(dom.interop_score, 0);
+
+    let all = &summary.scores[0];
+    assert_eq!(all.total_tests, 3);
+    assert_eq!(all.browser_scores, [1000, 500, 555]);
+    assert_eq!(all.interop_score, 0);
+}
+
+#[test]
+fn interop_scores_for_focus_areas() {
+    let reports = &reports()[..2];
+    let focus_areas = [
+        FocusArea {
+            name: String::from("Everything"),
+            areas: vec![String::from("/css"), String::from("/dom")],
+        },
+        FocusArea::from("/html"),
+    ];
+    let summary = summarize_interop(reports, Some(&focus_areas));
+    assert_eq!(summary.focus_areas, ["Everything", "/html"]);
+
+    // /css/b.html passes in both browsers, and so does one of the two subtests of /css/a.html
+    let everything = &summary.scores[0];
+    assert_eq!(everything.total_tests, 3);
+    assert_eq!(everything.interop_score, 500);
+
+    let html = &summary.scores[1];
+    assert_eq!(html.total_tests, 0);
+    assert_eq!(
+        (html.browser_scores.as_slice(), html.interop_score),
+        (&[0, 0][..], 0)
+    );
+
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(
+        json["scores"][0]["browser_scores"],
+        serde_json::json!([1000, 500])
+    );
+}
+
+/// A wpt.fyi summary, which only records subtest counts, for a browser
+struct Summary {
+    run_info: WptRunInfo,
+    summary: WptFyiSummary,
+}
+
+impl Summary {
+    fn new(product: &str, summary: &str) -> Self {
+        Self {
+            run_info: serde_json::from_value(run_info(product)).unwrap(),
+            summary: summary.parse().unwrap(),
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl ScorableReport for Summary {
+    type TestResultIter<'a> = (&'a String, &'a TestSummary) where Self: 'a;
+    fn results(&self) -> impl Iterator<Item = Self::TestResultIter<'_>> {
+        self.summary.results()
+    }
+}
+
+impl HasRunInfo for Summary {
+    fn run_info(&self) -> &WptRunInfo {
+        &self.run_info
+    }
+}
+
+#[test]
+fn count_only_tests_pass_everywhere_only_if_every_subtest_does() {
+    let summaries = [
+        Summary::new(
+            "chrome",
+            r#"{"/css/a.html": {"s": [4, 4], "c": "O"}, "/css/b.html": {"s": [2, 2], "c": "O"}}"#,
+        ),
+        Summary::new(
+            "firefox",
+            r#"{"/css/a.html": {"s": [3, 4], "c": "O"}, "/css/b.html": {"s": [2, 2], "c": "O"}}"#,
+        ),
+    ];
+    let summary = summarize_interop(&summaries, None);
+    let css = &summary.scores[1];
+    assert_eq!(css.browser_scores, [1000, 875]);
+    assert_eq!(css.interop_score, 500);
+}
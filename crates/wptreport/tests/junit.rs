@@ -0,0 +1,71 @@
+use wptreport::junit::{write_junit, SubtestMode};
+use wptreport::wpt_report::WptReport;
+
+const REPORT: &str = r#"{
+    "time_start": 1,
+    "time_end": 2,
+    "run_info": {"product": "servo & co", "revision": "abc", "os": "linux"},
+    "results": [
+        {"test": "/dom/a.html?q=<1>", "status": "OK", "duration": 1500, "subtests": [
+            {"name": "x \"quoted\"", "status": "PASS"},
+            {"name": "y", "status": "FAIL", "message": "assert_equals: 1 < 2\n"},
+            {"name": "z", "status": "NOTRUN"}
+        ]},
+        {"test": "/dom/b.html", "status": "TIMEOUT", "duration": 10000},
+        {"test": "/c.html", "status": "PRECONDITION_FAILED", "duration": 5, "message": "unsupported"}
+    ]
+}"#;
+
+fn junit(subtests: SubtestMode) -> String {
+    let report: WptReport = REPORT.parse().unwrap();
+    let mut out = Vec::new();
+    write_junit(&report, subtests, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn subtests_as_testcases() {
+    let xml = junit(SubtestMode::Testcases);
+    let attrs = r#"tests="6" failures="1" errors="1" skipped="2" time="11.505""#;
+    assert!(
+        xml.contains(&format!(r#"<testsuites name="wpt" {attrs}>"#)),
+        "{xml}"
+    );
+    assert!(xml.contains(&format!(r#"<testsuite name="servo &amp; co" {attrs}>"#)));
+    assert!(xml.contains(r#"<property name="product" value="servo &amp; co"/>"#));
+
+    // Test ids are split into a classname and a name, and subtests are classed by their test
+    assert!(xml.contains(r#"<testcase classname="/dom" name="a.html?q=&lt;1&gt;" time="1.500"/>"#));
+    assert!(xml.contains(
+        r#"<testcase classname="/dom/a.html?q=&lt;1&gt;" name="x &quot;quoted&quot;"/>"#
+    ));
+    assert!(xml.contains(concat!(
+        r#"<failure message="assert_equals: 1 &lt; 2&#10;" type="FAIL">"#,
+        r#"assert_equals: 1 &lt; 2&#10;</failure>"#
+    )));
+    assert!(xml.contains(r#"<skipped message="NOTRUN"/>"#));
+    assert!(xml.contains(r#"<error message="TIMEOUT" type="TIMEOUT"/>"#));
+    assert!(xml.contains(r#"<testcase classname="/" name="c.html" time="0.005">"#));
+    assert!(xml.contains(r#"<skipped message="unsupported"/>"#));
+}
+
+#[test]
+fn subtests_as_properties() {
+    let xml = junit(SubtestMode::Properties);
+    let attrs = r#"tests="3" failures="1" errors="1" skipped="1" time="11.505""#;
+    assert!(
+        xml.contains(&format!(r#"<testsuites name="wpt" {attrs}>"#)),
+        "{xml}"
+    );
+
+    // An OK test fails if any of its subtests do (but not if they're only skipped)
+    assert!(xml.contains(concat!(
+        r#"        <property name="x &quot;quoted&quot;" value="PASS"/>"#,
+        "\n",
+        r#"        <property name="y" value="FAIL"/>"#,
+        "\n",
+        r#"        <property name="z" value="NOTRUN"/>"#,
+    )));
+    assert!(xml.contains(r#"<failure message="1 of 3 subtests failed" type="FAIL">"#));
+    assert!(!xml.contains(r#"<testcase classname="/dom/a.html"#));
+}
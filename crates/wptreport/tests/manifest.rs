@@ -0,0 +1,122 @@
+use wptreport::manifest::{Manifest, ManifestTest, TestType, Timeout};
+use wptreport::wpt_report::WptReport;
+use wptreport::{score_wpt_report, ScorableReport, SubtestCounts, TestResultIter};
+
+const MANIFEST: &str = r#"{
+    "version": 8,
+    "url_base": "/",
+    "items": {
+        "testharness": {
+            "dom": {
+                "nodes": {
+                    "a.html": ["abc", [null, {}]],
+                    "b.any.js": [
+                        "def",
+                        ["/dom/nodes/b.any.html?1-10", {}],
+                        ["/dom/nodes/b.any.html?11-20", {"timeout": "long"}]
+                    ]
+                }
+            }
+        },
+        "reftest": {
+            "css": {
+                "r.html": ["123", [null, [["/css/r-ref.html", "=="]], {}]]
+            }
+        },
+        "manual": {
+            "css": {
+                "m-manual.html": ["456", [null, {}]]
+            }
+        },
+        "support": {
+            "css": {
+                "r-ref.html": ["789", [null, {}]]
+            }
+        }
+    }
+}"#;
+
+#[test]
+fn parses_nested_items() {
+    let manifest: Manifest = MANIFEST.parse().unwrap();
+    let ids: Vec<_> = manifest.tests.keys().map(String::as_str).collect();
+    assert_eq!(
+        ids,
+        [
+            "/dom/nodes/a.html",
+            "/dom/nodes/b.any.html?1-10",
+            "/dom/nodes/b.any.html?11-20",
+            "/css/r.html",
+            "/css/m-manual.html",
+        ]
+    );
+
+    assert_eq!(
+        manifest.get("/dom/nodes/b.any.html?11-20"),
+        Some(&ManifestTest {
+            path: String::from("dom/nodes/b.any.js"),
+            test_type: TestType::Testharness,
+            variant: Some(String::from("?11-20")),
+            timeout: Timeout::Long,
+        })
+    );
+    let reftest = manifest.get("/css/r.html").unwrap();
+    assert_eq!(
+        (&reftest.test_type, &reftest.variant, reftest.timeout),
+        (&TestType::Reftest, &None, Timeout::Normal)
+    );
+    assert_eq!(
+        manifest.get("/css/m-manual.html").unwrap().test_type,
+        TestType::Manual
+    );
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let manifest = MANIFEST.replace(r#""version": 8"#, r#""version": 7"#);
+    let err = manifest.parse::<Manifest>().unwrap_err();
+    assert_eq!(err.json_path(), Some("version"));
+}
+
+#[test]
+fn missing_tests_fail() {
+    let manifest: Manifest = MANIFEST.parse().unwrap();
+    let report: WptReport = r#"{
+        "run_info": {"product": "servo", "revision": "abc", "os": "linux"},
+        "time_start": 1,
+        "time_end": 2,
+        "results": [
+            {"test": "/dom/nodes/a.html", "status": "OK", "duration": 1, "subtests": [
+                {"name": "x", "status": "PASS"},
+                {"name": "y", "status": "PASS"}
+            ]},
+            {"test": "/dom/nodes/b.any.html?1-10", "status": "OK", "duration": 1, "subtests": [
+                {"name": "z", "status": "PASS"}
+            ]}
+        ]
+    }"#
+    .parse()
+    .unwrap();
+
+    let filled = manifest.fill_missing(&report);
+    let counts = |name| filled.get(name).map(|test| test.subtest_counts());
+    assert_eq!(
+        counts("/dom/nodes/a.html"),
+        Some(SubtestCounts { pass: 2, total: 2 })
+    );
+    assert_eq!(
+        counts("/dom/nodes/b.any.html?11-20"),
+        Some(SubtestCounts { pass: 0, total: 1 })
+    );
+    assert_eq!(counts("/css/m-manual.html"), None);
+
+    let scores = score_wpt_report(&filled);
+    // The missing variant scores 0/1, as does the missing reftest
+    let dom = &scores["/dom"];
+    assert_eq!(dom.tests, SubtestCounts { pass: 2, total: 3 });
+    assert_eq!(dom.subtests, SubtestCounts { pass: 3, total: 4 });
+    // Manual tests aren't expected to be run
+    let css = &scores["/css"];
+    assert_eq!(css.tests, SubtestCounts { pass: 0, total: 1 });
+    assert_eq!(css.subtests, SubtestCounts { pass: 0, total: 1 });
+}
@@ -0,0 +1,58 @@
+use wptreport::merge::WptReportMerger;
+use wptreport::wpt_report_stream::WptReportStream;
+use wptreport::ErrorKind;
+
+fn chunk(os: &str, test: &str, run_info_first: bool) -> String {
+    let run_info =
+        format!(r#""run_info": {{"product": "servo", "revision": "abc", "os": "{os}"}}"#);
+    let results = format!(r#""results": [{{"test": "{test}", "status": "PASS", "duration": 1}}]"#);
+    match run_info_first {
+        true => format!(r#"{{"time_start": 1, {run_info}, {results}, "time_end": 2}}"#),
+        false => format!(r#"{{"time_start": 1, {results}, {run_info}, "time_end": 2}}"#),
+    }
+}
+
+fn merged_tests(merger: WptReportMerger) -> Vec<String> {
+    let report = merger.into_merged_report();
+    report
+        .results
+        .into_iter()
+        .map(|result| result.test)
+        .collect()
+}
+
+#[test]
+fn mismatched_chunks_are_not_merged() {
+    for run_info_first in [true, false] {
+        let mut merger = WptReportMerger::new();
+        let first = chunk("linux", "/a.html", run_info_first);
+        merger
+            .add_chunk_stream(WptReportStream::new(first.as_bytes()).unwrap())
+            .unwrap();
+
+        let mismatched = chunk("mac", "/b.html", run_info_first);
+        let err = merger
+            .add_chunk_stream(WptReportStream::new(mismatched.as_bytes()).unwrap())
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::RunInfoMismatch { keys } if keys == &["os"]));
+
+        assert_eq!(merged_tests(merger), ["/a.html"]);
+    }
+}
+
+#[test]
+fn other_top_level_keys_are_kept() {
+    let mut merger = WptReportMerger::new();
+    for (test, commit) in [("/a.html", "abc"), ("/b.html", "def")] {
+        let json = chunk("linux", test, true).replace(
+            r#""time_end""#,
+            &format!(r#""wpt_commit": "{commit}", "time_end""#),
+        );
+        merger
+            .add_chunk_stream(WptReportStream::new(json.as_bytes()).unwrap())
+            .unwrap();
+    }
+    let report = merger.into_merged_report();
+    assert_eq!(report.extra["wpt_commit"], "abc");
+    assert_eq!(report.results.len(), 2);
+}
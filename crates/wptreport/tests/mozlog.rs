@@ -0,0 +1,137 @@
+use wptreport::mozlog::read_mozlog;
+use wptreport::wpt_report::{SubtestStatus, TestStatus};
+
+const SUITE_START: &str = r#"{"action": "suite_start", "time": 100, "run_info": {"product": "servo", "revision": "abc", "os": "linux"}}"#;
+
+fn log(lines: &[&str]) -> String {
+    let mut log = String::new();
+    for line in [SUITE_START].iter().chain(lines) {
+        log.push_str(line);
+        log.push('\n');
+    }
+    log
+}
+
+#[test]
+fn rebuilds_a_report() {
+    let log = log(&[
+        r#"{"action": "test_start", "time": 110, "test": "/a.html", "subsuite": "layout"}"#,
+        r#"{"action": "test_status", "time": 115, "test": "/a.html", "subtest": "x", "status": "FAIL", "expected": "PASS", "known_intermittent": ["TIMEOUT"], "message": "assert_true"}"#,
+        r#"{"action": "test_status", "time": 116, "test": "/a.html", "subtest": "y", "status": "PASS"}"#,
+        r#"{"action": "assertion_count", "time": 117, "test": "/a.html", "count": 2, "min_expected": 0, "max_expected": 3}"#,
+        r#"{"action": "test_end", "time": 130, "test": "/a.html", "status": "OK", "expected": "ERROR", "known_intermittent": ["CRASH"], "message": "done"}"#,
+        r#"{"action": "log", "time": 131, "message": "ignored"}"#,
+        r#"{"action": "test_start", "time": 140, "test": "/b.html"}"#,
+        r#"{"action": "test_end", "time": 150, "test": "/b.html", "status": "PASS", "extra": {"reftest_screenshots": [{"url": "http://web-platform.test:8000/b.html", "hash": "123"}, "==", {"url": "http://web-platform.test:8000/b-ref.html", "hash": "456"}]}}"#,
+        r#"{"action": "suite_end", "time": 200}"#,
+    ]);
+    let report = read_mozlog(log.as_bytes()).unwrap();
+    assert_eq!((report.time_start, report.time_end), (100, 200));
+    assert_eq!(report.run_info.product, "servo");
+
+    let a = &report.results[0];
+    assert_eq!((a.test.as_str(), a.duration), ("/a.html", 20));
+    assert_eq!(a.subsuite.as_deref(), Some("layout"));
+    assert_eq!(
+        (&a.status, &a.expected),
+        (&TestStatus::Ok, &Some(TestStatus::Error))
+    );
+    assert_eq!(a.known_intermittent, ["CRASH"]);
+    assert_eq!(a.message.as_deref(), Some("done"));
+    assert_eq!(a.asserts.as_ref().map(|asserts| asserts.count), Some(2));
+
+    let x = &a.subtests[0];
+    assert_eq!(
+        (x.name.as_str(), &x.status, &x.expected),
+        ("x", &SubtestStatus::Fail, &Some(SubtestStatus::Pass))
+    );
+    assert_eq!(x.known_intermittent, ["TIMEOUT"]);
+    assert_eq!(x.message.as_deref(), Some("assert_true"));
+    assert_eq!(a.subtests[1].status, SubtestStatus::Pass);
+
+    let screenshots = report.results[1].screenshots.as_ref().unwrap();
+    assert_eq!(screenshots["/b.html"], "sha1:123");
+    assert_eq!(screenshots["/b-ref.html"], "sha1:456");
+}
+
+#[test]
+fn unfinished_tests_crash() {
+    let log = log(&[
+        r#"{"action": "test_start", "time": 110, "test": "/a.html"}"#,
+        r#"{"action": "test_status", "time": 115, "test": "/a.html", "subtest": "x", "status": "PASS"}"#,
+        r#"{"action": "test_start", "time": 120, "test": "/b.html"}"#,
+        r#"{"action": "test_end", "time": 125, "test": "/b.html", "status": "PASS"}"#,
+    ]);
+    let report = read_mozlog(log.as_bytes()).unwrap();
+    // Without a suite_end, the run ends at the last entry
+    assert_eq!(report.time_end, 125);
+
+    let tests: Vec<_> = report
+        .results
+        .iter()
+        .map(|result| (result.test.as_str(), &result.status, result.duration))
+        .collect();
+    assert_eq!(
+        tests,
+        [
+            ("/b.html", &TestStatus::Pass, 5),
+            ("/a.html", &TestStatus::Crash, 15)
+        ]
+    );
+    let crashed = &report.results[1];
+    assert_eq!(crashed.subtests.len(), 1);
+    assert!(crashed.message.as_deref().unwrap().contains("no test_end"));
+}
+
+#[test]
+fn truncated_final_line_is_ignored() {
+    let mut log = log(&[
+        r#"{"action": "test_start", "time": 110, "test": "/a.html"}"#,
+        r#"{"action": "test_end", "time": 120, "test": "/a.html", "status": "PASS"}"#,
+    ]);
+    log.push_str(r#"{"action": "test_start", "time": 130, "te"#);
+    let report = read_mozlog(log.as_bytes()).unwrap();
+    assert_eq!(report.results.len(), 1);
+    assert_eq!(report.time_end, 120);
+
+    // A malformed line that isn't the last is an error
+    log.push('\n');
+    let err = read_mozlog(log.as_bytes()).unwrap_err();
+    assert_eq!(err.json_path(), Some("line 4"));
+}
+
+#[test]
+fn entries_without_a_test_start() {
+    let log = log(&[
+        r#"{"action": "test_status", "time": 110, "test": "/a.html", "subtest": "x", "status": "FAIL"}"#,
+        r#"{"action": "test_end", "time": 120, "test": "/a.html", "status": "OK"}"#,
+        r#"{"action": "test_end", "time": 130, "test": "/b.html", "status": "TIMEOUT"}"#,
+    ]);
+    let report = read_mozlog(log.as_bytes()).unwrap();
+    let tests: Vec<_> = report
+        .results
+        .iter()
+        .map(|result| {
+            let subtests = result.subtests.len();
+            (
+                result.test.as_str(),
+                &result.status,
+                result.duration,
+                subtests,
+            )
+        })
+        .collect();
+    assert_eq!(
+        tests,
+        [
+            ("/a.html", &TestStatus::Ok, 10, 1),
+            ("/b.html", &TestStatus::Timeout, 0, 0)
+        ]
+    );
+}
+
+#[test]
+fn run_info_is_required() {
+    let log = r#"{"action": "test_start", "time": 110, "test": "/a.html"}"#;
+    assert!(read_mozlog(log.as_bytes()).is_err());
+}
@@ -0,0 +1,126 @@
+#![cfg(feature = "arrow")]
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use arrow_array::{Array, BooleanArray, Int64Array, RecordBatch, StringArray, UInt32Array};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use wptreport::parquet::{ParquetWriter, RUNS_FILE, SUBTESTS_FILE, TESTS_FILE};
+use wptreport::servo_test_scores::WptScores;
+use wptreport::wpt_report::WptReport;
+
+/// An empty output directory that is unique to the calling test
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wptreport-parquet-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read_table(path: &Path) -> RecordBatch {
+    let file = File::open(path).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut batches: Vec<_> = reader.map(Result::unwrap).collect();
+    // The tables are small enough to be read as a single batch
+    assert_eq!(batches.len(), 1);
+    batches.pop().unwrap()
+}
+
+fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+    batch
+        .column_by_name(name)
+        .unwrap_or_else(|| panic!("no {name} column"))
+        .as_any()
+        .downcast_ref()
+        .unwrap()
+}
+
+fn strings(batch: &RecordBatch, name: &str) -> Vec<Option<String>> {
+    column::<StringArray>(batch, name)
+        .iter()
+        .map(|value| value.map(String::from))
+        .collect()
+}
+
+#[test]
+fn tables_read_back() {
+    let report: WptReport = r#"{
+        "time_start": 10,
+        "time_end": 20,
+        "run_info": {"product": "servo", "revision": "abc", "os": "linux", "debug": false, "label": "nightly"},
+        "results": [
+            {"test": "/a.html", "status": "OK", "duration": 5, "subtests": [
+                {"name": "x", "status": "PASS"},
+                {"name": "y", "status": "FAIL", "message": "nope"}
+            ]},
+            {"test": "/b.html", "status": "TIMEOUT", "expected": "PASS", "duration": 7}
+        ]
+    }"#
+    .parse()
+    .unwrap();
+    let scores: WptScores = r#"{
+        "run_info": {"product": "servo", "revision": "def", "os": "mac", "debug": true},
+        "test_scores": {
+            "/a.html": {"score": 1, "subtests": {"x": {"score": 1}, "y": {"score": 1}}}
+        }
+    }"#
+    .parse()
+    .unwrap();
+
+    let dir = out_dir("read-back");
+    let mut writer = ParquetWriter::create(&dir).unwrap();
+    assert_eq!(writer.add_report("report", &report).unwrap(), 0);
+    assert_eq!(writer.add_scores("scores", &scores).unwrap(), 1);
+    writer.finish().unwrap();
+
+    let runs = read_table(&dir.join(RUNS_FILE));
+    assert_eq!(
+        strings(&runs, "label"),
+        [Some(String::from("report")), Some(String::from("scores"))]
+    );
+    // The run_info label doesn't replace the run's label
+    assert_eq!(
+        strings(&runs, "run_info.label"),
+        [Some(String::from("nightly")), None]
+    );
+    assert_eq!(
+        strings(&runs, "os"),
+        [Some(String::from("linux")), Some(String::from("mac"))]
+    );
+    let debug = column::<BooleanArray>(&runs, "debug");
+    assert_eq!((debug.value(0), debug.value(1)), (false, true));
+
+    let tests = read_table(&dir.join(TESTS_FILE));
+    assert_eq!(column::<UInt32Array>(&tests, "run_id").values(), &[0, 0, 1]);
+    assert_eq!(
+        strings(&tests, "expected"),
+        [None, Some(String::from("PASS")), None]
+    );
+    let duration = column::<Int64Array>(&tests, "duration");
+    assert_eq!((duration.value(1), duration.is_null(2)), (7, true));
+    assert_eq!(
+        column::<UInt32Array>(&tests, "subtests_passed").values(),
+        &[1, 0, 2]
+    );
+    assert_eq!(
+        column::<UInt32Array>(&tests, "subtests_total").values(),
+        &[2, 1, 2]
+    );
+
+    let subtests = read_table(&dir.join(SUBTESTS_FILE));
+    assert_eq!(
+        strings(&subtests, "subtest"),
+        ["x", "y", "x", "y"].map(|name| Some(String::from(name)))
+    );
+    assert_eq!(
+        strings(&subtests, "message"),
+        [None, Some(String::from("nope")), None, None]
+    );
+    let passes: Vec<_> = column::<BooleanArray>(&subtests, "passes")
+        .iter()
+        .flatten()
+        .collect();
+    assert_eq!(passes, [true, false, true, true]);
+}
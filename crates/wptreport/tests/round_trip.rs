@@ -0,0 +1,95 @@
+use serde_json::Value;
+use wptreport::wpt_report::{WptReport, WptRunInfo};
+use wptreport::wpt_report_stream::WptReportStream;
+
+const REPORT: &str = r#"{
+  "time_start": 1700000000000,
+  "time_end": 1700000360000,
+  "run_info": {
+    "product": "servo",
+    "browser_version": "0.0.1-abcdef",
+    "revision": "0123456789abcdef0123456789abcdef01234567",
+    "os": "linux",
+    "debug": false,
+    "layout_2020": true,
+    "custom": {"nested": [1, 2, 3]}
+  },
+  "results": [
+    {
+      "test": "/css/css-flexbox/align-content-001.html",
+      "subsuite": "",
+      "status": "FAIL",
+      "expected": "PASS",
+      "known_intermittent": ["TIMEOUT"],
+      "message": "Screenshots differ",
+      "duration": 312,
+      "screenshots": {
+        "/css/css-flexbox/align-content-001.html": "sha1:da39a3ee5e6b4b0d3255bfef95601890afd80709",
+        "/css/css-flexbox/reference/align-content-001-ref.html": "sha1:2fd4e1c67a2d28fced849ee1bb76e7391b93eb12"
+      },
+      "asserts": {"count": 2, "min": 0, "max": 3},
+      "subtests": [],
+      "lsan_leaks": [{"frames": ["a", "b"], "allowed": false}],
+      "reftest_type": "=="
+    },
+    {
+      "test": "/dom/nodes/Node-cloneNode.html",
+      "subsuite": "layout-2020",
+      "status": "INTERNAL-ERROR",
+      "duration": 1045,
+      "subtests": [
+        {"name": "createElement(a)", "status": "PASS"},
+        {
+          "name": "createElement(b)",
+          "status": "FAIL",
+          "expected": "PASS",
+          "known_intermittent": ["PRECONDITION_FAILED"],
+          "message": "assert_equals: expected \"b\" but got \"B\"",
+          "stack": "@http://web-platform.test:8000/dom/nodes/Node-cloneNode.html:12:5"
+        },
+        {"name": "createElement(c)", "status": "NEW_STATUS", "vendor_data": 42}
+      ]
+    },
+    {"test": "/html/no-subsuite.html", "status": "PASS", "duration": 3, "subtests": []},
+    {"test": "/html/no-subtests-key.html", "status": "FAIL", "duration": 4}
+  ],
+  "wpt_commit": {"sha": "0123456789abcdef"}
+}"#;
+
+#[test]
+fn wpt_report_round_trips() {
+    let report: WptReport = REPORT.parse().unwrap();
+    let serialized = serde_json::to_value(&report).unwrap();
+    let original: Value = serde_json::from_str(REPORT).unwrap();
+    assert_eq!(serialized, original);
+}
+
+#[test]
+fn streamed_wpt_report_round_trips() {
+    let report = WptReportStream::new(REPORT.as_bytes())
+        .unwrap()
+        .into_report()
+        .unwrap();
+    let serialized = serde_json::to_value(&report).unwrap();
+    let original: Value = serde_json::from_str(REPORT).unwrap();
+    assert_eq!(serialized, original);
+}
+
+#[test]
+fn serialized_report_round_trips() {
+    let report: WptReport = REPORT.parse().unwrap();
+    let first = serde_json::to_string(&report).unwrap();
+    let reparsed: WptReport = first.parse().unwrap();
+    let second = serde_json::to_string(&reparsed).unwrap();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn minimal_run_info_round_trips() {
+    let json = r#"{"product": "ladybird", "revision": "abc"}"#;
+    let run_info: WptRunInfo = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        serde_json::to_value(&run_info).unwrap(),
+        serde_json::from_str::<Value>(json).unwrap()
+    );
+}
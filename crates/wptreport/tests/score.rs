@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use wptreport::intern::{InternedScores, Interner};
+use wptreport::score::TestsWithoutSubtests;
+use wptreport::servo_test_scores::WptScores;
+use wptreport::wpt_fyi_summary::WptFyiSummary;
+use wptreport::wpt_report::{SubtestStatus, TestStatus};
+use wptreport::{
+    score_wpt_report, score_wpt_report_against, score_wpt_report_against_with,
+    score_wpt_report_with,
+};
+use wptreport::{AreaScores, ScorableReport, ScoringPolicy, SubtestCounts, TestResultIter};
+
+mod common;
+
+use common::{report, RUN_INFO};
+
+/// A wpt.fyi summary, which only records subtest counts
+const SUMMARY: &str = r#"{
+    "/css/partial.html": {"s": [3, 4], "c": "O"},
+    "/css/passing.html": {"s": [2, 2], "c": "O"},
+    "/css/no-subtests.html": {"s": [1, 1], "c": "P"},
+    "/dom/failing.html": {"s": [0, 1], "c": "F"}
+}"#;
+
+#[test]
+fn count_only_reports_keep_their_counts() {
+    let summary: WptFyiSummary = SUMMARY.parse().unwrap();
+    let scores = score_wpt_report(&summary);
+
+    let css = &scores["/css"];
+    assert_eq!(css.tests, SubtestCounts { pass: 2, total: 3 });
+    assert_eq!(css.subtests, SubtestCounts { pass: 6, total: 7 });
+    assert!((css.servo_score() - (0.75 + 1.0 + 1.0)).abs() < 1e-9);
+    assert_eq!(scores["/dom"].subtests, SubtestCounts { pass: 0, total: 1 });
+
+    // Only the tests that count a single subtest are tests without subtests
+    let policy = ScoringPolicy {
+        tests_without_subtests: TestsWithoutSubtests::Exclude,
+        ..ScoringPolicy::default()
+    };
+    let scores = score_wpt_report_with(&summary, &policy);
+    assert_eq!(scores["/css"].tests, SubtestCounts { pass: 1, total: 2 });
+    assert_eq!(scores["/css"].subtests, SubtestCounts { pass: 5, total: 6 });
+    assert!(!scores.contains_key("/dom"));
+}
+
+#[test]
+fn keyed_reports_look_up_tests() {
+    let summary: WptFyiSummary = SUMMARY.parse().unwrap();
+    assert!(summary.is_keyed());
+    let counts = summary
+        .get("/css/partial.html")
+        .map(|test| test.subtest_counts());
+    assert_eq!(counts, Some(SubtestCounts { pass: 3, total: 4 }));
+    assert!(summary.get("/css/missing.html").is_none());
+
+    // Reports that aren't keyed search their results
+    let report = report(&[r#"{"test": "/css/a.html", "status": "PASS"}"#]);
+    assert!(!report.is_keyed());
+    assert!(report.get("/css/a.html").is_some());
+    assert!(report.get("/css/b.html").is_none());
+}
+
+#[test]
+fn count_only_reference_uses_the_reports_counts() {
+    let summary: WptFyiSummary = SUMMARY.parse().unwrap();
+    let reference: WptFyiSummary = r#"{
+        "/css/partial.html": {"s": [4, 4], "c": "O"},
+        "/css/missing.html": {"s": [2, 3], "c": "O"}
+    }"#
+    .parse()
+    .unwrap();
+    let scores = score_wpt_report_against(&summary, &reference);
+    assert_eq!(scores["/css"].tests, SubtestCounts { pass: 0, total: 2 });
+    assert_eq!(scores["/css"].subtests, SubtestCounts { pass: 3, total: 7 });
+}
+
+#[test]
+fn scores_files_score_the_same_in_every_representation() {
+    let scores_json = |a: u32, b: u32, c: u32| {
+        format!(
+            r#"{{"run_info": {RUN_INFO}, "test_scores": {{
+                "/css/a.html": {{"score": 1, "subtests": {{"x": {{"score": {a}}}, "y": {{"score": {b}}}}}}},
+                "/css/b.html": {{"score": {c}, "subtests": {{}}}},
+                "/dom/c.html": {{"score": 1, "subtests": {{"z": {{"score": 1}}}}}}
+            }}}}"#
+        )
+    };
+    let run_json = scores_json(1, 0, 1);
+    // Scores above 1 are malformed, and count as a single pass
+    let reference_json = scores_json(2, 1, 3).replace(r#""/dom/c.html""#, r#""/dom/d.html""#);
+
+    let run: WptScores = run_json.parse().unwrap();
+    let reference: WptScores = reference_json.parse().unwrap();
+    let expected = score_wpt_report_against(&run, &reference);
+    assert_eq!(
+        expected["/css"].subtests,
+        SubtestCounts { pass: 2, total: 3 }
+    );
+    assert_eq!(
+        expected["/dom"].subtests,
+        SubtestCounts { pass: 0, total: 1 }
+    );
+    assert_eq!(
+        reference.score()[""].subtests,
+        SubtestCounts { pass: 4, total: 4 }
+    );
+
+    let same = |scores: BTreeMap<String, AreaScores>| {
+        assert_eq!(format!("{scores:?}"), format!("{expected:?}"));
+    };
+    same(run.score_against(&reference));
+
+    let interner = Interner::new();
+    let interned_run = InternedScores::from_str_with(&run_json, &interner).unwrap();
+    let interned_reference = InternedScores::from_str_with(&reference_json, &interner).unwrap();
+    same(interned_run.score_against(&interned_reference));
+
+    // Malformed scores count as a single pass when counting a test's own subtests too
+    let counts: Vec<_> = reference
+        .results()
+        .map(|test| test.subtest_counts())
+        .collect();
+    let interned_counts: Vec<_> = interned_reference
+        .results()
+        .map(|test| test.subtest_counts())
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            SubtestCounts { pass: 2, total: 2 },
+            SubtestCounts { pass: 1, total: 1 },
+            SubtestCounts { pass: 1, total: 1 }
+        ]
+    );
+    assert_eq!(interned_counts, counts);
+
+    let counts =
+        run.test_scores["/css/a.html"].score_against(&reference.test_scores["/css/a.html"]);
+    assert_eq!(counts, SubtestCounts { pass: 1, total: 2 });
+}
+
+#[test]
+fn only_reference_tests_and_subtests_are_scored() {
+    let run = report(&[
+        r#"{"test": "/css/a.html", "status": "OK", "subtests": [
+            {"name": "x", "status": "PASS"},
+            {"name": "not in reference", "status": "PASS"}
+        ]}"#,
+        r#"{"test": "/css/b.html", "status": "PASS"}"#,
+        r#"{"test": "/css/not-in-reference.html", "status": "PASS"}"#,
+    ]);
+    let reference = report(&[
+        r#"{"test": "/css/a.html", "status": "OK", "subtests": [
+            {"name": "x", "status": "FAIL"},
+            {"name": "missing from run", "status": "PASS"}
+        ]}"#,
+        r#"{"test": "/css/b.html", "status": "FAIL"}"#,
+        r#"{"test": "/dom/missing-from-run.html", "status": "PASS"}"#,
+    ]);
+
+    let scores = score_wpt_report_against(&run, &reference);
+    assert_eq!(scores["/css"].tests, SubtestCounts { pass: 1, total: 2 });
+    assert_eq!(scores["/css"].subtests, SubtestCounts { pass: 2, total: 3 });
+    assert_eq!(scores["/dom"].subtests, SubtestCounts { pass: 0, total: 1 });
+    assert_eq!(scores[""].tests.total, 3);
+
+    // Scoring a run against itself is the same as scoring it alone
+    let alone = score_wpt_report(&run);
+    assert_eq!(
+        format!("{:?}", score_wpt_report_against(&run, &run)),
+        format!("{alone:?}")
+    );
+}
+
+#[test]
+fn scoring_against_applies_the_policy() {
+    let run = report(&[
+        r#"{"test": "/css/a.html", "status": "TIMEOUT", "subtests": [
+            {"name": "x", "status": "PASS"},
+            {"name": "y", "status": "PRECONDITION_FAILED"}
+        ]}"#,
+        r#"{"test": "/css/b.html", "status": "OK"}"#,
+    ]);
+    let policy = ScoringPolicy {
+        passing_test_statuses: vec![TestStatus::Pass, TestStatus::Ok],
+        passing_subtest_statuses: vec![SubtestStatus::Pass, SubtestStatus::PreconditionFailed],
+        ..ScoringPolicy::default()
+    };
+    let scores = score_wpt_report_against_with(&run, &run, &policy);
+    assert_eq!(scores["/css"].subtests, SubtestCounts { pass: 3, total: 3 });
+
+    let policy = ScoringPolicy {
+        require_harness_ok: true,
+        tests_without_subtests: TestsWithoutSubtests::Exclude,
+        ..policy
+    };
+    let scores = score_wpt_report_against_with(&run, &run, &policy);
+    assert_eq!(scores["/css"].tests, SubtestCounts { pass: 0, total: 1 });
+    assert_eq!(scores["/css"].subtests, SubtestCounts { pass: 0, total: 2 });
+}
+
+#[test]
+fn wptreport_against_scores_file() {
+    let run = report(&[
+        r#"{"test": "/css/a.html", "status": "OK", "subtests": [
+            {"name": "x", "status": "PASS"},
+            {"name": "y", "status": "TIMEOUT"}
+        ]}"#,
+        r#"{"test": "/css/b.html", "status": "PASS"}"#,
+    ]);
+    let reference: WptScores = format!(
+        r#"{{"run_info": {RUN_INFO}, "test_scores": {{
+            "/css/a.html": {{"score": 1, "subtests": {{"x": {{"score": 0}}, "y": {{"score": 1}}}}}},
+            "/css/b.html": {{"score": 0, "subtests": {{}}}}
+        }}}}"#
+    )
+    .parse()
+    .unwrap();
+
+    let scores = score_wpt_report_against(&run, &reference);
+    assert_eq!(scores["/css"].subtests, SubtestCounts { pass: 2, total: 3 });
+
+    // A scores file made from a run scores the same as the run against a wptreport reference
+    let converted = WptScores::from(report(&[
+        r#"{"test": "/css/a.html", "status": "OK", "subtests": [
+            {"name": "x", "status": "PASS"},
+            {"name": "y", "status": "TIMEOUT"}
+        ]}"#,
+        r#"{"test": "/css/b.html", "status": "PASS"}"#,
+    ]));
+    assert_eq!(
+        format!("{:?}", score_wpt_report_against(&converted, &run)),
+        format!("{:?}", score_wpt_report_against(&run, &run))
+    );
+}
+
+#[test]
+fn names_without_a_slash_are_only_in_the_root_area() {
+    let run = report(&[
+        r#"{"test": "a.html", "status": "PASS"}"#,
+        r#"{"test": "/css/b.html", "status": "FAIL"}"#,
+    ]);
+    let scores = score_wpt_report(&run);
+    assert_eq!(scores.keys().collect::<Vec<_>>(), ["", "/css"]);
+    assert_eq!(scores[""].tests, SubtestCounts { pass: 1, total: 2 });
+}
@@ -0,0 +1,49 @@
+use wptreport::score_summary::{RunScores, ScoringMethod};
+use wptreport::{AreaScores, SubtestCounts};
+
+/// An area with a test where 1 of 2 subtests pass and a passing test without subtests
+fn area_scores() -> AreaScores {
+    let mut scores = AreaScores::default();
+    scores.add_test(SubtestCounts { pass: 1, total: 2 });
+    scores.add_test(SubtestCounts { pass: 1, total: 1 });
+    scores
+}
+
+#[test]
+fn methods_add_their_fields() {
+    let servo_only = RunScores::with_methods(area_scores(), &[ScoringMethod::Servo]);
+    let json = serde_json::to_value(&servo_only).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "total_tests": 2,
+            "total_score": 1.5,
+            "total_subtests": 3,
+            "total_subtests_passed": 2
+        })
+    );
+
+    let all = RunScores::with_methods(
+        area_scores(),
+        &[ScoringMethod::Interop, ScoringMethod::TestsPassed],
+    );
+    assert_eq!(all.interop_score, Some(750));
+    assert_eq!(all.total_tests_passed, Some(1));
+    // The servo and subtest ratio fields are always present
+    assert_eq!((all.total_score, all.total_subtests_passed), (1.5, 2));
+}
+
+#[test]
+fn scores_are_fractions() {
+    let scores = RunScores::with_methods(area_scores(), &[ScoringMethod::TestsPassed]);
+    assert_eq!(scores.score(ScoringMethod::Servo), Some(0.75));
+    assert_eq!(scores.score(ScoringMethod::TestsPassed), Some(0.5));
+    assert_eq!(scores.score(ScoringMethod::SubtestRatio), Some(2.0 / 3.0));
+    // Not computed
+    assert_eq!(scores.score(ScoringMethod::Interop), None);
+
+    // An empty area scores 0 rather than NaN
+    let empty = RunScores::with_methods(AreaScores::default(), &[ScoringMethod::Interop]);
+    assert_eq!(empty.score(ScoringMethod::Servo), Some(0.0));
+    assert_eq!(empty.score(ScoringMethod::SubtestRatio), Some(0.0));
+}
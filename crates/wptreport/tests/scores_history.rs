@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use wptreport::scores_history::ScoresHistory;
+use wptreport::servo_test_scores::WptScores;
+use wptreport::{AreaScores, SubtestCounts};
+
+fn scores(revision: &str, tests: &str) -> WptScores {
+    format!(
+        r#"{{
+            "run_info": {{"product": "servo", "revision": "{revision}", "os": "linux"}},
+            "test_scores": {{{tests}}}
+        }}"#
+    )
+    .parse()
+    .unwrap()
+}
+
+fn runs() -> [WptScores; 3] {
+    [
+        scores(
+            "a",
+            r#""/css/a.html": {"score": 1, "subtests": {"x": {"score": 1}, "y": {"score": 0}}},
+               "/css/b.html": {"score": 0, "subtests": {}},
+               "/dom/c.html": {"score": 1, "subtests": {"z": {"score": 1}}}"#,
+        ),
+        // Tests in a different order, a new subtest and test, and a score above 1
+        scores(
+            "b",
+            r#""/dom/c.html": {"score": 1, "subtests": {"z": {"score": 0}, "w": {"score": 3}}},
+               "/css/a.html": {"score": 1, "subtests": {"y": {"score": 1}}},
+               "/new.html": {"score": 2, "subtests": {}}"#,
+        ),
+        scores("c", ""),
+    ]
+}
+
+fn history() -> ScoresHistory {
+    let mut history = ScoresHistory::new();
+    for (label, run) in ["a.json", "b.json.xz", "c.json"].iter().zip(runs()) {
+        history.push(*label, &run);
+    }
+    history
+}
+
+fn written(history: &ScoresHistory) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    history.write(&mut bytes).unwrap();
+    bytes
+}
+
+fn counts(scores: BTreeMap<String, AreaScores>) -> Vec<(String, SubtestCounts, SubtestCounts)> {
+    scores
+        .into_iter()
+        .map(|(area, scores)| (area, scores.tests, scores.subtests))
+        .collect()
+}
+
+#[test]
+fn runs_round_trip() {
+    let bytes = written(&history());
+    let history = ScoresHistory::from_reader(bytes.as_slice()).unwrap();
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.label(1), "b.json.xz");
+    assert_eq!(history.run_info(1).revision, "b");
+
+    for (idx, run) in runs().iter().enumerate() {
+        let unpacked = history.to_scores(idx).unwrap();
+        assert_eq!(
+            serde_json::to_value(&unpacked).unwrap(),
+            serde_json::to_value(run).unwrap()
+        );
+    }
+
+    // Writing a history that was read back gives the same bytes
+    assert_eq!(written(&history), bytes);
+}
+
+#[test]
+fn scores_match_the_unpacked_scores() {
+    let history = ScoresHistory::from_slice(&written(&history())).unwrap();
+    for reference_idx in 0..history.len() {
+        let reference = history.reference(reference_idx).unwrap();
+        let unpacked_reference = history.to_scores(reference_idx).unwrap();
+        for run in 0..history.len() {
+            let expected = history
+                .to_scores(run)
+                .unwrap()
+                .score_against(&unpacked_reference);
+            let packed = history.score_against(run, &reference).unwrap();
+            assert_eq!(
+                counts(packed),
+                counts(expected),
+                "{run} against {reference_idx}"
+            );
+        }
+    }
+}
+
+#[test]
+fn references_from_other_histories_are_rejected() {
+    let history = history();
+    let other = ScoresHistory::from_slice(&written(&history)).unwrap();
+    let reference = other.reference(0).unwrap();
+    assert!(history.score_against(0, &reference).is_err());
+}
+
+#[test]
+fn corrupt_input_is_an_error() {
+    let bytes = written(&history());
+    for len in 0..bytes.len() {
+        assert!(
+            ScoresHistory::from_slice(&bytes[..len]).is_err(),
+            "truncated to {len} bytes"
+        );
+    }
+
+    // The last run is empty, so its data ends with a count of zero scores above 1. Claiming
+    // there is one leaves the run's data too short, which is found when it's decoded.
+    let mut corrupt_run = bytes.clone();
+    *corrupt_run.last_mut().unwrap() = 1;
+    let history = ScoresHistory::from_slice(&corrupt_run).unwrap();
+    assert!(history.to_scores(2).is_err());
+    assert!(history.reference(2).is_err());
+    assert!(history.to_scores(1).is_ok());
+
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+    assert!(ScoresHistory::from_slice(&wrong_magic).is_err());
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(ScoresHistory::from_slice(&trailing).is_err());
+
+    // A huge string count is rejected without trying to allocate it
+    let mut huge_count = bytes[..12].to_vec();
+    huge_count.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+    let err = ScoresHistory::from_slice(&huge_count).unwrap_err();
+    assert!(err.to_string().contains("unexpected end of data"), "{err}");
+}
@@ -0,0 +1,239 @@
+#![cfg(feature = "wpt-fyi")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use wptreport::wpt_fyi_api::{RunsQuery, WptFyiClient};
+use wptreport::wpt_report::TestStatus;
+
+/// A minimal HTTP server which serves canned JSON bodies by path and records the
+/// request targets (path and query) it receives
+struct MockServer {
+    base_url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// `routes` is given the server's base URL so that responses can link back to it
+    fn start(routes: impl FnOnce(&str) -> Vec<(&'static str, String)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let routes = routes(&base_url);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let target = request_line.split(' ').nth(1).unwrap_or("").to_string();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                reader
+                    .take(content_length)
+                    .read_to_end(&mut Vec::new())
+                    .unwrap();
+
+                let path = target.split('?').next().unwrap();
+                let response = match routes.iter().find(|(route, _)| *route == path) {
+                    Some((_, body)) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    ),
+                    None => String::from(
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    ),
+                };
+                recorded.lock().unwrap().push(target);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        Self { base_url, requests }
+    }
+
+    fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn run_metadata(base_url: &str, id: u64, browser: &str) -> String {
+    format!(
+        r#"{{
+          "id": {id},
+          "browser_name": "{browser}",
+          "browser_version": "1.0",
+          "os_name": "linux",
+          "os_version": "22.04",
+          "revision": "0123456789",
+          "full_revision_hash": "0123456789abcdef0123456789abcdef01234567",
+          "results_url": "{base_url}/results/{id}-summary_v2.json.gz",
+          "created_at": "2025-01-01T00:00:00Z",
+          "time_start": "2025-01-01T00:00:00Z",
+          "time_end": "2025-01-01T01:00:00Z",
+          "raw_results_url": "{base_url}/results/{id}-report.json",
+          "labels": ["master", "stable"]
+        }}"#
+    )
+}
+
+fn wpt_fyi_routes(base_url: &str) -> Vec<(&'static str, String)> {
+    let runs = format!(
+        "[{}, {}]",
+        run_metadata(base_url, 1, "chrome"),
+        run_metadata(base_url, 2, "firefox")
+    );
+    let search = format!(
+        r#"{{
+          "runs": [{}, {}],
+          "results": [
+            {{
+              "test": "/dom/b.html",
+              "legacy_status": [
+                {{"passes": 1, "total": 1, "status": "O", "newAggProcess": true}},
+                {{"passes": 0, "total": 1, "status": "F", "newAggProcess": true}}
+              ]
+            }},
+            {{
+              "test": "/css/css-grid/a.html",
+              "legacy_status": [
+                {{"passes": 3, "total": 4, "status": "O", "newAggProcess": true}},
+                {{"passes": 4, "total": 4, "status": "O", "newAggProcess": true}}
+              ]
+            }}
+          ]
+        }}"#,
+        run_metadata(base_url, 1, "chrome"),
+        run_metadata(base_url, 2, "firefox")
+    );
+    let report = r#"{
+      "time_start": 1,
+      "time_end": 2,
+      "run_info": {"product": "chrome", "revision": "0123456789abcdef"},
+      "results": [
+        {"test": "/dom/b.html", "status": "PASS", "duration": 5, "subtests": []}
+      ]
+    }"#;
+    let summary = r#"{"/dom/b.html": {"s": [1, 1], "c": "P"}}"#;
+
+    vec![
+        ("/api/runs", runs),
+        ("/api/search", search),
+        ("/results/1-report.json", report.to_string()),
+        ("/results/1-summary_v2.json.gz", summary.to_string()),
+    ]
+}
+
+#[tokio::test]
+async fn get_runs_sends_query_parameters() {
+    let server = MockServer::start(|_| vec![("/api/runs", String::from("[]"))]);
+    let client = WptFyiClient::with_base_url(format!("{}/", server.base_url));
+
+    let query = RunsQuery {
+        products: vec![String::from("chrome"), String::from("firefox")],
+        labels: vec![String::from("experimental")],
+        sha: Some(String::from("abc123")),
+        aligned: true,
+        max_count: Some(5),
+    };
+    let runs = client.get_runs(&query).await.unwrap();
+    assert!(runs.is_empty());
+
+    assert_eq!(
+        server.requests(),
+        vec![String::from(
+            "/api/runs?product=chrome&product=firefox&label=experimental&sha=abc123&aligned=true&max-count=5"
+        )]
+    );
+}
+
+#[tokio::test]
+async fn get_latest_runs_parses_run_metadata() {
+    let server = MockServer::start(|base_url| {
+        vec![(
+            "/api/runs",
+            format!("[{}]", run_metadata(base_url, 42, "servo")),
+        )]
+    });
+    let client = WptFyiClient::with_base_url(&server.base_url);
+
+    let runs = client.get_latest_runs().await.unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].id, 42);
+    assert_eq!(runs[0].browser_name, "servo");
+    assert_eq!(
+        server.requests(),
+        vec![String::from("/api/runs?aligned=true")]
+    );
+}
+
+#[tokio::test]
+async fn search_and_score_runs() {
+    let server = MockServer::start(wpt_fyi_routes);
+    let client = WptFyiClient::with_base_url(&server.base_url);
+
+    let results = client
+        .search(&[1, 2], Some("/css/css-grid/"))
+        .await
+        .unwrap();
+    let tests: Vec<_> = results.results.iter().map(|r| r.test.as_str()).collect();
+    assert_eq!(tests, ["/css/css-grid/a.html", "/dom/b.html"]);
+
+    let summaries = client.get_run_data(&[1, 2]).await.unwrap();
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(summaries[0].metadata.browser_name, "chrome");
+    let chrome = &summaries[0].scores["/css/css-grid"];
+    assert_eq!(chrome.subtests.pass, 3);
+    assert_eq!(chrome.subtests.total, 4);
+    let firefox = &summaries[1].scores["/dom"];
+    assert_eq!(firefox.tests.pass, 0);
+
+    assert_eq!(
+        server.requests(),
+        vec![
+            String::from("/api/search?run_ids=1%2C2&q=%2Fcss%2Fcss-grid%2F"),
+            String::from("/api/search?run_ids=1%2C2"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn download_raw_results_and_summary() {
+    let server = MockServer::start(wpt_fyi_routes);
+    let client = WptFyiClient::with_base_url(&server.base_url);
+
+    let runs = client.get_latest_runs().await.unwrap();
+    let report = client.get_raw_results(&runs[0]).await.unwrap();
+    assert_eq!(report.run_info.product, "chrome");
+    assert_eq!(report.results[0].status, TestStatus::Pass);
+
+    let summary = client.get_summary(&runs[0]).await.unwrap();
+    assert_eq!(summary.results["/dom/b.html"].status, TestStatus::Pass);
+}
+
+#[tokio::test]
+async fn http_errors_include_the_url() {
+    let server = MockServer::start(|_| Vec::new());
+    let client = WptFyiClient::with_base_url(&server.base_url);
+
+    let err = client.get_latest_runs().await.unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("/api/runs?aligned=true"), "{message}");
+    assert!(message.contains("404"), "{message}");
+}
@@ -0,0 +1,92 @@
+use serde_json::json;
+use wptreport::score::TestsWithoutSubtests;
+use wptreport::wpt_fyi_summary::{TestSummary, WptFyiSummary};
+use wptreport::wpt_report::TestStatus;
+use wptreport::{score_wpt_report, score_wpt_report_with, ScoringPolicy, SubtestCounts};
+
+const SUMMARY: &str = r#"{
+    "/a.html": {"s": [1, 1], "c": "P"},
+    "/b.html": {"s": [2, 3], "c": "O"},
+    "/c.html": {"s": [0, 1], "c": "PF"},
+    "/d.html": {"s": [1, 2], "c": "T"},
+    "/e.html": {"s": [0, 1], "c": "Z"},
+    "/f.html": {"s": [0, 1], "c": "INTERNAL-ERROR"}
+}"#;
+
+#[test]
+fn statuses_are_abbreviated() {
+    let summary: WptFyiSummary = SUMMARY.parse().unwrap();
+    let statuses: Vec<_> = summary
+        .results
+        .values()
+        .map(|test| test.status.clone())
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            TestStatus::Pass,
+            TestStatus::Ok,
+            TestStatus::PreconditionFailed,
+            TestStatus::Timeout,
+            // Unrecognised codes and statuses without an abbreviation are kept as they are
+            TestStatus::from("Z"),
+            TestStatus::from("INTERNAL-ERROR"),
+        ]
+    );
+    assert_eq!(
+        summary.results["/b.html"].counts,
+        SubtestCounts { pass: 2, total: 3 }
+    );
+
+    let original: serde_json::Value = serde_json::from_str(SUMMARY).unwrap();
+    assert_eq!(serde_json::to_value(&summary).unwrap(), original);
+}
+
+#[test]
+fn statuses_are_written_in_abbreviated_form() {
+    let mut summary = WptFyiSummary::default();
+    for (name, status) in [
+        ("/a.html", "PASS"),
+        ("/b.html", "CRASH"),
+        ("/c.html", "SKIP"),
+    ] {
+        let test = TestSummary {
+            counts: SubtestCounts { pass: 0, total: 1 },
+            status: TestStatus::from(status),
+        };
+        summary.results.insert(name.to_string(), test);
+    }
+    assert_eq!(
+        serde_json::to_value(&summary).unwrap(),
+        json!({
+            "/a.html": {"s": [0, 1], "c": "P"},
+            "/b.html": {"s": [0, 1], "c": "C"},
+            "/c.html": {"s": [0, 1], "c": "S"}
+        })
+    );
+}
+
+#[test]
+fn policies_apply_to_statuses() {
+    let summary: WptFyiSummary = SUMMARY.parse().unwrap();
+    let scores = score_wpt_report(&summary);
+    assert_eq!(scores[""].tests, SubtestCounts { pass: 1, total: 6 });
+    assert_eq!(scores[""].subtests, SubtestCounts { pass: 4, total: 9 });
+
+    let policy = ScoringPolicy {
+        passing_test_statuses: vec![TestStatus::Pass, TestStatus::PreconditionFailed],
+        require_harness_ok: true,
+        ..ScoringPolicy::default()
+    };
+    let scores = score_wpt_report_with(&summary, &policy);
+    // /c.html now passes, and /d.html timed out so none of its subtests count
+    assert_eq!(scores[""].subtests, SubtestCounts { pass: 4, total: 9 });
+    assert_eq!(scores[""].tests, SubtestCounts { pass: 2, total: 6 });
+
+    let policy = ScoringPolicy {
+        tests_without_subtests: TestsWithoutSubtests::Exclude,
+        ..policy
+    };
+    let scores = score_wpt_report_with(&summary, &policy);
+    assert_eq!(scores[""].tests, SubtestCounts { pass: 0, total: 2 });
+}
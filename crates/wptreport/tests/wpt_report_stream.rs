@@ -0,0 +1,111 @@
+use std::io::BufReader;
+
+use serde_json::Value;
+use wptreport::wpt_report::WptReport;
+use wptreport::wpt_report_stream::WptReportStream;
+
+mod common;
+
+use common::RUN_INFO;
+
+/// A result whose strings contain escapes and brackets that the tokenizer must not mistake
+/// for the end of a value
+const TRICKY_RESULT: &str = r#"{
+  "test": "/dom/\"quoted\"\\path\u00e9.html",
+  "status": "OK",
+  "duration": 1,
+  "message": "] } , [ { \\\" still a string ] }",
+  "subtests": [
+    {"name": "a [b {c]} \"}\" \\", "status": "FAIL", "message": "\\\\\"]"},
+    {"name": "\u005d\u007d", "status": "PASS"}
+  ]
+}"#;
+
+fn report(results: &[&str]) -> String {
+    format!(
+        r#"{{"time_start": 1, "run_info": {RUN_INFO}, "results": [{}], "time_end": 2}}"#,
+        results.join(",")
+    )
+}
+
+/// Read a report with a tiny buffer so that tokens are split across refills
+fn stream_with_capacity(json: &str, capacity: usize) -> Result<WptReport, wptreport::Error> {
+    WptReportStream::new(BufReader::with_capacity(capacity, json.as_bytes()))?.into_report()
+}
+
+#[test]
+fn escapes_and_brackets_in_strings_across_refills() {
+    let json = report(&[TRICKY_RESULT, TRICKY_RESULT]);
+    let expected = serde_json::to_value(json.parse::<WptReport>().unwrap()).unwrap();
+    assert_eq!(
+        expected["results"][0]["test"],
+        "/dom/\"quoted\"\\pathé.html"
+    );
+    assert_eq!(expected["results"][0]["subtests"][1]["name"], "]}");
+
+    for capacity in 1..=16 {
+        let streamed = stream_with_capacity(&json, capacity).unwrap();
+        let streamed = serde_json::to_value(streamed).unwrap();
+        assert_eq!(streamed, expected, "buffer capacity {capacity}");
+    }
+}
+
+#[test]
+fn header_fields_after_results() {
+    let json = format!(
+        r#"{{"results": [{TRICKY_RESULT}], "run_info": {RUN_INFO}, "time_start": 1, "time_end": 2}}"#
+    );
+    let mut stream = WptReportStream::new(json.as_bytes()).unwrap();
+    assert!(stream.run_info().is_none());
+    assert_eq!(stream.time_start(), None);
+
+    assert_eq!(
+        stream.next().unwrap().unwrap().test,
+        "/dom/\"quoted\"\\pathé.html"
+    );
+    assert!(stream.next().is_none());
+    assert_eq!(stream.run_info().unwrap().product, "servo");
+    assert_eq!(stream.time_start(), Some(1));
+    assert_eq!(stream.time_end(), Some(2));
+
+    let header = stream.finish().unwrap();
+    assert_eq!((header.time_start, header.time_end), (1, 2));
+}
+
+#[test]
+fn empty_results() {
+    for results in ["[]", "[ ]", "[\n]"] {
+        let json = format!(
+            r#"{{"time_start": 1, "time_end": 2, "run_info": {RUN_INFO}, "results": {results}}}"#
+        );
+        let mut stream = WptReportStream::new(json.as_bytes()).unwrap();
+        assert!(stream.next().is_none());
+        let header = stream.finish().unwrap();
+        assert_eq!(header.run_info.product, "servo");
+    }
+}
+
+#[test]
+fn truncated_input_is_an_error() {
+    let json = report(&[TRICKY_RESULT]);
+    // The document is only complete once the closing brace has been read
+    for len in 0..json.len() {
+        let Some(prefix) = json.get(..len) else {
+            continue;
+        };
+        let result = stream_with_capacity(prefix, 4);
+        assert!(result.is_err(), "prefix of {len} bytes parsed: {prefix}");
+    }
+    assert!(stream_with_capacity(&json, 4).is_ok());
+}
+
+#[test]
+fn streamed_report_matches_parsed_report() {
+    let json = report(&[
+        TRICKY_RESULT,
+        r#"{"test": "/a.html", "status": "PASS", "duration": 3}"#,
+    ]);
+    let parsed: Value = serde_json::to_value(json.parse::<WptReport>().unwrap()).unwrap();
+    let streamed = WptReportStream::new(json.as_bytes()).unwrap().into_report();
+    assert_eq!(serde_json::to_value(streamed.unwrap()).unwrap(), parsed);
+}
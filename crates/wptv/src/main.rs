@@ -1,4 +1,3 @@
-// pub mod wpt_fyi_api;
 pub mod components;
 pub mod pages;
 
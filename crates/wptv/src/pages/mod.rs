@@ -1,2 +1,2 @@
-// pub mod wpt_fyi;
 pub mod home;
+pub mod wpt_fyi;
@@ -1,14 +1,13 @@
 use dioxus::prelude::*;
-use crate::wpt_fyi_api::{get_latest_runs, get_run_data};
+use wptreport::wpt_fyi_api::WptFyiClient;
 
 #[component]
 pub fn WptFyiPage() -> Element {
     let runs = use_resource(move || async move {
-        let runs = get_latest_runs().await?;
-        let run_ids : Vec<_> = runs.iter().map(|run| run.id).collect();
-        dbg!(&run_ids);
-        let results = get_run_data(&run_ids).await;
-        results
+        let client = WptFyiClient::new();
+        let runs = client.get_latest_runs().await?;
+        let run_ids: Vec<_> = runs.iter().map(|run| run.id).collect();
+        client.get_run_data(&run_ids).await
     });
 
     rsx! {
@@ -28,4 +27,4 @@ pub fn WptFyiPage() -> Element {
             "Loading..."
         }
     }
-}
\ No newline at end of file
+}